@@ -0,0 +1,167 @@
+use std::{any::Any, fmt::Debug};
+
+/// An event scheduled for an [`AudioNodeProcessor`].
+///
+/// [`AudioNodeProcessor`]: crate::node::AudioNodeProcessor
+#[derive(Debug)]
+pub struct NodeEvent {
+    /// The time of this event on the frame clock (see
+    /// [`ProcInfo::clock_frames`]).
+    ///
+    /// Events which were scheduled in the past are delivered at the
+    /// start of the next process cycle with this set to the first frame
+    /// of that cycle, so this is never less than
+    /// [`ProcInfo::clock_frames`].
+    ///
+    /// [`ProcInfo::clock_frames`]: crate::node::ProcInfo::clock_frames
+    pub clock_frame: u64,
+    /// The type of event.
+    pub event: NodeEventType,
+}
+
+impl NodeEvent {
+    /// The offset of this event in frames from the start of the process
+    /// cycle which starts at `clock_frames`.
+    pub fn frame_offset(&self, clock_frames: u64) -> usize {
+        self.clock_frame.saturating_sub(clock_frames) as usize
+    }
+}
+
+/// The type of a [`NodeEvent`].
+pub enum NodeEventType {
+    /// Start or resume playback.
+    Play,
+    /// Pause playback.
+    Pause,
+    /// Stop playback and return to the start.
+    Stop,
+    /// A custom event which the node processor can downcast.
+    ///
+    /// Once processed, the event is sent back to the main thread to be
+    /// dropped so that no deallocations happen in the audio thread.
+    Custom(Box<dyn Any + Send>),
+}
+
+impl Debug for NodeEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Play => write!(f, "Play"),
+            Self::Pause => write!(f, "Pause"),
+            Self::Stop => write!(f, "Stop"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// When a scheduled [`NodeEvent`] should occur.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventTime {
+    /// At the start of the next process cycle.
+    Immediate,
+    /// At the given frame on the frame clock.
+    ClockFrames(u64),
+    /// At the given time in seconds on the frame clock (the number of
+    /// frames processed divided by the sample rate).
+    ClockSecs(f64),
+}
+
+impl EventTime {
+    /// Convert this time to a frame on the frame clock.
+    pub fn to_clock_frame(&self, sample_rate: u32) -> u64 {
+        match self {
+            Self::Immediate => 0,
+            Self::ClockFrames(frame) => *frame,
+            Self::ClockSecs(secs) => (secs.max(0.0) * f64::from(sample_rate)).round() as u64,
+        }
+    }
+}
+
+/// An event sent from an [`AudioNodeProcessor`] to the main thread.
+///
+/// [`AudioNodeProcessor`]: crate::node::AudioNodeProcessor
+pub enum ProcessorEvent {
+    /// A sample has finished playing.
+    SampleFinished,
+    /// Playback has wrapped around to the start of the loop range.
+    LoopWrapped,
+    /// A reading from a meter on the given channel.
+    MeterValue { channel: usize, value: f32 },
+    /// An error occurred in the processor.
+    Error(&'static str),
+    /// A custom event which can be downcast on the main thread.
+    ///
+    /// Note that creating this event allocates, so consider using one of
+    /// the other variants if possible.
+    Custom(Box<dyn Any + Send>),
+}
+
+impl Debug for ProcessorEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SampleFinished => write!(f, "SampleFinished"),
+            Self::LoopWrapped => write!(f, "LoopWrapped"),
+            Self::MeterValue { channel, value } => f
+                .debug_struct("MeterValue")
+                .field("channel", channel)
+                .field("value", value)
+                .finish(),
+            Self::Error(e) => f.debug_tuple("Error").field(e).finish(),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// A queue of events to send from an [`AudioNodeProcessor`] to the main
+/// thread (see [`ProcInfo::out_events`]).
+///
+/// This has a fixed capacity so that pushing an event never allocates.
+///
+/// [`AudioNodeProcessor`]: crate::node::AudioNodeProcessor
+/// [`ProcInfo::out_events`]: crate::node::ProcInfo::out_events
+#[derive(Debug)]
+pub struct ProcessorEventQueue {
+    events: Vec<ProcessorEvent>,
+    max_len: usize,
+}
+
+impl ProcessorEventQueue {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: Vec::with_capacity(capacity),
+            max_len: capacity,
+        }
+    }
+
+    /// Limit the number of events which can be pushed onto the queue (i.e.
+    /// to the number of free slots in the channel the events are sent on).
+    ///
+    /// This is clamped to the capacity of the queue.
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len.min(self.events.capacity());
+    }
+
+    /// Push an event onto the queue.
+    ///
+    /// If the queue is full, then the event is returned as an error.
+    pub fn push(&mut self, event: ProcessorEvent) -> Result<(), ProcessorEvent> {
+        if self.events.len() >= self.max_len {
+            return Err(event);
+        }
+
+        self.events.push(event);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Remove all events from the queue.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, ProcessorEvent> {
+        self.events.drain(..)
+    }
+}
@@ -1,3 +1,4 @@
+pub mod event;
 pub mod node;
 pub mod param;
 pub mod sample_resource;
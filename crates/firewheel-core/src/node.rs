@@ -1,13 +1,40 @@
 use downcast_rs::Downcast;
 use std::{any::Any, error::Error};
 
-use crate::SilenceMask;
+use crate::{
+    event::{NodeEvent, ProcessorEventQueue},
+    param::info::{ParamID, ParamInfo},
+    SilenceMask,
+};
 
 pub trait AudioNode: 'static + Downcast {
     fn debug_name(&self) -> &'static str;
 
     fn info(&self) -> AudioNodeInfo;
 
+    /// A description of the parameters on this node.
+    ///
+    /// By default this node has no parameters.
+    fn params(&self) -> Vec<ParamInfo> {
+        Vec::new()
+    }
+
+    /// Set the value of the parameter with the given ID.
+    ///
+    /// The value is already clamped to the range in the [`ParamInfo`]
+    /// of the parameter when this is called through the audio graph.
+    #[allow(unused)]
+    fn set_param(&mut self, id: ParamID, val: f32) {}
+
+    /// Get the current value of the parameter with the given ID.
+    ///
+    /// Returns `None` if this node does not have a parameter with the
+    /// given ID.
+    #[allow(unused)]
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        None
+    }
+
     /// Activate the audio node for processing.
     fn activate(
         &mut self,
@@ -76,6 +103,34 @@ pub struct AudioNodeInfo {
     ///
     /// By default this is set to `false`.
     pub updates: bool,
+
+    /// Whether or not the block should be split at the boundaries of
+    /// scheduled events.
+    ///
+    /// If this is `true`, then [`AudioNodeProcessor::process`] is called
+    /// separately for each group of events in a block, so that every event
+    /// in [`ProcInfo::events`] occurs on the first frame of the call. This
+    /// makes it easy to process events sample-accurately at the cost of
+    /// some performance overhead.
+    ///
+    /// By default this is set to `false`.
+    pub split_at_events: bool,
+
+    /// Whether or not this node always produces silence when all of its
+    /// inputs are silent.
+    ///
+    /// If this is `true`, then the audio graph will skip calling
+    /// [`AudioNodeProcessor::process`] in any block where all of the inputs
+    /// are silent, and the outputs will be marked as silent instead. Any
+    /// events scheduled for this node are delayed until the next time it
+    /// is processed.
+    ///
+    /// Only set this if the node has no "tail" (i.e. a delay or a reverb
+    /// which continues to output sound after the input becomes silent),
+    /// and if the node has at least one input.
+    ///
+    /// By default this is set to `false`.
+    pub silent_when_inputs_silent: bool,
 }
 
 impl Default for AudioNodeInfo {
@@ -86,6 +141,8 @@ impl Default for AudioNodeInfo {
             num_min_supported_outputs: 0,
             num_max_supported_outputs: 0,
             updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: false,
         }
     }
 }
@@ -113,6 +170,28 @@ pub struct ProcInfo<'a> {
     /// Flags indicating the current status of the audio stream
     pub stream_status: StreamStatus,
 
+    /// The value of the frame clock at the first frame in this process
+    /// cycle. The frame clock counts the number of frames that have been
+    /// processed since the stream was started.
+    pub clock_frames: u64,
+
+    /// The events scheduled for this node which occur in this process
+    /// cycle, sorted by time.
+    ///
+    /// Use [`NodeEvent::frame_offset`] to get the offset of an event in
+    /// this block.
+    pub events: &'a mut [NodeEvent],
+
+    /// A queue of events to send to the main thread (i.e. to notify that
+    /// a sample has finished playing).
+    ///
+    /// The events are tagged with the ID of this node and returned from
+    /// the context's `update` method.
+    ///
+    /// If the channel to the main thread is full, then pushing an event
+    /// fails and the event is returned to the processor.
+    pub out_events: &'a mut ProcessorEventQueue,
+
     /// A global user-defined context
     pub cx: &'a mut Box<dyn Any + Send>,
 }
@@ -128,5 +207,9 @@ bitflags::bitflags! {
         /// The output buffer ran low, likely producing a break in the
         /// output sound.
         const OUTPUT_UNDERFLOW = 0b10;
+
+        /// The input buffer ran low, so some frames of silence were
+        /// inserted into the input.
+        const INPUT_UNDERFLOW = 0b100;
     }
 }
@@ -0,0 +1,585 @@
+use std::error::Error;
+use std::fmt;
+use std::ops;
+use std::slice;
+
+use crate::event::EventTime;
+
+/// The shape of an automation ramp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AutomationCurve {
+    /// Move towards the target value at a constant rate.
+    Linear,
+    /// Move towards the target value by a constant ratio per sample. This
+    /// sounds natural for gain and frequency parameters.
+    ///
+    /// If the start and target values are not both non-zero with the same
+    /// sign, then a linear ramp is used instead.
+    Exponential,
+    /// A cubic bezier curve going from `(0, 0)` to `(1, 1)` with the
+    /// control points `(x1, y1)` and `(x2, y2)`, where `x` is the
+    /// normalized time and `y` is the normalized progress towards the
+    /// target value. This is the same as the CSS `cubic-bezier()` easing
+    /// function.
+    ///
+    /// `x1` and `x2` are clamped to the range `[0.0, 1.0]`.
+    CubicBezier { x1: f32, y1: f32, x2: f32, y2: f32 },
+}
+
+impl AutomationCurve {
+    /// An ease-in-out bezier curve.
+    pub const EASE_IN_OUT: Self = Self::CubicBezier {
+        x1: 0.42,
+        y1: 0.0,
+        x2: 0.58,
+        y2: 1.0,
+    };
+
+    /// Get the normalized progress towards the target value at the
+    /// normalized time `t` in the range `[0.0, 1.0]`.
+    ///
+    /// For [`AutomationCurve::Exponential`] this returns the linear
+    /// progress, since the exponential curve depends on the start and
+    /// target values.
+    pub fn progress(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match *self {
+            Self::Linear | Self::Exponential => t,
+            Self::CubicBezier { x1, y1, x2, y2 } => {
+                let x1 = x1.clamp(0.0, 1.0);
+                let x2 = x2.clamp(0.0, 1.0);
+
+                let s = solve_bezier_x(t, x1, x2);
+                bezier(s, y1, y2)
+            }
+        }
+    }
+}
+
+/// A 1D cubic bezier from `0` to `1` with the control points `p1` and `p2`.
+#[inline]
+fn bezier(s: f32, p1: f32, p2: f32) -> f32 {
+    let inv_s = 1.0 - s;
+    3.0 * inv_s * inv_s * s * p1 + 3.0 * inv_s * s * s * p2 + s * s * s
+}
+
+#[inline]
+fn bezier_derivative(s: f32, p1: f32, p2: f32) -> f32 {
+    let inv_s = 1.0 - s;
+    3.0 * inv_s * inv_s * p1 + 6.0 * inv_s * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+}
+
+/// Find the curve parameter `s` where the x coordinate of the curve
+/// equals `x`.
+fn solve_bezier_x(x: f32, x1: f32, x2: f32) -> f32 {
+    const EPSILON: f32 = 0.00001;
+
+    // Newton's method converges very quickly in most cases.
+    let mut s = x;
+    for _ in 0..8 {
+        let err = bezier(s, x1, x2) - x;
+        if err.abs() < EPSILON {
+            return s;
+        }
+
+        let d = bezier_derivative(s, x1, x2);
+        if d.abs() < EPSILON {
+            break;
+        }
+
+        s -= err / d;
+    }
+
+    // Fall back to bisection. Since `x1` and `x2` are in the range
+    // `[0.0, 1.0]`, x is monotonic over `s`.
+    let mut low = 0.0;
+    let mut high = 1.0;
+    s = x;
+    for _ in 0..32 {
+        let err = bezier(s, x1, x2) - x;
+        if err.abs() < EPSILON {
+            break;
+        }
+
+        if err > 0.0 {
+            high = s;
+        } else {
+            low = s;
+        }
+        s = (low + high) * 0.5;
+    }
+
+    s
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ramp {
+    start_frame: u64,
+    end_frame: u64,
+    target: f32,
+    curve: AutomationCurve,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ActiveRamp {
+    ramp: Ramp,
+    start_value: f32,
+    /// The ratio of the target value to the start value, if this is an
+    /// exponential ramp.
+    exp_ratio: Option<f32>,
+    duration_recip: f64,
+}
+
+impl ActiveRamp {
+    fn new(ramp: Ramp, start_value: f32) -> Self {
+        let exp_ratio = if ramp.curve == AutomationCurve::Exponential
+            && start_value != 0.0
+            && ramp.target != 0.0
+            && start_value.is_sign_positive() == ramp.target.is_sign_positive()
+        {
+            Some(ramp.target / start_value)
+        } else {
+            None
+        };
+
+        Self {
+            ramp,
+            start_value,
+            exp_ratio,
+            duration_recip: ((ramp.end_frame - ramp.start_frame) as f64).recip(),
+        }
+    }
+
+    fn value_at(&self, frame: u64) -> f32 {
+        if frame >= self.ramp.end_frame {
+            return self.ramp.target;
+        }
+
+        let t = (frame.saturating_sub(self.ramp.start_frame) as f64 * self.duration_recip) as f32;
+
+        if let Some(ratio) = self.exp_ratio {
+            self.start_value * ratio.powf(t)
+        } else {
+            self.start_value + (self.ramp.target - self.start_value) * self.ramp.curve.progress(t)
+        }
+    }
+}
+
+enum AutomationMsg {
+    Ramp(Ramp),
+    CancelRamps,
+}
+
+/// Create a new automated parameter.
+///
+/// The [`AutomationSender`] is used on the main thread to schedule ramps,
+/// and the [`AutomatedParam`] is used in the [`AudioNodeProcessor`] to
+/// produce the automated values.
+///
+/// * `val` - The initial value
+/// * `sample_rate` - The sampling rate
+/// * `max_block_frames` - The maximum number of frames that can
+///   appear in a processing block.
+/// * `capacity` - The maximum number of ramps that can be scheduled
+///   at once.
+///
+/// [`AudioNodeProcessor`]: crate::node::AudioNodeProcessor
+pub fn automated_param(
+    val: f32,
+    sample_rate: u32,
+    max_block_frames: usize,
+    capacity: usize,
+) -> (AutomationSender, AutomatedParam) {
+    let (to_processor_tx, from_sender_rx) = rtrb::RingBuffer::<AutomationMsg>::new(capacity);
+
+    (
+        AutomationSender {
+            to_processor_tx,
+            sample_rate,
+        },
+        AutomatedParam {
+            output: vec![val; max_block_frames],
+            value: val,
+            output_is_constant: true,
+            active_ramp: None,
+            pending_ramps: Vec::with_capacity(capacity),
+            from_sender_rx,
+        },
+    )
+}
+
+/// Schedules automation ramps for an [`AutomatedParam`] from the main
+/// thread.
+pub struct AutomationSender {
+    to_processor_tx: rtrb::Producer<AutomationMsg>,
+    sample_rate: u32,
+}
+
+impl AutomationSender {
+    /// Schedule a ramp from the value of the parameter at `start` to the
+    /// `target` value at `end`.
+    ///
+    /// If another ramp is still running at `start`, then that ramp is
+    /// stopped and the new ramp starts from its current value. If `end`
+    /// is not after `start`, then the parameter jumps to the target value
+    /// at `start`.
+    ///
+    /// This will return an error if the message queue is full.
+    pub fn ramp_to(
+        &mut self,
+        target: f32,
+        start: EventTime,
+        end: EventTime,
+        curve: AutomationCurve,
+    ) -> Result<(), AutomationQueueFullError> {
+        let start_frame = start.to_clock_frame(self.sample_rate);
+        let end_frame = end.to_clock_frame(self.sample_rate).max(start_frame);
+
+        self.to_processor_tx
+            .push(AutomationMsg::Ramp(Ramp {
+                start_frame,
+                end_frame,
+                target,
+                curve,
+            }))
+            .map_err(|_| AutomationQueueFullError)
+    }
+
+    /// Set the parameter to the given value at the given time.
+    ///
+    /// This will return an error if the message queue is full.
+    pub fn set_value_at(
+        &mut self,
+        val: f32,
+        time: EventTime,
+    ) -> Result<(), AutomationQueueFullError> {
+        self.ramp_to(val, time, time, AutomationCurve::Linear)
+    }
+
+    /// Cancel the current ramp and all scheduled ramps. The parameter
+    /// will hold its current value.
+    ///
+    /// This will return an error if the message queue is full.
+    pub fn cancel_ramps(&mut self) -> Result<(), AutomationQueueFullError> {
+        self.to_processor_tx
+            .push(AutomationMsg::CancelRamps)
+            .map_err(|_| AutomationQueueFullError)
+    }
+}
+
+impl fmt::Debug for AutomationSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutomationSender")
+            .field("sample_rate", &self.sample_rate)
+            .finish()
+    }
+}
+
+/// The message queue of an [`AutomationSender`] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutomationQueueFullError;
+
+impl Error for AutomationQueueFullError {}
+
+impl fmt::Display for AutomationQueueFullError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The automation message queue is full")
+    }
+}
+
+/// The output of an [`AutomatedParam`]
+pub struct AutomationOutput<'a> {
+    pub values: &'a [f32],
+    /// Whether or not a ramp was running in this block. If this is
+    /// `false`, then all values are the same.
+    pub is_automating: bool,
+}
+
+impl<'a, I> ops::Index<I> for AutomationOutput<'a>
+where
+    I: slice::SliceIndex<[f32]>,
+{
+    type Output = I::Output;
+
+    #[inline(always)]
+    fn index(&self, idx: I) -> &I::Output {
+        &self.values[idx]
+    }
+}
+
+/// A parameter which follows the ramps scheduled by an
+/// [`AutomationSender`].
+///
+/// This does not allocate after it has been created.
+pub struct AutomatedParam {
+    output: Vec<f32>,
+    value: f32,
+    output_is_constant: bool,
+
+    active_ramp: Option<ActiveRamp>,
+    /// The scheduled ramps sorted by start time.
+    pending_ramps: Vec<Ramp>,
+
+    from_sender_rx: rtrb::Consumer<AutomationMsg>,
+}
+
+impl AutomatedParam {
+    /// Produce the values of the parameter for the block starting at
+    /// `clock_frames` on the frame clock (see [`ProcInfo::clock_frames`]).
+    ///
+    /// [`ProcInfo::clock_frames`]: crate::node::ProcInfo::clock_frames
+    pub fn process(&mut self, clock_frames: u64, frames: usize) -> AutomationOutput<'_> {
+        self.poll_messages();
+
+        let frames = frames.min(self.output.len());
+
+        if self.active_ramp.is_none()
+            && self
+                .pending_ramps
+                .first()
+                .map(|r| r.start_frame >= clock_frames + frames as u64)
+                .unwrap_or(true)
+        {
+            // Nothing is happening in this block.
+            if !self.output_is_constant {
+                self.output.fill(self.value);
+                self.output_is_constant = true;
+            }
+
+            return AutomationOutput {
+                values: &self.output[..frames],
+                is_automating: false,
+            };
+        }
+
+        self.output_is_constant = false;
+
+        let mut i = 0;
+        while i < frames {
+            let frame = clock_frames + i as u64;
+
+            while let Some(ramp) = self.pending_ramps.first() {
+                if ramp.start_frame > frame {
+                    break;
+                }
+
+                let ramp = self.pending_ramps.remove(0);
+                self.active_ramp = Some(ActiveRamp::new(ramp, self.value));
+            }
+
+            let segment_end = self
+                .pending_ramps
+                .first()
+                .map(|r| ((r.start_frame - clock_frames) as usize).min(frames))
+                .unwrap_or(frames);
+
+            if let Some(ramp) = &self.active_ramp {
+                for (j, out) in self.output[i..segment_end].iter_mut().enumerate() {
+                    *out = ramp.value_at(frame + j as u64);
+                }
+                self.value = self.output[segment_end - 1];
+
+                if clock_frames + segment_end as u64 >= ramp.ramp.end_frame {
+                    self.value = ramp.ramp.target;
+                    self.active_ramp = None;
+                }
+            } else {
+                self.output[i..segment_end].fill(self.value);
+            }
+
+            i = segment_end;
+        }
+
+        AutomationOutput {
+            values: &self.output[..frames],
+            is_automating: true,
+        }
+    }
+
+    /// The most recently-processed value.
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Returns `true` if a ramp is currently running or scheduled.
+    pub fn is_automating(&self) -> bool {
+        self.active_ramp.is_some() || !self.pending_ramps.is_empty()
+    }
+
+    /// Cancel all ramps and set the parameter to the given value.
+    pub fn reset(&mut self, val: f32) {
+        self.active_ramp = None;
+        self.pending_ramps.clear();
+        self.value = val;
+        self.output.fill(val);
+        self.output_is_constant = true;
+    }
+
+    /// The maximum number of frames tha can appear in a single processing block.
+    pub fn max_block_frames(&self) -> usize {
+        self.output.len()
+    }
+
+    fn poll_messages(&mut self) {
+        while let Ok(msg) = self.from_sender_rx.pop() {
+            match msg {
+                AutomationMsg::Ramp(ramp) => {
+                    if self.pending_ramps.len() == self.pending_ramps.capacity() {
+                        // Don't allocate in the audio thread. If too many
+                        // ramps are scheduled at once, then the newest ones
+                        // are discarded.
+                        continue;
+                    }
+
+                    // Keep the ramps sorted by start time. Ramps with the
+                    // same start time stay in the order they were sent.
+                    let i = self
+                        .pending_ramps
+                        .iter()
+                        .position(|r| r.start_frame > ramp.start_frame)
+                        .unwrap_or(self.pending_ramps.len());
+                    self.pending_ramps.insert(i, ramp);
+                }
+                AutomationMsg::CancelRamps => {
+                    self.active_ramp = None;
+                    self.pending_ramps.clear();
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AutomatedParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutomatedParam")
+            .field("value", &self.value)
+            .field("max_block_frames", &self.max_block_frames())
+            .field("active_ramp", &self.active_ramp)
+            .field("pending_ramps", &self.pending_ramps)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_ramp() {
+        let (mut sender, mut param) = automated_param(0.0, 100, 16, 8);
+
+        sender
+            .ramp_to(
+                1.0,
+                EventTime::ClockFrames(4),
+                EventTime::ClockFrames(12),
+                AutomationCurve::Linear,
+            )
+            .unwrap();
+
+        let out = param.process(0, 16);
+        assert!(out.is_automating);
+        assert_eq!(&out[..4], &[0.0; 4]);
+        assert_eq!(out[4], 0.0);
+        assert_eq!(out[8], 0.5);
+        assert_eq!(&out[12..], &[1.0; 4]);
+
+        let out = param.process(16, 16);
+        assert!(!out.is_automating);
+        assert_eq!(out.values, &[1.0; 16]);
+    }
+
+    #[test]
+    fn ramp_across_blocks() {
+        let (mut sender, mut param) = automated_param(1.0, 100, 8, 8);
+
+        sender
+            .ramp_to(
+                4.0,
+                EventTime::Immediate,
+                EventTime::ClockFrames(16),
+                AutomationCurve::Exponential,
+            )
+            .unwrap();
+
+        param.process(0, 8);
+        assert!((param.value() - 2.0f32.powf(7.0 / 8.0)).abs() < 0.0001);
+
+        let out = param.process(8, 8);
+        assert!((out[0] - 2.0).abs() < 0.0001);
+        assert_eq!(param.value(), 4.0);
+    }
+
+    #[test]
+    fn new_ramp_starts_from_current_value() {
+        let (mut sender, mut param) = automated_param(0.0, 100, 8, 8);
+
+        sender
+            .ramp_to(
+                8.0,
+                EventTime::Immediate,
+                EventTime::ClockFrames(8),
+                AutomationCurve::Linear,
+            )
+            .unwrap();
+        sender
+            .ramp_to(
+                0.0,
+                EventTime::ClockFrames(4),
+                EventTime::ClockFrames(8),
+                AutomationCurve::Linear,
+            )
+            .unwrap();
+
+        let out = param.process(0, 8);
+        assert_eq!(out.values, &[0.0, 1.0, 2.0, 3.0, 3.0, 2.25, 1.5, 0.75]);
+        assert_eq!(param.value(), 0.0);
+    }
+
+    #[test]
+    fn cancel_ramps_holds_value() {
+        let (mut sender, mut param) = automated_param(0.0, 100, 8, 8);
+
+        sender
+            .ramp_to(
+                1.0,
+                EventTime::Immediate,
+                EventTime::ClockFrames(16),
+                AutomationCurve::EASE_IN_OUT,
+            )
+            .unwrap();
+
+        param.process(0, 8);
+        let value = param.value();
+        assert!(value > 0.0 && value < 1.0);
+
+        sender.cancel_ramps().unwrap();
+
+        let out = param.process(8, 8);
+        assert!(!out.is_automating);
+        assert_eq!(out.values, &[value; 8]);
+    }
+
+    #[test]
+    fn bezier_curve() {
+        let linear = AutomationCurve::CubicBezier {
+            x1: 0.25,
+            y1: 0.25,
+            x2: 0.75,
+            y2: 0.75,
+        };
+        let ease = AutomationCurve::EASE_IN_OUT;
+
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert!((linear.progress(t) - t).abs() < 0.001);
+        }
+
+        assert_eq!(ease.progress(0.0), 0.0);
+        assert!((ease.progress(0.5) - 0.5).abs() < 0.001);
+        assert!(ease.progress(0.1) < 0.1);
+        assert!(ease.progress(0.9) > 0.9);
+        assert!((ease.progress(1.0) - 1.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,119 @@
+use super::{
+    range::{LinearRange, NormToFreqRange, NormToPowRange},
+    smoother::SmootherConfig,
+};
+
+/// The ID of a parameter on an [`AudioNode`]. This is unique per node
+/// type.
+///
+/// [`AudioNode`]: crate::node::AudioNode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ParamID(pub u32);
+
+/// The unit of a parameter value
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParamUnit {
+    /// A value with no unit
+    #[default]
+    Generic,
+    /// A percentage, where `100.0` is 100%
+    Percent,
+    /// Decibels
+    Decibels,
+    /// Hertz
+    Hz,
+    /// Seconds
+    Seconds,
+    /// An on/off toggle, where a value `>= 0.5` means on
+    Toggle,
+}
+
+/// The range of a parameter value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamRange {
+    /// A range with a linear mapping
+    Linear(LinearRange),
+    /// A frequency range with a logarithmic mapping
+    Freq(NormToFreqRange),
+    /// A range with a power curve mapping
+    Pow(NormToPowRange),
+}
+
+impl ParamRange {
+    /// Clamp the value to this range.
+    pub fn clamp(&self, val: f32) -> f32 {
+        match self {
+            Self::Linear(r) => r.clamp(val),
+            Self::Freq(r) => val.clamp(r.min_hz(), r.max_hz()),
+            Self::Pow(r) => val.clamp(r.min(), r.max()),
+        }
+    }
+
+    /// Map the value to the corresponding normalized value in the range
+    /// `[0.0, 1.0]` (i.e. for use in a slider).
+    pub fn normalize(&self, val: f32) -> f32 {
+        match self {
+            Self::Linear(r) => {
+                if r.max == r.min {
+                    0.0
+                } else {
+                    ((val - r.min) / (r.max - r.min)).clamp(0.0, 1.0)
+                }
+            }
+            Self::Freq(r) => r.to_normalized(val),
+            Self::Pow(r) => r.to_normalized(val),
+        }
+    }
+
+    /// Map the normalized value in the range `[0.0, 1.0]` to the
+    /// corresponding value in this range.
+    pub fn denormalize(&self, normalized: f32) -> f32 {
+        match self {
+            Self::Linear(r) => r.min + normalized.clamp(0.0, 1.0) * (r.max - r.min),
+            Self::Freq(r) => r.to_hz(normalized),
+            Self::Pow(r) => r.to_dsp(normalized),
+        }
+    }
+}
+
+/// A description of a parameter on an [`AudioNode`]
+///
+/// [`AudioNode`]: crate::node::AudioNode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamInfo {
+    /// The ID of this parameter
+    pub id: ParamID,
+    /// The display name of this parameter
+    pub name: &'static str,
+    /// The range of the parameter value
+    pub range: ParamRange,
+    /// The default value
+    pub default: f32,
+    /// The unit of the parameter value
+    pub unit: ParamUnit,
+    /// The configuration of the smoothing applied to this parameter, or
+    /// `None` if changes to this parameter are not smoothed.
+    pub smoothing: Option<SmootherConfig>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_roundtrip() {
+        let ranges = [
+            ParamRange::Linear(LinearRange::new(-100.0, 24.0)),
+            ParamRange::Freq(NormToFreqRange::new(20.0, 20_000.0)),
+            ParamRange::Pow(NormToPowRange::new(0.0, 10.0, 2.0)),
+        ];
+
+        for range in ranges {
+            for i in 0..=10 {
+                let normalized = i as f32 / 10.0;
+                let val = range.denormalize(normalized);
+                assert!((range.normalize(val) - normalized).abs() < 0.0001);
+            }
+        }
+    }
+}
@@ -83,6 +83,20 @@ impl NormToFreqRange {
 
         2.0f32.powf((normalized * self.range) + self.min_log2)
     }
+
+    /// Convert the frequency value in hz to the corresponding normalized
+    /// value in the range `[0.0, 1.0]`.
+    pub fn to_normalized(&self, hz: f32) -> f32 {
+        if hz <= self.min_hz {
+            return 0.0;
+        }
+
+        if hz >= self.max_hz {
+            return 1.0;
+        }
+
+        (hz.log2() - self.min_log2) / self.range
+    }
 }
 
 /// A parameter range that takes a normalized value in the range `[0.0, 1.0]`
@@ -122,4 +136,18 @@ impl NormToPowRange {
 
         normalized.powf(self.exponent) * (self.max - self.min) + self.min
     }
+
+    /// Convert the value to the corresponding normalized value in the
+    /// range `[0.0, 1.0]`.
+    pub fn to_normalized(&self, val: f32) -> f32 {
+        if val <= self.min {
+            return 0.0;
+        }
+
+        if val >= self.max {
+            return 1.0;
+        }
+
+        ((val - self.min) / (self.max - self.min)).powf(self.exponent.recip())
+    }
 }
@@ -1,4 +1,8 @@
-use std::{num::NonZeroUsize, ops::Range, sync::Arc};
+use std::{
+    num::{NonZeroU32, NonZeroUsize},
+    ops::Range,
+    sync::Arc,
+};
 
 /// A resource of audio samples.
 pub trait SampleResource: Send + 'static {
@@ -9,6 +13,14 @@ pub trait SampleResource: Send + 'static {
     /// in a single channel).
     fn len_frames(&self) -> u64;
 
+    /// The sample rate of this resource in Hz.
+    ///
+    /// If this returns `None` (the default), then the resource is assumed
+    /// to have the same sample rate as the audio stream.
+    fn sample_rate(&self) -> Option<NonZeroU32> {
+        None
+    }
+
     /// Fill the given buffers with audio data starting from the given
     /// starting frame in the resource.
     ///
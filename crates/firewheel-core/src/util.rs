@@ -55,12 +55,6 @@ pub fn deinterleave<'a>(
             return silence_mask;
         };
 
-        if calculate_silence_mask && i < 64 {
-            if ch.iter().find(|&&s| s != 0.0).is_none() {
-                silence_mask.set_channel(i, true);
-            }
-        }
-
         for (input, output) in interleaved
             .iter()
             .skip(i)
@@ -70,6 +64,12 @@ pub fn deinterleave<'a>(
             *output = *input;
         }
 
+        if calculate_silence_mask && i < 64 {
+            if ch.iter().find(|&&s| s != 0.0).is_none() {
+                silence_mask.set_channel(i, true);
+            }
+        }
+
         i += 1;
     }
 
@@ -173,3 +173,43 @@ pub fn clear_all_outputs(
 
     *out_silence_mask = SilenceMask::new_all_silent(outputs.len());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deinterleave_silence_mask() {
+        let interleaved = [1.0, 0.0, 0.0, 0.0, 2.0, 0.0];
+
+        let mut ch0 = [0.0; 2];
+        let mut ch1 = [0.0; 2];
+        let mut ch2 = [0.0; 2];
+        let mut ch3 = [1.0; 2];
+
+        let silence_mask = deinterleave(
+            [
+                ch0.as_mut_slice(),
+                ch1.as_mut_slice(),
+                ch2.as_mut_slice(),
+                ch3.as_mut_slice(),
+            ]
+            .into_iter(),
+            &interleaved,
+            3,
+            true,
+        );
+
+        assert_eq!(ch0, [1.0, 0.0]);
+        assert_eq!(ch1, [0.0, 2.0]);
+        assert_eq!(ch2, [0.0, 0.0]);
+        assert_eq!(ch3, [0.0, 0.0]);
+
+        // The channels are checked after the samples are copied into them,
+        // and any extra channels are cleared.
+        assert!(!silence_mask.is_channel_silent(0));
+        assert!(!silence_mask.is_channel_silent(1));
+        assert!(silence_mask.is_channel_silent(2));
+        assert!(silence_mask.is_channel_silent(3));
+    }
+}
@@ -0,0 +1,219 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use firewheel_core::node::StreamStatus;
+
+use crate::{CpalInputConfig, StartStreamError, BUILD_STREAM_TIMEOUT, MSG_CHANNEL_CAPACITY};
+
+/// The number of frames the input ring buffer can hold on top of the
+/// requested latency.
+const EXTRA_CAPACITY_FRAMES: usize = 8192;
+
+pub(crate) struct InputStream {
+    pub stream: cpal::Stream,
+    pub from_err_rx: rtrb::Consumer<cpal::StreamError>,
+    pub receiver: InputReceiver,
+    pub device_name: String,
+    pub config: cpal::StreamConfig,
+}
+
+/// Start an input stream which runs at the same sample rate as the
+/// output stream.
+///
+/// `max_read_frames` is the maximum number of frames the output stream is
+/// expected to read in a single callback.
+pub(crate) fn start_input_stream(
+    host: &cpal::Host,
+    config: &CpalInputConfig,
+    sample_rate: u32,
+    max_read_frames: usize,
+) -> Result<InputStream, StartStreamError> {
+    let device = if let Some(input_device_name) = &config.device {
+        host.input_devices()?
+            .find(|d| {
+                if let Ok(name) = d.name() {
+                    &name == input_device_name
+                } else {
+                    false
+                }
+            })
+            .ok_or_else(|| StartStreamError::DeviceNotFound(input_device_name.clone()))?
+    } else {
+        host.default_input_device()
+            .ok_or(StartStreamError::DefaultInputDeviceNotFound)?
+    };
+
+    let default_config = device.default_input_config()?;
+
+    // Firewheel does not resample the input, so the input stream must
+    // run at the same sample rate as the output stream.
+    let stream_config = if default_config.sample_rate().0 == sample_rate {
+        default_config.config()
+    } else {
+        let Some(range) = device.supported_input_configs()?.find(|r| {
+            r.channels() == default_config.channels()
+                && r.min_sample_rate().0 <= sample_rate
+                && r.max_sample_rate().0 >= sample_rate
+        }) else {
+            return Err(StartStreamError::InputSampleRateNotSupported(sample_rate));
+        };
+
+        range
+            .with_sample_rate(cpal::SampleRate(sample_rate))
+            .config()
+    };
+
+    let num_channels = stream_config.channels as usize;
+    assert_ne!(num_channels, 0);
+
+    let device_name = device.name().unwrap_or_else(|_| "unkown".into());
+
+    log::info!(
+        "Starting input audio stream with device \"{}\" with configuration {:?}",
+        &device_name,
+        &stream_config
+    );
+
+    let latency_frames = ((config.latency_secs * f64::from(sample_rate)).round() as usize).max(1);
+
+    let capacity_frames = latency_frames * 2 + EXTRA_CAPACITY_FRAMES;
+    let (mut producer, consumer) = rtrb::RingBuffer::<f32>::new(capacity_frames * num_channels);
+    let (mut err_to_cx_tx, from_err_rx) =
+        rtrb::RingBuffer::<cpal::StreamError>::new(MSG_CHANNEL_CAPACITY);
+
+    let overflowed = Arc::new(AtomicBool::new(false));
+    let max_in_chunk_frames = Arc::new(AtomicUsize::new(0));
+
+    let receiver = InputReceiver {
+        consumer,
+        num_channels,
+        latency_frames,
+        max_in_chunk_frames: Arc::clone(&max_in_chunk_frames),
+        overflowed: Arc::clone(&overflowed),
+        primed: false,
+        // Reading more than the capacity of the ring buffer at once would
+        // always underflow anyway.
+        buffer: vec![0.0; max_read_frames.clamp(1, capacity_frames) * num_channels],
+    };
+
+    let stream = device.build_input_stream(
+        &stream_config,
+        move |input: &[f32], _info: &cpal::InputCallbackInfo| {
+            max_in_chunk_frames.fetch_max(input.len() / num_channels, Ordering::Relaxed);
+
+            // Only push whole frames so the channels never get out of sync.
+            let samples = input
+                .len()
+                .min(producer.slots() / num_channels * num_channels);
+            if samples < input.len() {
+                overflowed.store(true, Ordering::Relaxed);
+            }
+
+            if let Ok(chunk) = producer.write_chunk_uninit(samples) {
+                chunk.fill_from_iter(input[..samples].iter().copied());
+            }
+        },
+        move |err| {
+            let _ = err_to_cx_tx.push(err);
+        },
+        Some(BUILD_STREAM_TIMEOUT),
+    )?;
+
+    stream.play()?;
+
+    Ok(InputStream {
+        stream,
+        from_err_rx,
+        receiver,
+        device_name,
+        config: stream_config,
+    })
+}
+
+/// Receives captured audio from the input stream in the output stream's
+/// data callback.
+pub(crate) struct InputReceiver {
+    consumer: rtrb::Consumer<f32>,
+    num_channels: usize,
+    latency_frames: usize,
+    max_in_chunk_frames: Arc<AtomicUsize>,
+    overflowed: Arc<AtomicBool>,
+    primed: bool,
+    buffer: Vec<f32>,
+}
+
+impl InputReceiver {
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    /// The maximum number of frames which can be read at once
+    pub fn max_read_frames(&self) -> usize {
+        self.buffer.len() / self.num_channels
+    }
+
+    /// Read `frames` frames of interleaved input data.
+    ///
+    /// Frames that are not available yet are filled with silence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frames` is greater than [`InputReceiver::max_read_frames`].
+    pub fn read(&mut self, frames: usize, stream_status: &mut StreamStatus) -> &[f32] {
+        let num_channels = self.num_channels;
+        let buffer = &mut self.buffer[..frames * num_channels];
+
+        if self.overflowed.swap(false, Ordering::Relaxed) {
+            stream_status.insert(StreamStatus::INPUT_OVERFLOW);
+        }
+
+        let mut available_frames = self.consumer.slots() / num_channels;
+
+        if !self.primed {
+            // Wait until enough input has been buffered to absorb jitter
+            // between the input and output callbacks.
+            if available_frames < self.latency_frames {
+                buffer.fill(0.0);
+                return buffer;
+            }
+
+            self.primed = true;
+        }
+
+        // If the clock of the input device runs faster than the clock of
+        // the output device, then frames will keep piling up. Discard the
+        // excess so the latency does not keep growing.
+        let max_in_chunk_frames = self.max_in_chunk_frames.load(Ordering::Relaxed);
+        let max_buffered_frames = self.latency_frames + frames.max(max_in_chunk_frames) * 2;
+        if available_frames > max_buffered_frames {
+            let discard_frames = available_frames - (self.latency_frames + frames);
+
+            if let Ok(chunk) = self.consumer.read_chunk(discard_frames * num_channels) {
+                chunk.commit_all();
+                available_frames -= discard_frames;
+            }
+        }
+
+        let read_frames = available_frames.min(frames);
+        if let Ok(chunk) = self.consumer.read_chunk(read_frames * num_channels) {
+            let (s1, s2) = chunk.as_slices();
+            buffer[..s1.len()].copy_from_slice(s1);
+            buffer[s1.len()..s1.len() + s2.len()].copy_from_slice(s2);
+            chunk.commit_all();
+        }
+
+        if read_frames < frames {
+            // The clock of the input device runs slower than the clock of
+            // the output device (or the input stream stalled). Fill the rest
+            // with silence and wait for the buffer to fill back up.
+            buffer[read_frames * num_channels..].fill(0.0);
+            stream_status.insert(StreamStatus::INPUT_UNDERFLOW);
+            self.primed = false;
+        }
+
+        buffer
+    }
+}
@@ -1,118 +1,229 @@
-use std::{any::Any, fmt::Debug, time::Duration};
+use std::time::Duration;
+
+mod input;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use firewheel_core::node::StreamStatus;
+use firewheel_dummy::{DummyBackend, DummyConfig};
 use firewheel_graph::{
-    backend::DeviceInfo,
-    graph::{AudioGraph, AudioGraphConfig},
+    backend::{AudioBackend, DeviceInfo, StreamInfo},
     processor::{FirewheelProcessor, FirewheelProcessorStatus},
-    FirewheelGraphCtx, UpdateStatus,
+    FirewheelCtx,
 };
 
 const BUILD_STREAM_TIMEOUT: Duration = Duration::from_secs(5);
 const MSG_CHANNEL_CAPACITY: usize = 4;
+const DEFAULT_INPUT_LATENCY_SECS: f64 = 0.01;
+const DEFAULT_MAX_BLOCK_FRAMES: usize = 1024;
 
-struct ActiveState {
-    _stream: cpal::Stream,
-    _to_stream_tx: rtrb::Producer<CtxToStreamMsg>,
-    from_err_rx: rtrb::Consumer<cpal::StreamError>,
-    out_device_name: String,
-    config: cpal::StreamConfig,
+/// The configuration of an audio stream started by a [`CpalBackend`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpalConfig {
+    /// The name of the output device to use.
+    ///
+    /// Set to `None` to use the default output device.
+    ///
+    /// By default this is set to `None`.
+    pub output_device: Option<String>,
+    /// The requested configuration of the output stream.
+    ///
+    /// By default the device's default configuration is used.
+    pub stream: CpalStreamConfig,
+    /// The configuration of the input stream.
+    ///
+    /// Set to `None` to not open an input stream.
+    ///
+    /// By default this is set to `None`.
+    pub input: Option<CpalInputConfig>,
+    /// Whether or not to fall back to the default device and then to a
+    /// dummy stream if the requested device could not be started.
+    ///
+    /// By default this is set to `true`.
+    pub fallback: bool,
+}
+
+impl Default for CpalConfig {
+    fn default() -> Self {
+        Self {
+            output_device: None,
+            stream: CpalStreamConfig::default(),
+            input: None,
+            fallback: true,
+        }
+    }
 }
 
-pub struct FirewheelCpalCtx {
-    cx: FirewheelGraphCtx,
-    active_state: Option<ActiveState>,
+/// The requested configuration of a cpal output stream.
+///
+/// If the device does not support a requested value, then the closest
+/// supported value (or the device's default) is used instead. The
+/// configuration that was actually chosen can be retrieved with
+/// [`CpalBackend::stream_config`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpalStreamConfig {
+    /// The requested sample rate.
+    ///
+    /// Set to `None` to use the device's default sample rate.
+    ///
+    /// By default this is set to `None`.
+    pub sample_rate: Option<u32>,
+    /// The requested buffer size in frames.
+    ///
+    /// Set to `None` to use the device's default buffer size.
+    ///
+    /// By default this is set to `None`.
+    pub buffer_size: Option<u32>,
+    /// The requested number of output channels.
+    ///
+    /// Set to `None` to use the device's default number of channels.
+    ///
+    /// By default this is set to `None`.
+    pub num_out_channels: Option<u16>,
 }
 
-impl FirewheelCpalCtx {
-    pub fn new(graph_config: AudioGraphConfig) -> Self {
+/// The configuration of the input stream started by a [`CpalBackend`].
+///
+/// The captured audio is sent to the graph input node, so make sure to
+/// set [`AudioGraphConfig::num_graph_inputs`] accordingly.
+///
+/// [`AudioGraphConfig::num_graph_inputs`]: firewheel_graph::graph::AudioGraphConfig::num_graph_inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpalInputConfig {
+    /// The name of the input device to use.
+    ///
+    /// Set to `None` to use the default input device.
+    ///
+    /// By default this is set to `None`.
+    pub device: Option<String>,
+    /// The amount of input audio (in seconds) to buffer before it is sent
+    /// to the graph. This absorbs jitter and clock drift between the input
+    /// and output devices.
+    ///
+    /// By default this is set to `0.01`.
+    pub latency_secs: f64,
+    /// Whether or not to continue with only an output stream if the input
+    /// stream could not be started.
+    ///
+    /// By default this is set to `true`.
+    pub fallback: bool,
+}
+
+impl Default for CpalInputConfig {
+    fn default() -> Self {
         Self {
-            cx: FirewheelGraphCtx::new(graph_config),
-            active_state: None,
+            device: None,
+            latency_secs: DEFAULT_INPUT_LATENCY_SECS,
+            fallback: true,
         }
     }
+}
 
-    pub fn graph(&self) -> &AudioGraph {
-        &self.cx.graph
-    }
+enum StreamHandle {
+    Cpal {
+        _stream: cpal::Stream,
+        to_stream_tx: rtrb::Producer<CtxToStreamMsg>,
+        from_err_rx: rtrb::Consumer<cpal::StreamError>,
+        config: cpal::StreamConfig,
+        input: Option<InputHandle>,
+    },
+    Dummy(DummyBackend),
+}
 
-    pub fn graph_mut(&mut self) -> &mut AudioGraph {
-        &mut self.cx.graph
-    }
+struct InputHandle {
+    _stream: cpal::Stream,
+    from_err_rx: rtrb::Consumer<cpal::StreamError>,
+    config: cpal::StreamConfig,
+}
 
-    pub fn available_output_devices(&self) -> Vec<DeviceInfo> {
-        let mut devices = Vec::with_capacity(16);
+/// An [`AudioBackend`] which uses cpal.
+pub struct CpalBackend {
+    stream: StreamHandle,
+    /// The name of the default output device at the time the stream was
+    /// started.
+    default_device_name: Option<String>,
+    /// Whether or not the stream should follow changes to the default
+    /// output device.
+    follows_default_device: bool,
+}
 
-        let host = cpal::default_host();
+impl CpalBackend {
+    /// Returns `true` if the stream is running on a dummy stream because
+    /// no audio device could be used.
+    pub fn is_using_dummy_stream(&self) -> bool {
+        matches!(&self.stream, StreamHandle::Dummy(_))
+    }
 
-        let default_device_name = if let Some(default_device) = host.default_output_device() {
-            match default_device.name() {
-                Ok(n) => Some(n),
-                Err(e) => {
-                    log::warn!("Failed to get name of default audio output device: {}", e);
-                    None
-                }
-            }
+    /// Get the configuration of the cpal output stream.
+    ///
+    /// Returns `None` if the backend is running on a dummy stream.
+    pub fn stream_config(&self) -> Option<&cpal::StreamConfig> {
+        if let StreamHandle::Cpal { config, .. } = &self.stream {
+            Some(config)
         } else {
             None
-        };
+        }
+    }
 
-        match host.output_devices() {
-            Ok(output_devices) => {
-                for device in output_devices {
-                    let Ok(name) = device.name() else {
-                        continue;
-                    };
+    /// Get the configuration of the cpal input stream.
+    ///
+    /// Returns `None` if there is no input stream.
+    pub fn input_stream_config(&self) -> Option<&cpal::StreamConfig> {
+        if let StreamHandle::Cpal {
+            input: Some(input), ..
+        } = &self.stream
+        {
+            Some(&input.config)
+        } else {
+            None
+        }
+    }
 
-                    let is_default = if let Some(default_device_name) = &default_device_name {
-                        &name == default_device_name
-                    } else {
-                        false
-                    };
-
-                    let default_out_config = match device.default_output_config() {
-                        Ok(c) => c,
-                        Err(e) => {
-                            if is_default {
-                                log::warn!("Failed to get default config for the default audio output device: {}", e);
-                            }
-                            continue;
-                        }
-                    };
+    /// Start a dummy stream which discards its output.
+    fn start_dummy_stream(
+        default_device_name: Option<String>,
+    ) -> Result<(Self, StreamInfo), StartStreamError> {
+        let (backend, stream_info) = DummyBackend::start_stream(DummyConfig::default())
+            .map_err(StartStreamError::DummyStreamError)?;
+
+        Ok((
+            Self {
+                stream: StreamHandle::Dummy(backend),
+                default_device_name,
+                follows_default_device: true,
+            },
+            stream_info,
+        ))
+    }
+}
 
-                    devices.push(DeviceInfo {
-                        name,
-                        num_channels: default_out_config.channels(),
-                        is_default,
-                    })
-                }
-            }
-            Err(e) => {
-                log::error!("Failed to get output audio devices: {}", e);
-            }
-        }
+impl AudioBackend for CpalBackend {
+    type Config = CpalConfig;
+    type StartStreamError = StartStreamError;
+    type StreamError = cpal::StreamError;
 
-        devices
+    fn available_input_devices() -> Vec<DeviceInfo> {
+        available_devices(true)
     }
 
-    /// Activate the context and start the audio stream.
-    ///
-    /// Returns an error if the context is already active.
-    pub fn activate(
-        &mut self,
-        output_device: Option<&String>,
-        fallback: bool,
-        user_cx: Option<Box<dyn Any + Send>>,
-    ) -> Result<(), (ActivateError, Option<Box<dyn Any + Send>>)> {
-        if self.cx.is_activated() {
-            return Err((ActivateError::AlreadyActivated, user_cx));
-        }
+    fn available_output_devices() -> Vec<DeviceInfo> {
+        available_devices(false)
+    }
+
+    fn start_stream(config: Self::Config) -> Result<(Self, StreamInfo), Self::StartStreamError> {
+        let CpalConfig {
+            output_device,
+            stream: requested_config,
+            input,
+            fallback,
+        } = config;
 
         let host = cpal::default_host();
 
+        let default_device_name = host.default_output_device().and_then(|d| d.name().ok());
+
         let mut device = None;
-        if let Some(output_device_name) = output_device {
+        let mut follows_default_device = true;
+        if let Some(output_device_name) = &output_device {
             match host.output_devices() {
                 Ok(mut output_devices) => {
                     if let Some(d) = output_devices.find(|d| {
@@ -123,20 +234,18 @@ impl FirewheelCpalCtx {
                         }
                     }) {
                         device = Some(d);
+                        follows_default_device = false;
                     } else if fallback {
                         log::warn!("Could not find requested audio output device: {}. Falling back to default device...", &output_device_name);
                     } else {
-                        return Err((
-                            ActivateError::DeviceNotFound(output_device_name.clone()),
-                            user_cx,
-                        ));
+                        return Err(StartStreamError::DeviceNotFound(output_device_name.clone()));
                     }
                 }
                 Err(e) => {
                     if fallback {
                         log::error!("Failed to get output audio devices: {}. Falling back to default device...", e);
                     } else {
-                        return Err((e.into(), user_cx));
+                        return Err(e.into());
                     }
                 }
             }
@@ -146,17 +255,16 @@ impl FirewheelCpalCtx {
             let Some(default_device) = host.default_output_device() else {
                 if fallback {
                     log::error!("No default audio output device found. Falling back to dummy output device...");
-                    // TODO: Use dummy audio backend as fallback.
-                    todo!()
+                    return Self::start_dummy_stream(default_device_name);
                 } else {
-                    return Err((ActivateError::DefaultDeviceNotFound, user_cx));
+                    return Err(StartStreamError::DefaultDeviceNotFound);
                 }
             };
             device = Some(default_device);
         }
         let device = device.unwrap();
 
-        let config = match device.default_output_config() {
+        let config = match negotiate_output_config(&device, &requested_config) {
             Ok(c) => c,
             Err(e) => {
                 if fallback {
@@ -164,17 +272,13 @@ impl FirewheelCpalCtx {
                         "Failed to get default config for output audio device: {}. Falling back to dummy output device...",
                         e
                     );
-                    // TODO: Use dummy audio backend as fallback.
-                    todo!()
+                    return Self::start_dummy_stream(default_device_name);
                 } else {
-                    return Err((e.into(), user_cx));
+                    return Err(e.into());
                 }
             }
         };
 
-        let config = config.config();
-
-        let num_in_channels = 0;
         let num_out_channels = config.channels as usize;
 
         assert_ne!(num_out_channels, 0);
@@ -187,168 +291,411 @@ impl FirewheelCpalCtx {
             &config
         );
 
+        let input_stream = if let Some(input_config) = &input {
+            match input::start_input_stream(
+                &host,
+                input_config,
+                config.sample_rate.0,
+                max_callback_frames(&device, &config),
+            ) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    if input_config.fallback {
+                        log::error!(
+                            "Failed to start input audio stream: {}. Continuing without audio input...",
+                            e
+                        );
+                        None
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+        } else {
+            None
+        };
+
+        let (input_handle, input_receiver, in_device_name) = match input_stream {
+            Some(s) => (
+                Some(InputHandle {
+                    _stream: s.stream,
+                    from_err_rx: s.from_err_rx,
+                    config: s.config,
+                }),
+                Some(s.receiver),
+                Some(s.device_name),
+            ),
+            None => (None, None, None),
+        };
+
+        let mut config = config;
+        let mut built_stream = None;
+        while built_stream.is_none() {
+            let (to_stream_tx, from_ctx_rx) =
+                rtrb::RingBuffer::<CtxToStreamMsg>::new(MSG_CHANNEL_CAPACITY);
+            let (mut err_to_cx_tx, from_err_rx) =
+                rtrb::RingBuffer::<cpal::StreamError>::new(MSG_CHANNEL_CAPACITY);
+
+            let mut data_callback =
+                DataCallback::new(num_out_channels, from_ctx_rx, config.sample_rate.0);
+
+            match device.build_output_stream(
+                &config,
+                move |output: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                    data_callback.callback(output, info);
+                },
+                move |err| {
+                    let _ = err_to_cx_tx.push(err);
+                },
+                Some(BUILD_STREAM_TIMEOUT),
+            ) {
+                Ok(s) => built_stream = Some((s, to_stream_tx, from_err_rx)),
+                Err(e) => {
+                    if let cpal::BufferSize::Fixed(buffer_size) = config.buffer_size {
+                        // Not all backends report the supported buffer sizes, so
+                        // retry with the default buffer size.
+                        log::warn!("Failed to start output audio stream with a buffer size of {} frames: {}. Retrying with default buffer size...", buffer_size, e);
+                        config.buffer_size = cpal::BufferSize::Default;
+                    } else if fallback {
+                        log::error!("Failed to start output audio stream: {}. Falling back to dummy output device...", e);
+                        return Self::start_dummy_stream(default_device_name);
+                    } else {
+                        return Err(e.into());
+                    }
+                }
+            }
+        }
+        let (stream, mut to_stream_tx, from_err_rx) = built_stream.unwrap();
+
+        log::info!(
+            "Started output audio stream with device \"{}\" with configuration {:?}",
+            &out_device_name,
+            &config
+        );
+
         let max_block_frames = match config.buffer_size {
-            cpal::BufferSize::Default => 1024,
+            cpal::BufferSize::Default => DEFAULT_MAX_BLOCK_FRAMES,
             cpal::BufferSize::Fixed(f) => f as usize,
         };
 
-        let (mut to_stream_tx, from_ctx_rx) =
-            rtrb::RingBuffer::<CtxToStreamMsg>::new(MSG_CHANNEL_CAPACITY);
-        let (mut err_to_cx_tx, from_err_rx) =
-            rtrb::RingBuffer::<cpal::StreamError>::new(MSG_CHANNEL_CAPACITY);
+        let num_in_channels = input_receiver
+            .as_ref()
+            .map(|r| r.num_channels())
+            .unwrap_or(0);
 
-        let mut data_callback = DataCallback::new(
-            num_in_channels,
-            num_out_channels,
-            from_ctx_rx,
-            config.sample_rate.0,
-        );
+        if let Some(input_receiver) = input_receiver {
+            // The message channel was just created, so it can't be full.
+            let _ = to_stream_tx.push(CtxToStreamMsg::NewInput(input_receiver));
+        }
 
-        let stream = match device.build_output_stream(
-            &config,
-            move |output: &mut [f32], info: &cpal::OutputCallbackInfo| {
-                data_callback.callback(output, info);
-            },
-            move |err| {
-                let _ = err_to_cx_tx.push(err);
+        if let Err(e) = stream.play() {
+            if fallback {
+                log::error!(
+                    "Failed to play output audio stream: {}. Falling back to dummy output device...",
+                    e
+                );
+                return Self::start_dummy_stream(default_device_name);
+            } else {
+                return Err(e.into());
+            }
+        }
+
+        let stream_info = StreamInfo {
+            sample_rate: config.sample_rate.0,
+            max_block_frames,
+            num_stream_in_channels: num_in_channels,
+            num_stream_out_channels: num_out_channels,
+            input_device_name: in_device_name,
+            output_device_name: Some(out_device_name),
+        };
+
+        Ok((
+            Self {
+                stream: StreamHandle::Cpal {
+                    _stream: stream,
+                    to_stream_tx,
+                    from_err_rx,
+                    config,
+                    input: input_handle,
+                },
+                default_device_name,
+                follows_default_device,
             },
-            Some(BUILD_STREAM_TIMEOUT),
-        ) {
-            Ok(s) => s,
-            Err(e) => {
-                if fallback {
-                    log::error!("Failed to start output audio stream: {}. Falling back to dummy output device...", e);
-                    // TODO: Use dummy audio backend as fallback.
-                    todo!()
-                } else {
-                    return Err((e.into(), user_cx));
+            stream_info,
+        ))
+    }
+
+    fn set_processor(&mut self, processor: FirewheelProcessor) {
+        match &mut self.stream {
+            StreamHandle::Cpal { to_stream_tx, .. } => {
+                if to_stream_tx
+                    .push(CtxToStreamMsg::NewProcessor(processor))
+                    .is_err()
+                {
+                    log::error!(
+                        "Failed to send processor to audio stream: message channel is full"
+                    );
                 }
             }
-        };
+            StreamHandle::Dummy(backend) => backend.set_processor(processor),
+        }
+    }
 
-        if let Err(e) = stream.play() {
-            return Err((e.into(), user_cx));
+    fn stop_stream(&mut self) {
+        if let StreamHandle::Dummy(backend) = &mut self.stream {
+            backend.stop_stream();
         }
 
-        let user_cx = user_cx.unwrap_or(Box::new(()));
+        // The cpal stream is stopped once it is dropped.
+    }
 
-        let processor = self
-            .cx
-            .activate(
-                config.sample_rate.0,
-                num_in_channels,
-                num_out_channels,
-                max_block_frames,
-                user_cx,
-            )
-            .unwrap();
-
-        to_stream_tx
-            .push(CtxToStreamMsg::NewProcessor(processor))
-            .unwrap();
-
-        self.active_state = Some(ActiveState {
-            _stream: stream,
-            _to_stream_tx: to_stream_tx,
-            from_err_rx,
-            out_device_name,
-            config,
-        });
+    fn poll_for_errors(&mut self) -> Result<(), Self::StreamError> {
+        if let StreamHandle::Cpal {
+            from_err_rx, input, ..
+        } = &mut self.stream
+        {
+            if let Ok(e) = from_err_rx.pop() {
+                return Err(e);
+            }
+
+            if let Some(input) = input {
+                if let Ok(e) = input.from_err_rx.pop() {
+                    return Err(e);
+                }
+            }
+        }
 
         Ok(())
     }
 
-    /// Returns whether or not this context is currently activated.
-    pub fn is_activated(&self) -> bool {
-        self.cx.is_activated()
+    fn is_running(&self) -> bool {
+        match &self.stream {
+            StreamHandle::Cpal { .. } => true,
+            StreamHandle::Dummy(backend) => backend.is_running(),
+        }
     }
 
-    /// Get the name of the audio output device.
-    ///
-    /// Returns `None` if the context is not currently activated.
-    pub fn out_device_name(&self) -> Option<&str> {
-        self.active_state
-            .as_ref()
-            .map(|s| s.out_device_name.as_str())
+    fn default_device_changed(&mut self) -> bool {
+        if !self.follows_default_device {
+            return false;
+        }
+
+        let default_device_name = cpal::default_host()
+            .default_output_device()
+            .and_then(|d| d.name().ok());
+
+        default_device_name != self.default_device_name
     }
 
-    /// Get the current configuration of the audio stream.
-    ///
-    /// Returns `None` if the context is not currently activated.
-    pub fn stream_config(&self) -> Option<&cpal::StreamConfig> {
-        self.active_state.as_ref().map(|s| &s.config)
+    fn reconnect_config(config: &Self::Config) -> Self::Config {
+        // The requested devices may no longer exist, so reconnect to the
+        // default devices instead.
+        CpalConfig {
+            output_device: None,
+            input: config.input.as_ref().map(|input| CpalInputConfig {
+                device: None,
+                ..input.clone()
+            }),
+            ..config.clone()
+        }
     }
+}
 
-    /// Update the firewheel context.
-    ///
-    /// This must be called reguarly once the context has been activated
-    /// (i.e. once every frame).
-    pub fn update(&mut self) -> UpdateStatus {
-        if let Some(state) = &mut self.active_state {
-            if let Ok(e) = state.from_err_rx.pop() {
-                let user_cx = self.cx.deactivate(false);
-                self.active_state = None;
-
-                return UpdateStatus::Deactivated {
-                    error: Some(Box::new(e)),
-                    returned_user_cx: user_cx,
-                };
+/// A Firewheel context which runs the audio graph on a [`CpalBackend`].
+pub type FirewheelCpalCtx = FirewheelCtx<CpalBackend>;
+
+/// Find the supported output configuration which best matches the
+/// requested configuration.
+/// The maximum number of frames the output device may ask for in a single
+/// data callback with the given config.
+fn max_callback_frames(device: &cpal::Device, config: &cpal::StreamConfig) -> usize {
+    match config.buffer_size {
+        cpal::BufferSize::Fixed(f) => f as usize,
+        cpal::BufferSize::Default => match device.default_output_config() {
+            Ok(c) => match c.buffer_size() {
+                cpal::SupportedBufferSize::Range { max, .. } => *max as usize,
+                cpal::SupportedBufferSize::Unknown => DEFAULT_MAX_BLOCK_FRAMES,
+            },
+            Err(_) => DEFAULT_MAX_BLOCK_FRAMES,
+        },
+    }
+}
+
+fn negotiate_output_config(
+    device: &cpal::Device,
+    requested: &CpalStreamConfig,
+) -> Result<cpal::StreamConfig, cpal::DefaultStreamConfigError> {
+    let default_config = device.default_output_config()?;
+
+    if *requested == CpalStreamConfig::default() {
+        return Ok(default_config.config());
+    }
+
+    let supported_configs: Vec<cpal::SupportedStreamConfigRange> =
+        match device.supported_output_configs() {
+            Ok(c) => c.collect(),
+            Err(e) => {
+                log::warn!(
+                    "Failed to get supported configs for output audio device: {}",
+                    e
+                );
+                Vec::new()
+            }
+        };
+
+    let find_supported = |num_channels: u16, sample_rate: u32| {
+        supported_configs.iter().find(|c| {
+            c.channels() == num_channels
+                && c.min_sample_rate().0 <= sample_rate
+                && c.max_sample_rate().0 >= sample_rate
+        })
+    };
+
+    let mut num_channels = requested
+        .num_out_channels
+        .unwrap_or(default_config.channels());
+    let mut sample_rate = requested
+        .sample_rate
+        .unwrap_or(default_config.sample_rate().0);
+
+    let mut supported = find_supported(num_channels, sample_rate);
+
+    if supported.is_none() && num_channels != default_config.channels() {
+        log::warn!(
+            "Output audio device does not support {} channels. Falling back to default of {} channels...",
+            num_channels,
+            default_config.channels()
+        );
+        num_channels = default_config.channels();
+        supported = find_supported(num_channels, sample_rate);
+    }
+
+    if supported.is_none() && sample_rate != default_config.sample_rate().0 {
+        log::warn!(
+            "Output audio device does not support a sample rate of {}. Falling back to default sample rate of {}...",
+            sample_rate,
+            default_config.sample_rate().0
+        );
+        sample_rate = default_config.sample_rate().0;
+        supported = find_supported(num_channels, sample_rate);
+    }
+
+    let supported_buffer_size = match supported {
+        Some(c) => *c.buffer_size(),
+        None => {
+            num_channels = default_config.channels();
+            sample_rate = default_config.sample_rate().0;
+            *default_config.buffer_size()
+        }
+    };
+
+    let buffer_size = match (requested.buffer_size, supported_buffer_size) {
+        (None, _) => cpal::BufferSize::Default,
+        (Some(buffer_size), cpal::SupportedBufferSize::Range { min, max }) => {
+            let clamped = buffer_size.clamp(min, max);
+            if clamped != buffer_size {
+                log::warn!(
+                    "Output audio device does not support a buffer size of {} frames. Using {} frames instead...",
+                    buffer_size,
+                    clamped
+                );
             }
+            cpal::BufferSize::Fixed(clamped)
+        }
+        (Some(buffer_size), cpal::SupportedBufferSize::Unknown) => {
+            cpal::BufferSize::Fixed(buffer_size)
         }
+    };
 
-        match self.cx.update() {
-            UpdateStatus::Active { graph_error } => UpdateStatus::Active { graph_error },
-            UpdateStatus::Inactive => UpdateStatus::Inactive,
-            UpdateStatus::Deactivated {
-                returned_user_cx,
-                error,
-            } => {
-                if self.active_state.is_some() {
-                    self.active_state = None;
-                }
+    Ok(cpal::StreamConfig {
+        channels: num_channels,
+        sample_rate: cpal::SampleRate(sample_rate),
+        buffer_size,
+    })
+}
 
-                UpdateStatus::Deactivated {
-                    error,
-                    returned_user_cx,
-                }
+fn available_devices(input: bool) -> Vec<DeviceInfo> {
+    let mut devices = Vec::with_capacity(16);
+
+    let host = cpal::default_host();
+    let kind = if input { "input" } else { "output" };
+
+    let default_device = if input {
+        host.default_input_device()
+    } else {
+        host.default_output_device()
+    };
+
+    let default_device_name = if let Some(default_device) = default_device {
+        match default_device.name() {
+            Ok(n) => Some(n),
+            Err(e) => {
+                log::warn!("Failed to get name of default audio {} device: {}", kind, e);
+                None
             }
         }
-    }
+    } else {
+        None
+    };
+
+    let all_devices: Result<Box<dyn Iterator<Item = cpal::Device>>, cpal::DevicesError> = if input {
+        host.input_devices().map(|d| Box::new(d) as _)
+    } else {
+        host.output_devices().map(|d| Box::new(d) as _)
+    };
+
+    match all_devices {
+        Ok(all_devices) => {
+            for device in all_devices {
+                let Ok(name) = device.name() else {
+                    continue;
+                };
 
-    /// Deactivate the firewheel context and stop the audio stream.
-    ///
-    /// This will block the thread until either the processor has
-    /// been successfully dropped or a timeout has been reached.
-    ///
-    /// If the stream is still currently running, then the context
-    /// will attempt to cleanly deactivate the processor. If not,
-    /// then the context will wait for either the processor to be
-    /// dropped or a timeout being reached.
-    ///
-    /// If the context is already deactivated, then this will do
-    /// nothing and return `None`.
-    pub fn deactivate(&mut self) -> Option<Box<dyn Any + Send>> {
-        if self.cx.is_activated() {
-            let user_cx = self.cx.deactivate(self.active_state.is_some());
-            self.active_state = None;
-            user_cx
-        } else {
-            None
+                let is_default = if let Some(default_device_name) = &default_device_name {
+                    &name == default_device_name
+                } else {
+                    false
+                };
+
+                let default_config = if input {
+                    device.default_input_config()
+                } else {
+                    device.default_output_config()
+                };
+
+                let default_config = match default_config {
+                    Ok(c) => c,
+                    Err(e) => {
+                        if is_default {
+                            log::warn!(
+                                "Failed to get default config for the default audio {} device: {}",
+                                kind,
+                                e
+                            );
+                        }
+                        continue;
+                    }
+                };
+
+                devices.push(DeviceInfo {
+                    name,
+                    num_channels: default_config.channels(),
+                    is_default,
+                })
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to get {} audio devices: {}", kind, e);
         }
     }
-}
 
-// Implement Debug so `unwrap()` can be used.
-impl Debug for FirewheelCpalCtx {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "FirewheelCpalCtx")
-    }
+    devices
 }
 
 struct DataCallback {
-    num_in_channels: usize,
     num_out_channels: usize,
     from_ctx_rx: rtrb::Consumer<CtxToStreamMsg>,
+    input: Option<input::InputReceiver>,
     processor: Option<FirewheelProcessor>,
     sample_rate_recip: f64,
     first_stream_instant: Option<cpal::StreamInstant>,
@@ -358,15 +705,14 @@ struct DataCallback {
 
 impl DataCallback {
     fn new(
-        num_in_channels: usize,
         num_out_channels: usize,
         from_ctx_rx: rtrb::Consumer<CtxToStreamMsg>,
         sample_rate: u32,
     ) -> Self {
         Self {
-            num_in_channels,
             num_out_channels,
             from_ctx_rx,
+            input: None,
             processor: None,
             sample_rate_recip: f64::from(sample_rate).recip(),
             first_stream_instant: None,
@@ -377,8 +723,10 @@ impl DataCallback {
 
     fn callback(&mut self, output: &mut [f32], info: &cpal::OutputCallbackInfo) {
         while let Ok(msg) = self.from_ctx_rx.pop() {
-            let CtxToStreamMsg::NewProcessor(p) = msg;
-            self.processor = Some(p);
+            match msg {
+                CtxToStreamMsg::NewProcessor(p) => self.processor = Some(p),
+                CtxToStreamMsg::NewInput(r) => self.input = Some(r),
+            }
         }
 
         let frames = output.len() / self.num_out_channels;
@@ -418,63 +766,89 @@ impl DataCallback {
             (stream_time_secs, false)
         };
 
-        let mut drop_processor = false;
-        if let Some(processor) = &mut self.processor {
-            let mut stream_status = StreamStatus::empty();
+        let mut stream_status = StreamStatus::empty();
 
-            if underflow {
-                stream_status.insert(StreamStatus::OUTPUT_UNDERFLOW);
-            }
+        if underflow {
+            stream_status.insert(StreamStatus::OUTPUT_UNDERFLOW);
+        }
+
+        // Process in chunks which fit in the preallocated input buffer. This
+        // only results in more than one chunk if the output device asks for
+        // more frames than it reported.
+        let chunk_frames = self
+            .input
+            .as_ref()
+            .map(|input| input.max_read_frames())
+            .unwrap_or(frames);
+
+        let mut frames_processed = 0;
+        while frames_processed < frames {
+            let block_frames = (frames - frames_processed).min(chunk_frames);
+            let output = &mut output[frames_processed * self.num_out_channels
+                ..(frames_processed + block_frames) * self.num_out_channels];
+            let block_time_secs =
+                stream_time_secs + frames_processed as f64 * self.sample_rate_recip;
+            frames_processed += block_frames;
+
+            // Always drain the input buffer, even when there is no processor,
+            // so that stale input does not pile up.
+            let (input, num_in_channels) = if let Some(input) = &mut self.input {
+                let num_in_channels = input.num_channels();
+                (
+                    input.read(block_frames, &mut stream_status),
+                    num_in_channels,
+                )
+            } else {
+                (&[][..], 0)
+            };
+
+            let Some(processor) = &mut self.processor else {
+                output.fill(0.0);
+                continue;
+            };
 
             match processor.process_interleaved(
-                &[],
+                input,
                 output,
-                self.num_in_channels,
+                num_in_channels,
                 self.num_out_channels,
-                frames,
-                stream_time_secs,
+                block_frames,
+                block_time_secs,
                 stream_status,
             ) {
                 FirewheelProcessorStatus::Ok => {}
-                FirewheelProcessorStatus::DropProcessor => drop_processor = true,
+                FirewheelProcessorStatus::DropProcessor => self.processor = None,
             }
-        } else {
-            output.fill(0.0);
-            return;
-        }
-
-        if drop_processor {
-            self.processor = None;
         }
     }
 }
 
-impl Drop for FirewheelCpalCtx {
-    fn drop(&mut self) {
-        if self.cx.is_activated() {
-            self.cx.deactivate(self.active_state.is_some());
-        }
-    }
-}
 enum CtxToStreamMsg {
     NewProcessor(FirewheelProcessor),
+    NewInput(input::InputReceiver),
 }
 
-/// An error occured while trying to activate an [`InactiveFwCpalCtx`]
+/// An error occured while trying to start a cpal audio stream
 #[derive(Debug, thiserror::Error)]
-pub enum ActivateError {
-    #[error("The firewheel context is already activated")]
-    AlreadyActivated,
+pub enum StartStreamError {
     #[error("The requested audio device was not found: {0}")]
     DeviceNotFound(String),
     #[error("Could not get audio devices: {0}")]
     FailedToGetDevices(#[from] cpal::DevicesError),
     #[error("Failed to get default audio output device")]
     DefaultDeviceNotFound,
+    #[error("Failed to get default audio input device")]
+    DefaultInputDeviceNotFound,
     #[error("Failed to get audio device config: {0}")]
     FailedToGetConfig(#[from] cpal::DefaultStreamConfigError),
+    #[error("Failed to get supported audio device configs: {0}")]
+    FailedToGetSupportedConfigs(#[from] cpal::SupportedStreamConfigsError),
+    #[error("The audio input device does not support the sample rate of the output device: {0}")]
+    InputSampleRateNotSupported(u32),
     #[error("Failed to build audio stream: {0}")]
     BuildStreamError(#[from] cpal::BuildStreamError),
     #[error("Failed to play audio stream: {0}")]
     PlayStreamError(#[from] cpal::PlayStreamError),
+    #[error("Failed to start dummy audio stream: {0}")]
+    DummyStreamError(std::io::Error),
 }
@@ -0,0 +1,330 @@
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use firewheel_core::node::StreamStatus;
+use firewheel_graph::{
+    backend::{AudioBackend, DeviceInfo, StreamInfo},
+    processor::{FirewheelProcessor, FirewheelProcessorStatus},
+    FirewheelCtx,
+};
+
+/// The name reported for the dummy output device
+pub const DUMMY_DEVICE_NAME: &str = "dummy";
+
+/// The configuration of a dummy audio stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DummyConfig {
+    /// The simulated sample rate.
+    ///
+    /// By default this is set to `48000`.
+    pub sample_rate: u32,
+    /// The simulated number of output channels.
+    ///
+    /// By default this is set to `2`.
+    pub num_out_channels: usize,
+    /// The number of frames processed in each simulated callback.
+    ///
+    /// By default this is set to `1024`.
+    pub block_frames: usize,
+}
+
+impl Default for DummyConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 48_000,
+            num_out_channels: 2,
+            block_frames: 1024,
+        }
+    }
+}
+
+const MSG_CHANNEL_CAPACITY: usize = 4;
+
+/// An [`AudioBackend`] which runs a [`FirewheelProcessor`] on its own
+/// thread using a simulated realtime clock. The output of the processor
+/// is discarded.
+///
+/// This is useful when no audio device exists, such as on CI machines
+/// and headless servers.
+pub struct DummyBackend {
+    thread: Option<JoinHandle<()>>,
+    stop_signal: Arc<AtomicBool>,
+    to_stream_tx: rtrb::Producer<FirewheelProcessor>,
+    config: DummyConfig,
+}
+
+impl DummyBackend {
+    /// The configuration of this stream.
+    pub fn config(&self) -> &DummyConfig {
+        &self.config
+    }
+}
+
+impl AudioBackend for DummyBackend {
+    type Config = DummyConfig;
+    type StartStreamError = std::io::Error;
+    type StreamError = Infallible;
+
+    fn available_input_devices() -> Vec<DeviceInfo> {
+        Vec::new()
+    }
+
+    fn available_output_devices() -> Vec<DeviceInfo> {
+        vec![DeviceInfo {
+            name: DUMMY_DEVICE_NAME.into(),
+            num_channels: DummyConfig::default().num_out_channels as u16,
+            is_default: true,
+        }]
+    }
+
+    fn start_stream(config: Self::Config) -> Result<(Self, StreamInfo), Self::StartStreamError> {
+        assert_ne!(config.sample_rate, 0);
+        assert_ne!(config.num_out_channels, 0);
+        assert_ne!(config.block_frames, 0);
+
+        log::info!(
+            "Starting dummy audio stream with configuration {:?}",
+            &config
+        );
+
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let thread_stop_signal = Arc::clone(&stop_signal);
+
+        let (to_stream_tx, from_ctx_rx) =
+            rtrb::RingBuffer::<FirewheelProcessor>::new(MSG_CHANNEL_CAPACITY);
+
+        // TODO: Threads are not supported in WASM, so we will need to
+        // figure out something if that's the case.
+        let thread = std::thread::Builder::new()
+            .name("firewheel-dummy-stream".into())
+            .spawn(move || {
+                run_stream(from_ctx_rx, config, thread_stop_signal);
+            })?;
+
+        Ok((
+            Self {
+                thread: Some(thread),
+                stop_signal,
+                to_stream_tx,
+                config,
+            },
+            StreamInfo {
+                sample_rate: config.sample_rate,
+                max_block_frames: config.block_frames,
+                num_stream_in_channels: 0,
+                num_stream_out_channels: config.num_out_channels,
+                input_device_name: None,
+                output_device_name: Some(DUMMY_DEVICE_NAME.into()),
+            },
+        ))
+    }
+
+    fn set_processor(&mut self, processor: FirewheelProcessor) {
+        if self.to_stream_tx.push(processor).is_err() {
+            log::error!("Failed to send processor to dummy stream: message channel is full");
+        }
+    }
+
+    fn stop_stream(&mut self) {
+        self.stop_signal.store(true, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::error!("Firewheel dummy stream thread panicked");
+            }
+        }
+    }
+
+    fn poll_for_errors(&mut self) -> Result<(), Self::StreamError> {
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .map(|t| !t.is_finished())
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for DummyBackend {
+    fn drop(&mut self) {
+        self.stop_stream();
+    }
+}
+
+/// A simulated realtime clock which tells the stream thread when to
+/// process the next block
+struct SimulatedClock {
+    sample_rate: f64,
+    block_duration: Duration,
+    start: Instant,
+    frames_since_start: u64,
+}
+
+impl SimulatedClock {
+    fn new(config: &DummyConfig, now: Instant) -> Self {
+        let sample_rate = f64::from(config.sample_rate);
+
+        Self {
+            sample_rate,
+            block_duration: Duration::from_secs_f64(config.block_frames as f64 / sample_rate),
+            start: now,
+            frames_since_start: 0,
+        }
+    }
+
+    /// Returns how long to wait before processing the next block, and
+    /// whether or not an underflow occurred.
+    fn poll(&mut self, now: Instant) -> (Duration, bool) {
+        let deadline =
+            self.start + Duration::from_secs_f64(self.frames_since_start as f64 / self.sample_rate);
+
+        if now < deadline {
+            (deadline - now, false)
+        } else if now - deadline > self.block_duration {
+            // We have fallen behind by more than a block, which a real
+            // audio device would report as an underflow. Resync the clock
+            // instead of trying to catch up in a burst.
+            self.start = now;
+            self.frames_since_start = 0;
+            (Duration::ZERO, true)
+        } else {
+            (Duration::ZERO, false)
+        }
+    }
+
+    /// Advance the clock after a block has been processed.
+    fn advance(&mut self, frames: usize) {
+        self.frames_since_start += frames as u64;
+    }
+}
+
+fn run_stream(
+    mut from_ctx_rx: rtrb::Consumer<FirewheelProcessor>,
+    config: DummyConfig,
+    stop_signal: Arc<AtomicBool>,
+) {
+    let sample_rate = f64::from(config.sample_rate);
+
+    let mut output = vec![0.0; config.block_frames * config.num_out_channels];
+    let mut processor: Option<FirewheelProcessor> = None;
+
+    let mut clock = SimulatedClock::new(&config, Instant::now());
+    let mut frames_processed: u64 = 0;
+
+    while !stop_signal.load(Ordering::Relaxed) {
+        while let Ok(p) = from_ctx_rx.pop() {
+            processor = Some(p);
+        }
+
+        let mut stream_status = StreamStatus::empty();
+
+        let (wait, underflow) = clock.poll(Instant::now());
+        if underflow {
+            stream_status.insert(StreamStatus::OUTPUT_UNDERFLOW);
+        } else if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+
+        let stream_time_secs = frames_processed as f64 / sample_rate;
+
+        if let Some(p) = &mut processor {
+            match p.process_interleaved(
+                &[],
+                &mut output,
+                0,
+                config.num_out_channels,
+                config.block_frames,
+                stream_time_secs,
+                stream_status,
+            ) {
+                FirewheelProcessorStatus::Ok => {}
+                FirewheelProcessorStatus::DropProcessor => processor = None,
+            }
+        }
+
+        frames_processed += config.block_frames as u64;
+        clock.advance(config.block_frames);
+    }
+}
+
+/// A Firewheel context which runs the audio graph on a [`DummyBackend`].
+pub type FirewheelDummyCtx = FirewheelCtx<DummyBackend>;
+
+#[cfg(test)]
+mod tests {
+    use firewheel_graph::UpdateStatus;
+
+    use super::*;
+
+    #[test]
+    fn simulated_clock() {
+        let config = DummyConfig {
+            sample_rate: 1_000,
+            block_frames: 10,
+            ..Default::default()
+        };
+        let block = Duration::from_millis(10);
+
+        let start = Instant::now();
+        let mut clock = SimulatedClock::new(&config, start);
+
+        assert_eq!(clock.poll(start), (Duration::ZERO, false));
+        clock.advance(10);
+
+        // The next block is due one block after the start.
+        assert_eq!(clock.poll(start), (block, false));
+        assert_eq!(clock.poll(start + block / 2), (block / 2, false));
+
+        // Falling behind by less than a block is caught up without an
+        // underflow.
+        clock.advance(10);
+        assert_eq!(clock.poll(start + block * 2), (Duration::ZERO, false));
+        clock.advance(10);
+
+        // Falling behind by more than a block is an underflow, and the
+        // clock is resynced to the current time.
+        let now = start + block * 5;
+        assert_eq!(clock.poll(now), (Duration::ZERO, true));
+        clock.advance(10);
+        assert_eq!(clock.poll(now), (block, false));
+    }
+
+    #[test]
+    fn lifecycle() {
+        let mut cx = FirewheelDummyCtx::new(Default::default());
+
+        cx.activate(
+            DummyConfig {
+                block_frames: 64,
+                ..Default::default()
+            },
+            Some(Box::new(5u32)),
+        )
+        .unwrap();
+        assert!(cx.is_activated());
+
+        // Let the stream run for a few blocks.
+        let start = Instant::now();
+        while cx.clock_frames() < 64 * 4 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(matches!(cx.update(), UpdateStatus::Active { .. }));
+        assert!(cx.active_backend().unwrap().is_running());
+
+        let user_cx = cx.deactivate();
+        assert_eq!(*user_cx.unwrap().downcast::<u32>().unwrap(), 5);
+        assert!(!cx.is_activated());
+        assert!(cx.active_backend().is_none());
+    }
+}
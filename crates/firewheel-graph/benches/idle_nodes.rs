@@ -0,0 +1,131 @@
+//! Benchmarks processing a graph with a large pool of idle nodes (i.e.
+//! effect chains which currently have no sound playing through them).
+
+use std::error::Error;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use firewheel_core::node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, StreamStatus};
+use firewheel_graph::{
+    basic_nodes::{HardClipNode, VolumeNode},
+    graph::AudioGraphConfig,
+    processor::FirewheelProcessor,
+    FirewheelGraphCtx,
+};
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK_FRAMES: usize = 256;
+
+/// A wrapper which disables skipping the wrapped node when its inputs
+/// are silent.
+struct AlwaysProcess<N: AudioNode>(N);
+
+impl<N: AudioNode> AudioNode for AlwaysProcess<N> {
+    fn debug_name(&self) -> &'static str {
+        self.0.debug_name()
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            silent_when_inputs_silent: false,
+            ..self.0.info()
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn Error>> {
+        self.0
+            .activate(sample_rate, max_block_frames, num_inputs, num_outputs)
+    }
+}
+
+/// Build a graph with `num_chains` chains of `volume -> hard_clip` nodes
+/// which are all fed by the (silent) graph input.
+fn idle_graph(num_chains: usize, skip: bool) -> (FirewheelGraphCtx, FirewheelProcessor) {
+    let mut cx = FirewheelGraphCtx::new(AudioGraphConfig {
+        num_graph_inputs: 1,
+        initial_node_capacity: num_chains * 2 + 2,
+        initial_edge_capacity: num_chains * 2,
+        ..Default::default()
+    });
+
+    let graph_in = cx.graph.graph_in_node();
+
+    for _ in 0..num_chains {
+        let (volume, hard_clip) = if skip {
+            (
+                cx.graph.add_node(1, 1, VolumeNode::new(100.0)),
+                cx.graph.add_node(1, 1, HardClipNode::new(0.0)),
+            )
+        } else {
+            (
+                cx.graph.add_node(
+                    1,
+                    1,
+                    Box::new(AlwaysProcess(VolumeNode::new(100.0))) as Box<dyn AudioNode>,
+                ),
+                cx.graph.add_node(
+                    1,
+                    1,
+                    Box::new(AlwaysProcess(HardClipNode::new(0.0))) as Box<dyn AudioNode>,
+                ),
+            )
+        };
+
+        cx.graph.connect(graph_in, 0, volume, 0, false).unwrap();
+        cx.graph.connect(volume, 0, hard_clip, 0, false).unwrap();
+    }
+
+    let processor = cx
+        .activate(SAMPLE_RATE, 1, 2, BLOCK_FRAMES, Box::new(()))
+        .unwrap();
+    cx.update();
+
+    (cx, processor)
+}
+
+fn bench_idle_nodes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("idle_nodes");
+
+    let input = vec![0.0; BLOCK_FRAMES];
+    let mut output = vec![0.0; BLOCK_FRAMES * 2];
+
+    for num_chains in [128, 512] {
+        for skip in [false, true] {
+            let (_cx, mut processor) = idle_graph(num_chains, skip);
+
+            let name = if skip {
+                "skip_silent"
+            } else {
+                "always_process"
+            };
+
+            group.bench_with_input(
+                BenchmarkId::new(name, num_chains * 2),
+                &num_chains,
+                |b, _| {
+                    b.iter(|| {
+                        processor.process_interleaved(
+                            &input,
+                            &mut output,
+                            1,
+                            2,
+                            BLOCK_FRAMES,
+                            0.0,
+                            StreamStatus::empty(),
+                        )
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_idle_nodes);
+criterion_main!(benches);
@@ -1,6 +1,96 @@
+use std::error::Error;
+
+use crate::processor::FirewheelProcessor;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DeviceInfo {
     pub name: String,
     pub num_channels: u16,
     pub is_default: bool,
 }
+
+/// Information about a running audio stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub sample_rate: u32,
+    /// The maximum number of frames that can appear in a single process
+    /// cycle.
+    pub max_block_frames: usize,
+    pub num_stream_in_channels: usize,
+    pub num_stream_out_channels: usize,
+    /// The name of the input device, if there is one.
+    pub input_device_name: Option<String>,
+    /// The name of the output device, if there is one.
+    pub output_device_name: Option<String>,
+}
+
+/// The trait implemented by audio backends (i.e. cpal) which drive a
+/// [`FirewheelProcessor`].
+///
+/// A backend is used through [`FirewheelCtx`](crate::FirewheelCtx),
+/// which takes care of the lifecycle of the audio graph.
+pub trait AudioBackend: Sized {
+    /// The configuration used when starting a stream.
+    type Config: Default + Clone;
+
+    /// An error that can occur while starting a stream.
+    type StartStreamError: Error + 'static;
+
+    /// An error that can occur while a stream is running.
+    type StreamError: Error + 'static;
+
+    /// Get a list of the available audio input devices.
+    fn available_input_devices() -> Vec<DeviceInfo>;
+
+    /// Get a list of the available audio output devices.
+    fn available_output_devices() -> Vec<DeviceInfo>;
+
+    /// Start an audio stream with the given configuration.
+    ///
+    /// The stream should output silence until a processor is received
+    /// in [`AudioBackend::set_processor`].
+    fn start_stream(config: Self::Config) -> Result<(Self, StreamInfo), Self::StartStreamError>;
+
+    /// Send the processor to the audio thread.
+    ///
+    /// This is called once directly after the stream has started.
+    fn set_processor(&mut self, processor: FirewheelProcessor);
+
+    /// Stop the audio stream and drop the processor if the stream still
+    /// owns it.
+    ///
+    /// If the stream is running, then this is called after the context
+    /// has told the processor to stop. Otherwise (i.e. if the stream
+    /// stopped unexpectedly), this is called first, and the context relies
+    /// on it to drop the processor so that it does not have to wait for a
+    /// timeout.
+    ///
+    /// The backend is dropped directly after this is called, so the
+    /// processor must be dropped by then at the latest.
+    fn stop_stream(&mut self);
+
+    /// Returns an error if the stream has stopped unexpectedly.
+    ///
+    /// This is called once every time the context is updated.
+    fn poll_for_errors(&mut self) -> Result<(), Self::StreamError>;
+
+    /// Returns `true` if the stream is still running.
+    fn is_running(&self) -> bool;
+
+    /// Returns `true` if the system's default device has changed and the
+    /// stream should be restarted to follow it.
+    ///
+    /// This is polled periodically when auto-reconnect is enabled. By
+    /// default this returns `false`.
+    fn default_device_changed(&mut self) -> bool {
+        false
+    }
+
+    /// Get the configuration to use when restarting a stream with
+    /// auto-reconnect.
+    ///
+    /// By default the original configuration is used.
+    fn reconnect_config(config: &Self::Config) -> Self::Config {
+        config.clone()
+    }
+}
@@ -3,22 +3,39 @@ use std::sync::{
     Arc,
 };
 
-use firewheel_core::node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo};
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::{LinearRange, NormToFreqRange},
+    },
+};
 
 pub struct BeepTestNode {
     enabled: Arc<AtomicBool>,
-    freq_hz: f32,
-    gain: f32,
+    freq_hz: Arc<AtomicF32>,
+    gain: Arc<AtomicF32>,
+    gain_db: f32,
 }
 
 impl BeepTestNode {
+    /// The ID of the enabled parameter
+    pub const PARAM_ENABLED: ParamID = ParamID(0);
+    /// The ID of the frequency parameter in hz
+    pub const PARAM_FREQ: ParamID = ParamID(1);
+    /// The ID of the gain parameter in decibels
+    pub const PARAM_GAIN: ParamID = ParamID(2);
+
     pub fn new(freq_hz: f32, gain_db: f32, enabled: bool) -> Self {
-        let freq_hz = freq_hz.clamp(20.0, 20_000.0);
-        let gain = firewheel_core::util::db_to_gain_clamped_neg_100_db(gain_db).clamp(0.0, 1.0);
+        let gain_db = gain_db.clamp(-100.0, 0.0);
 
         Self {
-            freq_hz,
-            gain,
+            freq_hz: Arc::new(AtomicF32::new(freq_hz.clamp(20.0, 20_000.0))),
+            gain: Arc::new(AtomicF32::new(
+                firewheel_core::util::db_to_gain_clamped_neg_100_db(gain_db),
+            )),
+            gain_db,
             enabled: Arc::new(AtomicBool::new(enabled)),
         }
     }
@@ -30,6 +47,27 @@ impl BeepTestNode {
     pub fn set_enabled(&self, enabled: bool) {
         self.enabled.store(enabled, Ordering::Relaxed);
     }
+
+    pub fn freq_hz(&self) -> f32 {
+        self.freq_hz.load(Ordering::Relaxed)
+    }
+
+    pub fn set_freq_hz(&self, freq_hz: f32) {
+        self.freq_hz
+            .store(freq_hz.clamp(20.0, 20_000.0), Ordering::Relaxed);
+    }
+
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    pub fn set_gain_db(&mut self, gain_db: f32) {
+        self.gain_db = gain_db.clamp(-100.0, 0.0);
+        self.gain.store(
+            firewheel_core::util::db_to_gain_clamped_neg_100_db(self.gain_db),
+            Ordering::Relaxed,
+        );
+    }
 }
 
 impl AudioNode for BeepTestNode {
@@ -45,6 +83,53 @@ impl AudioNode for BeepTestNode {
         }
     }
 
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo {
+                id: Self::PARAM_ENABLED,
+                name: "enabled",
+                range: ParamRange::Linear(LinearRange::new(0.0, 1.0)),
+                default: 1.0,
+                unit: ParamUnit::Toggle,
+                smoothing: None,
+            },
+            ParamInfo {
+                id: Self::PARAM_FREQ,
+                name: "frequency",
+                range: ParamRange::Freq(NormToFreqRange::new(20.0, 20_000.0)),
+                default: 440.0,
+                unit: ParamUnit::Hz,
+                smoothing: None,
+            },
+            ParamInfo {
+                id: Self::PARAM_GAIN,
+                name: "gain",
+                range: ParamRange::Linear(LinearRange::new(-100.0, 0.0)),
+                default: -12.0,
+                unit: ParamUnit::Decibels,
+                smoothing: None,
+            },
+        ]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        match id {
+            Self::PARAM_ENABLED => self.set_enabled(val >= 0.5),
+            Self::PARAM_FREQ => self.set_freq_hz(val),
+            Self::PARAM_GAIN => self.set_gain_db(val),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        match id {
+            Self::PARAM_ENABLED => Some(if self.enabled() { 1.0 } else { 0.0 }),
+            Self::PARAM_FREQ => Some(self.freq_hz()),
+            Self::PARAM_GAIN => Some(self.gain_db),
+            _ => None,
+        }
+    }
+
     fn activate(
         &mut self,
         sample_rate: u32,
@@ -54,18 +139,20 @@ impl AudioNode for BeepTestNode {
     ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
         Ok(Box::new(BeepTestProcessor {
             enabled: Arc::clone(&self.enabled),
+            freq_hz: Arc::clone(&self.freq_hz),
+            gain: Arc::clone(&self.gain),
             phasor: 0.0,
-            phasor_inc: self.freq_hz / sample_rate as f32,
-            gain: self.gain,
+            sample_rate_recip: (sample_rate as f32).recip(),
         }))
     }
 }
 
 struct BeepTestProcessor {
     enabled: Arc<AtomicBool>,
+    freq_hz: Arc<AtomicF32>,
+    gain: Arc<AtomicF32>,
     phasor: f32,
-    phasor_inc: f32,
-    gain: f32,
+    sample_rate_recip: f32,
 }
 
 impl AudioNodeProcessor for BeepTestProcessor {
@@ -85,9 +172,12 @@ impl AudioNodeProcessor for BeepTestProcessor {
             return;
         }
 
+        let phasor_inc = self.freq_hz.load(Ordering::Relaxed) * self.sample_rate_recip;
+        let gain = self.gain.load(Ordering::Relaxed);
+
         for s in out1[..frames].iter_mut() {
-            *s = (self.phasor * std::f32::consts::TAU).sin() * self.gain;
-            self.phasor = (self.phasor + self.phasor_inc).fract();
+            *s = (self.phasor * std::f32::consts::TAU).sin() * gain;
+            self.phasor = (self.phasor + phasor_inc).fract();
         }
 
         for out2 in outputs.iter_mut() {
@@ -0,0 +1,773 @@
+use std::sync::Arc;
+
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    sample_resource::SampleResource,
+};
+use realfft::{num_complex::Complex, ComplexToReal, RealFftPlanner, RealToComplex};
+
+const CHANNEL_CAPACITY: usize = 16;
+
+/// The latency mode of a [`ConvolutionNode`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConvolutionLatency {
+    /// The output is delayed by the partition size (the maximum block
+    /// size of the stream rounded up to a power of two).
+    #[default]
+    Block,
+    /// The first partition of the impulse response is convolved directly
+    /// in the time domain, so that there is no added latency.
+    ///
+    /// This uses more CPU than [`ConvolutionLatency::Block`], especially
+    /// with large block sizes.
+    Zero,
+}
+
+/// An error while setting the impulse response of a [`ConvolutionNode`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvolutionError {
+    /// The impulse response must have either 1 (mono), 2 (stereo), or 4
+    /// (true stereo) channels.
+    InvalidNumChannels(usize),
+    /// The impulse response has no frames.
+    Empty,
+    /// The message channel to the audio thread is full.
+    MessageChannelFull,
+}
+
+impl std::error::Error for ConvolutionError {}
+
+impl std::fmt::Display for ConvolutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidNumChannels(n) => write!(
+                f,
+                "An impulse response must have either 1, 2, or 4 channels, got {}",
+                n
+            ),
+            Self::Empty => write!(f, "The impulse response is empty"),
+            Self::MessageChannelFull => {
+                write!(f, "The message channel to the audio thread is full")
+            }
+        }
+    }
+}
+
+struct NodeToProcessorMsg {
+    engine: Box<ConvolutionEngine>,
+    crossfade_frames: usize,
+}
+
+enum ProcessorToNodeMsg {
+    ReturnEngine(#[allow(unused)] Box<ConvolutionEngine>),
+}
+
+struct ActiveState {
+    to_processor_tx: rtrb::Producer<NodeToProcessorMsg>,
+    from_processor_rx: rtrb::Consumer<ProcessorToNodeMsg>,
+    forward_fft: Arc<dyn RealToComplex<f32>>,
+    partition_len: usize,
+    num_inputs: usize,
+    num_outputs: usize,
+    sample_rate: u32,
+}
+
+/// A node which convolves its inputs with an impulse response (i.e. for
+/// convolution reverb)
+///
+/// The impulse response can have 1 channel (mono), 2 channels (stereo),
+/// or 4 channels (true stereo, in the order left-to-left, left-to-right,
+/// right-to-left, right-to-right). The output is fully wet.
+///
+/// The impulse response is partitioned and transformed on the main
+/// thread, so no allocations happen in the audio thread when it is
+/// changed.
+pub struct ConvolutionNode {
+    active_state: Option<ActiveState>,
+    impulse_response: Option<Vec<Vec<f32>>>,
+    latency: ConvolutionLatency,
+}
+
+impl ConvolutionNode {
+    pub fn new(latency: ConvolutionLatency) -> Self {
+        Self {
+            active_state: None,
+            impulse_response: None,
+            latency,
+        }
+    }
+
+    pub fn latency(&self) -> ConvolutionLatency {
+        self.latency
+    }
+
+    /// The latency of the node in frames, or `None` if the node is not
+    /// activated.
+    pub fn latency_frames(&self) -> Option<usize> {
+        self.active_state.as_ref().map(|state| match self.latency {
+            ConvolutionLatency::Block => state.partition_len,
+            ConvolutionLatency::Zero => 0,
+        })
+    }
+
+    /// Set the impulse response.
+    ///
+    /// If the node is activated, then the output crossfades from the old
+    /// impulse response to the new one over `crossfade_secs` seconds.
+    pub fn set_impulse_response<S: SampleResource>(
+        &mut self,
+        resource: &S,
+        crossfade_secs: f32,
+    ) -> Result<(), ConvolutionError> {
+        let num_channels = resource.num_channels().get();
+        if !matches!(num_channels, 1 | 2 | 4) {
+            return Err(ConvolutionError::InvalidNumChannels(num_channels));
+        }
+
+        let len_frames = resource.len_frames() as usize;
+        if len_frames == 0 {
+            return Err(ConvolutionError::Empty);
+        }
+
+        let mut impulse_response: Vec<Vec<f32>> = vec![vec![0.0; len_frames]; num_channels];
+        let mut buffers: Vec<&mut [f32]> = impulse_response
+            .iter_mut()
+            .map(|ch| ch.as_mut_slice())
+            .collect();
+        resource.fill_buffers(&mut buffers, 0..len_frames, 0);
+
+        if let Some(state) = &mut self.active_state {
+            let engine = ConvolutionEngine::new(
+                &impulse_response,
+                state.num_inputs,
+                state.num_outputs,
+                state.partition_len,
+                self.latency,
+                &*state.forward_fft,
+            );
+
+            state
+                .to_processor_tx
+                .push(NodeToProcessorMsg {
+                    engine: Box::new(engine),
+                    crossfade_frames: (crossfade_secs.max(0.0) * state.sample_rate as f32).round()
+                        as usize,
+                })
+                .map_err(|_| ConvolutionError::MessageChannelFull)?;
+        }
+
+        self.impulse_response = Some(impulse_response);
+
+        Ok(())
+    }
+}
+
+impl AudioNode for ConvolutionNode {
+    fn debug_name(&self) -> &'static str {
+        "convolution"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 1,
+            num_max_supported_inputs: 2,
+            num_min_supported_outputs: 1,
+            num_max_supported_outputs: 2,
+            updates: true,
+            split_at_events: false,
+            // The impulse response may still be ringing when the inputs
+            // become silent.
+            silent_when_inputs_silent: false,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if !(1..=2).contains(&num_inputs) || !(1..=2).contains(&num_outputs) {
+            return Err(format!("A Convolution node must have either 1 or 2 inputs and either 1 or 2 outputs. Got num_inputs: {}, num_outputs: {}", num_inputs, num_outputs).into());
+        }
+
+        let partition_len = max_block_frames.max(1).next_power_of_two();
+        let fft_len = partition_len * 2;
+
+        let mut planner = RealFftPlanner::<f32>::new();
+        let forward_fft = planner.plan_fft_forward(fft_len);
+        let inverse_fft = planner.plan_fft_inverse(fft_len);
+
+        let engine = self.impulse_response.as_ref().map(|ir| {
+            Box::new(ConvolutionEngine::new(
+                ir,
+                num_inputs,
+                num_outputs,
+                partition_len,
+                self.latency,
+                &*forward_fft,
+            ))
+        });
+
+        let (to_processor_tx, from_node_rx) =
+            rtrb::RingBuffer::<NodeToProcessorMsg>::new(CHANNEL_CAPACITY);
+        let (to_node_tx, from_processor_rx) =
+            rtrb::RingBuffer::<ProcessorToNodeMsg>::new(CHANNEL_CAPACITY);
+
+        let scratch_len = forward_fft
+            .get_scratch_len()
+            .max(inverse_fft.get_scratch_len());
+
+        let processor = ConvolutionProcessor {
+            engine,
+            fading_out: None,
+            crossfade_frames: 0,
+            crossfade_progress: 0,
+            in_bufs: vec![vec![0.0; fft_len]; num_inputs],
+            in_spectra: vec![forward_fft.make_output_vec(); num_inputs],
+            fft_buf: forward_fft.make_input_vec(),
+            spectrum_buf: forward_fft.make_output_vec(),
+            fft_scratch: vec![Complex::default(); scratch_len],
+            fade_buf: vec![0.0; partition_len],
+            forward_fft: Arc::clone(&forward_fft),
+            inverse_fft,
+            partition_len,
+            pos: 0,
+            silent_frames: 0,
+            is_cleared: true,
+            from_node_rx,
+            to_node_tx,
+        };
+
+        self.active_state = Some(ActiveState {
+            to_processor_tx,
+            from_processor_rx,
+            forward_fft,
+            partition_len,
+            num_inputs,
+            num_outputs,
+            sample_rate,
+        });
+
+        Ok(Box::new(processor))
+    }
+
+    fn deactivate(&mut self, _processor: Option<Box<dyn AudioNodeProcessor>>) {
+        self.active_state = None;
+    }
+
+    fn update(&mut self) {
+        if let Some(state) = &mut self.active_state {
+            // Drop the old engines here so they are not deallocated in the
+            // audio thread.
+            while let Ok(msg) = state.from_processor_rx.pop() {
+                match msg {
+                    ProcessorToNodeMsg::ReturnEngine(_engine) => {}
+                }
+            }
+        }
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for ConvolutionNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+/// A path from an input channel to an output channel through one channel
+/// of the impulse response
+struct ConvolutionPath {
+    input: usize,
+    output: usize,
+    /// The head of the impulse response which is convolved directly in the
+    /// time domain (empty if the latency mode is `Block`)
+    head: Vec<f32>,
+    /// The spectra of the partitions of the rest of the impulse response
+    partitions: Vec<Vec<Complex<f32>>>,
+}
+
+/// The spectra of the most recent input blocks of an input channel
+struct FrequencyDelayLine {
+    spectra: Vec<Vec<Complex<f32>>>,
+    /// The index of the most recent spectrum
+    pos: usize,
+}
+
+/// An impulse response prepared for convolution, along with the state
+/// which depends on its length.
+///
+/// This is constructed on the main thread.
+struct ConvolutionEngine {
+    paths: Vec<ConvolutionPath>,
+    delay_lines: Vec<FrequencyDelayLine>,
+    out_bufs: Vec<Vec<f32>>,
+    /// The number of frames it takes for the output to decay after the
+    /// input becomes silent
+    tail_frames: usize,
+}
+
+impl ConvolutionEngine {
+    fn new(
+        impulse_response: &[Vec<f32>],
+        num_inputs: usize,
+        num_outputs: usize,
+        partition_len: usize,
+        latency: ConvolutionLatency,
+        forward_fft: &dyn RealToComplex<f32>,
+    ) -> Self {
+        // (input, output, impulse response channel)
+        let mut routing: Vec<(usize, usize, usize)> = Vec::new();
+        if impulse_response.len() == 4 && num_inputs == 2 && num_outputs == 2 {
+            routing.extend_from_slice(&[(0, 0, 0), (0, 1, 1), (1, 0, 2), (1, 1, 3)]);
+        } else {
+            for output in 0..num_outputs {
+                routing.push((
+                    output.min(num_inputs - 1),
+                    output,
+                    output.min(impulse_response.len().min(2) - 1),
+                ));
+            }
+        }
+
+        let fft_len = partition_len * 2;
+        // The FFTs are not normalized, so apply the normalization to the
+        // impulse response instead.
+        let norm = (fft_len as f32).recip();
+
+        let mut fft_buf = forward_fft.make_input_vec();
+        let paths: Vec<ConvolutionPath> = routing
+            .iter()
+            .map(|&(input, output, ir_ch)| {
+                let ir = &impulse_response[ir_ch];
+
+                let head_len = match latency {
+                    ConvolutionLatency::Block => 0,
+                    ConvolutionLatency::Zero => partition_len.min(ir.len()),
+                };
+                let head = ir[..head_len].to_vec();
+
+                let partitions = ir[head_len..]
+                    .chunks(partition_len)
+                    .map(|chunk| {
+                        fft_buf.fill(0.0);
+                        for (b, &s) in fft_buf.iter_mut().zip(chunk.iter()) {
+                            *b = s * norm;
+                        }
+
+                        let mut spectrum = forward_fft.make_output_vec();
+                        let _ = forward_fft.process(&mut fft_buf, &mut spectrum);
+                        spectrum
+                    })
+                    .collect();
+
+                ConvolutionPath {
+                    input,
+                    output,
+                    head,
+                    partitions,
+                }
+            })
+            .collect();
+
+        let delay_lines = (0..num_inputs)
+            .map(|input| {
+                let num_partitions = paths
+                    .iter()
+                    .filter(|p| p.input == input)
+                    .map(|p| p.partitions.len())
+                    .max()
+                    .unwrap_or(0);
+
+                FrequencyDelayLine {
+                    spectra: vec![forward_fft.make_output_vec(); num_partitions],
+                    pos: 0,
+                }
+            })
+            .collect();
+
+        let ir_len = impulse_response
+            .iter()
+            .map(|ch| ch.len())
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            paths,
+            delay_lines,
+            out_bufs: vec![vec![0.0; partition_len]; num_outputs],
+            tail_frames: ir_len + partition_len * 2,
+        }
+    }
+
+    /// Push the spectra of the latest input block into the delay lines.
+    fn push_input_spectra(&mut self, in_spectra: &[Vec<Complex<f32>>]) {
+        for (delay_line, spectrum) in self.delay_lines.iter_mut().zip(in_spectra.iter()) {
+            if delay_line.spectra.is_empty() {
+                continue;
+            }
+
+            delay_line.pos = (delay_line.pos + 1) % delay_line.spectra.len();
+            delay_line.spectra[delay_line.pos].copy_from_slice(spectrum);
+        }
+    }
+
+    /// Compute the next block of output from the delay lines.
+    fn compute_output_block(
+        &mut self,
+        spectrum_buf: &mut [Complex<f32>],
+        fft_buf: &mut [f32],
+        fft_scratch: &mut [Complex<f32>],
+        inverse_fft: &dyn ComplexToReal<f32>,
+    ) {
+        let partition_len = fft_buf.len() / 2;
+
+        for (output, out_buf) in self.out_bufs.iter_mut().enumerate() {
+            spectrum_buf.fill(Complex::default());
+
+            for path in self.paths.iter().filter(|p| p.output == output) {
+                let delay_line = &self.delay_lines[path.input];
+                let num_spectra = delay_line.spectra.len();
+
+                for (i, partition) in path.partitions.iter().enumerate() {
+                    let spectrum =
+                        &delay_line.spectra[(delay_line.pos + num_spectra - i) % num_spectra];
+
+                    for ((acc, &x), &h) in spectrum_buf
+                        .iter_mut()
+                        .zip(spectrum.iter())
+                        .zip(partition.iter())
+                    {
+                        *acc += x * h;
+                    }
+                }
+            }
+
+            let _ = inverse_fft.process_with_scratch(spectrum_buf, fft_buf, fft_scratch);
+
+            // With overlap-save, only the second half of the output is valid.
+            out_buf.copy_from_slice(&fft_buf[partition_len..]);
+        }
+    }
+
+    /// Copy the input history from the previous engine, so that the new
+    /// impulse response is applied to the recent input.
+    fn copy_history_from(&mut self, other: &ConvolutionEngine) {
+        for (delay_line, other_line) in self.delay_lines.iter_mut().zip(other.delay_lines.iter()) {
+            let len = delay_line.spectra.len();
+            let other_len = other_line.spectra.len();
+
+            for i in 0..len.min(other_len) {
+                delay_line.spectra[(delay_line.pos + len - i) % len].copy_from_slice(
+                    &other_line.spectra[(other_line.pos + other_len - i) % other_len],
+                );
+            }
+        }
+    }
+
+    /// Render the output of this engine for the given output channel.
+    ///
+    /// The input buffers contain the previous block followed by the current
+    /// block up to `pos + out.len()`.
+    fn render(&self, output: usize, in_bufs: &[Vec<f32>], pos: usize, out: &mut [f32]) {
+        let partition_len = self.out_bufs[output].len();
+
+        out.copy_from_slice(&self.out_bufs[output][pos..pos + out.len()]);
+
+        for path in self.paths.iter().filter(|p| p.output == output) {
+            if path.head.is_empty() {
+                continue;
+            }
+
+            let in_buf = &in_bufs[path.input];
+
+            for (i, out_s) in out.iter_mut().enumerate() {
+                let newest = partition_len + pos + i;
+
+                let mut sum = 0.0;
+                for (j, &h) in path.head.iter().enumerate() {
+                    sum += h * in_buf[newest - j];
+                }
+
+                *out_s += sum;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        for delay_line in self.delay_lines.iter_mut() {
+            for spectrum in delay_line.spectra.iter_mut() {
+                spectrum.fill(Complex::default());
+            }
+        }
+        for out_buf in self.out_bufs.iter_mut() {
+            out_buf.fill(0.0);
+        }
+    }
+}
+
+struct ConvolutionProcessor {
+    engine: Option<Box<ConvolutionEngine>>,
+    /// The previous engine while crossfading to a new impulse response
+    fading_out: Option<Box<ConvolutionEngine>>,
+    crossfade_frames: usize,
+    crossfade_progress: usize,
+
+    /// The previous and current input block of each input channel
+    in_bufs: Vec<Vec<f32>>,
+    in_spectra: Vec<Vec<Complex<f32>>>,
+    fft_buf: Vec<f32>,
+    spectrum_buf: Vec<Complex<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    fade_buf: Vec<f32>,
+    forward_fft: Arc<dyn RealToComplex<f32>>,
+    inverse_fft: Arc<dyn ComplexToReal<f32>>,
+
+    partition_len: usize,
+    /// The position in the current input block
+    pos: usize,
+
+    /// The number of consecutive frames in which the inputs were silent
+    silent_frames: usize,
+    /// Whether all of the buffers contain only silence
+    is_cleared: bool,
+
+    from_node_rx: rtrb::Consumer<NodeToProcessorMsg>,
+    to_node_tx: rtrb::Producer<ProcessorToNodeMsg>,
+}
+
+impl ConvolutionProcessor {
+    fn set_engine(&mut self, mut engine: Box<ConvolutionEngine>, crossfade_frames: usize) {
+        // The caller makes sure that there is room in the channel for both
+        // of the old engines, so they are never dropped in the audio thread.
+        if let Some(fading_out) = self.fading_out.take() {
+            let _ = self
+                .to_node_tx
+                .push(ProcessorToNodeMsg::ReturnEngine(fading_out));
+        }
+
+        if let Some(old_engine) = self.engine.take() {
+            engine.copy_history_from(&old_engine);
+            engine.compute_output_block(
+                &mut self.spectrum_buf,
+                &mut self.fft_buf,
+                &mut self.fft_scratch,
+                &*self.inverse_fft,
+            );
+
+            if crossfade_frames > 0 {
+                self.fading_out = Some(old_engine);
+            } else {
+                let _ = self
+                    .to_node_tx
+                    .push(ProcessorToNodeMsg::ReturnEngine(old_engine));
+            }
+        }
+
+        self.engine = Some(engine);
+        self.crossfade_frames = crossfade_frames;
+        self.crossfade_progress = 0;
+    }
+
+    fn process_input_block(&mut self) {
+        for (in_buf, in_spectrum) in self.in_bufs.iter_mut().zip(self.in_spectra.iter_mut()) {
+            self.fft_buf.copy_from_slice(in_buf);
+            let _ = self.forward_fft.process_with_scratch(
+                &mut self.fft_buf,
+                in_spectrum,
+                &mut self.fft_scratch,
+            );
+
+            // Keep the current block as the previous block.
+            in_buf.copy_within(self.partition_len.., 0);
+        }
+
+        for engine in self.engine.iter_mut().chain(self.fading_out.iter_mut()) {
+            engine.push_input_spectra(&self.in_spectra);
+            engine.compute_output_block(
+                &mut self.spectrum_buf,
+                &mut self.fft_buf,
+                &mut self.fft_scratch,
+                &*self.inverse_fft,
+            );
+        }
+    }
+}
+
+impl AudioNodeProcessor for ConvolutionProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        // Setting an engine can return up to two old engines to the node. If
+        // there isn't room for them, leave the new engines in the channel
+        // until the next block.
+        while self.to_node_tx.slots() >= 2 {
+            let Ok(msg) = self.from_node_rx.pop() else {
+                break;
+            };
+
+            self.set_engine(msg.engine, msg.crossfade_frames);
+            self.is_cleared = false;
+        }
+
+        let Some(tail_frames) = self.engine.as_ref().map(|e| e.tail_frames) else {
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        };
+
+        if proc_info.in_silence_mask.all_channels_silent(inputs.len()) {
+            self.silent_frames += frames;
+        } else {
+            self.silent_frames = 0;
+            self.is_cleared = false;
+        }
+
+        if self.silent_frames >= tail_frames && self.fading_out.is_none() {
+            if !self.is_cleared {
+                // The tail has fully decayed, so there is no need to process
+                // until the inputs are no longer silent.
+                for in_buf in self.in_bufs.iter_mut() {
+                    in_buf.fill(0.0);
+                }
+                if let Some(engine) = &mut self.engine {
+                    engine.clear();
+                }
+                self.pos = 0;
+                self.is_cleared = true;
+            }
+
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        }
+
+        let mut frames_processed = 0;
+        while frames_processed < frames {
+            let chunk_frames = (frames - frames_processed).min(self.partition_len - self.pos);
+            let chunk = frames_processed..frames_processed + chunk_frames;
+            let buf_range =
+                self.partition_len + self.pos..self.partition_len + self.pos + chunk_frames;
+
+            for (in_buf, input) in self.in_bufs.iter_mut().zip(inputs.iter()) {
+                in_buf[buf_range.clone()].copy_from_slice(&input[chunk.clone()]);
+            }
+
+            for (ch, output) in outputs.iter_mut().enumerate() {
+                let out = &mut output[chunk.clone()];
+
+                if let Some(engine) = &self.engine {
+                    engine.render(ch, &self.in_bufs, self.pos, out);
+                }
+
+                if let Some(fading_out) = &self.fading_out {
+                    let fade_buf = &mut self.fade_buf[..chunk_frames];
+                    fading_out.render(ch, &self.in_bufs, self.pos, fade_buf);
+
+                    let step = (self.crossfade_frames as f32).recip();
+                    for (i, (out_s, &old_s)) in out.iter_mut().zip(fade_buf.iter()).enumerate() {
+                        let gain = ((self.crossfade_progress + i) as f32 * step).min(1.0);
+                        *out_s = *out_s * gain + old_s * (1.0 - gain);
+                    }
+                }
+            }
+
+            if self.fading_out.is_some() {
+                self.crossfade_progress += chunk_frames;
+
+                // If there is no room in the channel, keep the old engine
+                // around (at zero gain) until there is.
+                if self.crossfade_progress >= self.crossfade_frames && self.to_node_tx.slots() > 0 {
+                    if let Some(fading_out) = self.fading_out.take() {
+                        let _ = self
+                            .to_node_tx
+                            .push(ProcessorToNodeMsg::ReturnEngine(fading_out));
+                    }
+                }
+            }
+
+            self.pos += chunk_frames;
+            frames_processed += chunk_frames;
+
+            if self.pos == self.partition_len {
+                self.process_input_block();
+                self.pos = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::TestGraphCtx, graph::AudioGraphConfig};
+
+    fn convolve(latency: ConvolutionLatency, input: &[f32], ir: &[f32]) -> (Vec<f32>, usize) {
+        let mut node = ConvolutionNode::new(latency);
+        node.set_impulse_response(&vec![ir.to_vec()], 0.0).unwrap();
+        let (mut test_cx, conv) = TestGraphCtx::with_node(
+            AudioGraphConfig {
+                num_graph_inputs: 1,
+                num_graph_outputs: 1,
+                ..Default::default()
+            },
+            1,
+            1,
+            node,
+        );
+
+        let latency_frames = test_cx
+            .cx
+            .graph
+            .node(conv)
+            .unwrap()
+            .downcast_ref::<ConvolutionNode>()
+            .unwrap()
+            .latency_frames()
+            .unwrap();
+
+        // Process in uneven blocks to test the internal buffering.
+        let mut output = vec![0.0; input.len()];
+        for (in_block, out_block) in input.chunks(50).zip(output.chunks_mut(50)) {
+            test_cx.process(in_block, out_block);
+        }
+
+        (output, latency_frames)
+    }
+
+    #[test]
+    fn partitioned_convolution() {
+        let input: Vec<f32> = (0..1000)
+            .map(|i| ((i * 7919) % 13) as f32 / 13.0 - 0.5)
+            .collect();
+        let ir: Vec<f32> = (0..300)
+            .map(|i| (-(i as f32) / 60.0).exp() * if i % 2 == 0 { 1.0 } else { -0.5 })
+            .collect();
+
+        let mut expected = vec![0.0; input.len()];
+        for (n, out) in expected.iter_mut().enumerate() {
+            for (j, &h) in ir.iter().enumerate().take(n + 1) {
+                *out += h * input[n - j];
+            }
+        }
+
+        for latency in [ConvolutionLatency::Block, ConvolutionLatency::Zero] {
+            let (output, latency_frames) = convolve(latency, &input, &ir);
+
+            assert_eq!(
+                latency_frames,
+                if latency == ConvolutionLatency::Block {
+                    64
+                } else {
+                    0
+                }
+            );
+            for (&out, &exp) in output[latency_frames..].iter().zip(expected.iter()) {
+                assert!((out - exp).abs() < 0.0001);
+            }
+        }
+    }
+}
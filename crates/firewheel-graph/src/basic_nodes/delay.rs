@@ -0,0 +1,512 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::{LinearRange, NormToFreqRange},
+        smoother::{ParamSmoother, SmootherConfig},
+    },
+};
+
+/// The echoes are considered to have decayed once they fall below this
+/// value (about -100 dB).
+const SILENCE_EPSILON: f32 = 0.00001;
+
+/// The delay time is smoothed more slowly than the other parameters, so
+/// that changing it sounds like a tape delay speeding up or slowing down
+/// instead of clicking.
+const DELAY_SMOOTHER_CONFIG: SmootherConfig = SmootherConfig {
+    smooth_secs: 100.0 / 1000.0,
+    settle_epsilon: 0.00001,
+};
+
+/// A multi-channel echo (feedback delay) node
+///
+/// The delay line is allocated when the node is activated, with enough
+/// room for the maximum delay time given in [`DelayNode::new`].
+///
+/// Once the inputs become silent, the node keeps processing until the
+/// echoes have decayed, and then it reports its outputs as silent.
+pub struct DelayNode {
+    // TODO: Find a good solution for webassembly.
+    raw_delay_secs: Arc<AtomicF32>,
+    raw_feedback: Arc<AtomicF32>,
+    raw_mix: Arc<AtomicF32>,
+    raw_damping_hz: Arc<AtomicF32>,
+    max_delay_secs: f32,
+    delay_secs: f32,
+    feedback: f32,
+    mix: f32,
+    damping_hz: f32,
+}
+
+impl DelayNode {
+    /// The ID of the delay time parameter in seconds
+    pub const PARAM_DELAY: ParamID = ParamID(0);
+    /// The ID of the feedback parameter, in the range `[0.0, 0.99]`
+    pub const PARAM_FEEDBACK: ParamID = ParamID(1);
+    /// The ID of the wet/dry mix parameter, in the range `[0.0, 1.0]`
+    /// where `0.0` is fully dry and `1.0` is fully wet
+    pub const PARAM_MIX: ParamID = ParamID(2);
+    /// The ID of the cutoff frequency in Hz of the lowpass filter in the
+    /// feedback loop
+    ///
+    /// Setting this to [`DelayNode::MAX_DAMPING_HZ`] disables the filter.
+    pub const PARAM_DAMPING: ParamID = ParamID(3);
+    /// The ID of the maximum delay time parameter in seconds
+    ///
+    /// Changes to this parameter take effect the next time the node is
+    /// activated (see [`DelayNode::set_max_delay_secs`]).
+    pub const PARAM_MAX_DELAY: ParamID = ParamID(4);
+
+    /// The shortest delay time in seconds
+    pub const MIN_DELAY_SECS: f32 = 0.001;
+    /// The largest maximum delay time in seconds
+    pub const LONGEST_MAX_DELAY_SECS: f32 = 60.0;
+    /// The maximum amount of feedback
+    pub const MAX_FEEDBACK: f32 = 0.99;
+    /// The minimum cutoff frequency of the damping filter in Hz
+    pub const MIN_DAMPING_HZ: f32 = 200.0;
+    /// The maximum cutoff frequency of the damping filter in Hz (which
+    /// disables the filter)
+    pub const MAX_DAMPING_HZ: f32 = 20_000.0;
+
+    /// Create a new delay node.
+    ///
+    /// * `max_delay_secs` - The maximum delay time in seconds (see
+    ///   [`DelayNode::set_max_delay_secs`])
+    /// * `delay_secs` - The initial delay time in seconds
+    pub fn new(max_delay_secs: f32, delay_secs: f32) -> Self {
+        let max_delay_secs =
+            max_delay_secs.clamp(Self::MIN_DELAY_SECS, Self::LONGEST_MAX_DELAY_SECS);
+        let delay_secs = delay_secs.clamp(Self::MIN_DELAY_SECS, max_delay_secs);
+
+        Self {
+            raw_delay_secs: Arc::new(AtomicF32::new(delay_secs)),
+            raw_feedback: Arc::new(AtomicF32::new(0.5)),
+            raw_mix: Arc::new(AtomicF32::new(0.5)),
+            raw_damping_hz: Arc::new(AtomicF32::new(Self::MAX_DAMPING_HZ)),
+            max_delay_secs,
+            delay_secs,
+            feedback: 0.5,
+            mix: 0.5,
+            damping_hz: Self::MAX_DAMPING_HZ,
+        }
+    }
+
+    pub fn max_delay_secs(&self) -> f32 {
+        self.max_delay_secs
+    }
+
+    /// Set the maximum delay time in seconds.
+    ///
+    /// The delay line is allocated when the node is activated, so this
+    /// takes effect the next time the node is activated. Until then, the
+    /// delay time is still limited by the old maximum.
+    ///
+    /// If the current delay time is longer than the new maximum, then it
+    /// is shortened to the new maximum.
+    pub fn set_max_delay_secs(&mut self, max_delay_secs: f32) {
+        self.max_delay_secs =
+            max_delay_secs.clamp(Self::MIN_DELAY_SECS, Self::LONGEST_MAX_DELAY_SECS);
+        self.set_delay_secs(self.delay_secs);
+    }
+
+    pub fn delay_secs(&self) -> f32 {
+        self.delay_secs
+    }
+
+    pub fn set_delay_secs(&mut self, delay_secs: f32) {
+        self.delay_secs = delay_secs.clamp(Self::MIN_DELAY_SECS, self.max_delay_secs);
+        self.raw_delay_secs
+            .store(self.delay_secs, Ordering::Relaxed);
+    }
+
+    /// Set the delay time to the given number of beats at the given tempo
+    /// in beats per minute.
+    ///
+    /// The delay time is clamped to the maximum delay time of this node.
+    pub fn set_delay_beats(&mut self, beats: f32, bpm: f32) {
+        self.set_delay_secs(beats * 60.0 / bpm.max(1.0));
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(0.0, Self::MAX_FEEDBACK);
+        self.raw_feedback.store(self.feedback, Ordering::Relaxed);
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+        self.raw_mix.store(self.mix, Ordering::Relaxed);
+    }
+
+    pub fn damping_hz(&self) -> f32 {
+        self.damping_hz
+    }
+
+    pub fn set_damping_hz(&mut self, damping_hz: f32) {
+        self.damping_hz = damping_hz.clamp(Self::MIN_DAMPING_HZ, Self::MAX_DAMPING_HZ);
+        self.raw_damping_hz
+            .store(self.damping_hz, Ordering::Relaxed);
+    }
+}
+
+impl AudioNode for DelayNode {
+    fn debug_name(&self) -> &'static str {
+        "delay"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 1,
+            num_max_supported_inputs: 64,
+            num_min_supported_outputs: 1,
+            num_max_supported_outputs: 64,
+            updates: false,
+            split_at_events: false,
+            // The echoes may still be ringing when the inputs become silent.
+            silent_when_inputs_silent: false,
+        }
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            // This comes first so that the maximum is restored before the
+            // delay time when loading a graph description.
+            ParamInfo {
+                id: Self::PARAM_MAX_DELAY,
+                name: "max delay",
+                range: ParamRange::Linear(LinearRange::new(
+                    Self::MIN_DELAY_SECS,
+                    Self::LONGEST_MAX_DELAY_SECS,
+                )),
+                default: 2.0,
+                unit: ParamUnit::Seconds,
+                smoothing: None,
+            },
+            ParamInfo {
+                id: Self::PARAM_DELAY,
+                name: "delay",
+                range: ParamRange::Linear(LinearRange::new(
+                    Self::MIN_DELAY_SECS,
+                    self.max_delay_secs,
+                )),
+                default: self.max_delay_secs.min(0.5),
+                unit: ParamUnit::Seconds,
+                smoothing: Some(DELAY_SMOOTHER_CONFIG),
+            },
+            ParamInfo {
+                id: Self::PARAM_FEEDBACK,
+                name: "feedback",
+                range: ParamRange::Linear(LinearRange::new(0.0, Self::MAX_FEEDBACK)),
+                default: 0.5,
+                unit: ParamUnit::Generic,
+                smoothing: Some(SmootherConfig::default()),
+            },
+            ParamInfo {
+                id: Self::PARAM_MIX,
+                name: "mix",
+                range: ParamRange::Linear(LinearRange::new(0.0, 1.0)),
+                default: 0.5,
+                unit: ParamUnit::Generic,
+                smoothing: Some(SmootherConfig::default()),
+            },
+            ParamInfo {
+                id: Self::PARAM_DAMPING,
+                name: "damping",
+                range: ParamRange::Freq(NormToFreqRange::new(
+                    Self::MIN_DAMPING_HZ,
+                    Self::MAX_DAMPING_HZ,
+                )),
+                default: Self::MAX_DAMPING_HZ,
+                unit: ParamUnit::Hz,
+                smoothing: None,
+            },
+        ]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        match id {
+            Self::PARAM_DELAY => self.set_delay_secs(val),
+            Self::PARAM_FEEDBACK => self.set_feedback(val),
+            Self::PARAM_MIX => self.set_mix(val),
+            Self::PARAM_DAMPING => self.set_damping_hz(val),
+            Self::PARAM_MAX_DELAY => self.set_max_delay_secs(val),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        match id {
+            Self::PARAM_DELAY => Some(self.delay_secs),
+            Self::PARAM_FEEDBACK => Some(self.feedback),
+            Self::PARAM_MIX => Some(self.mix),
+            Self::PARAM_DAMPING => Some(self.damping_hz),
+            Self::PARAM_MAX_DELAY => Some(self.max_delay_secs),
+            _ => None,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if num_inputs != num_outputs {
+            return Err(format!("The number of inputs on a Delay node must equal the number of outputs. Got num_inputs: {}, num_outputs: {}", num_inputs, num_outputs).into());
+        }
+
+        let sample_rate_f = sample_rate as f32;
+        let max_delay_frames = (self.max_delay_secs * sample_rate_f).ceil().max(1.0);
+
+        // Leave room for the interpolated sample past the maximum delay.
+        let buffer_len = max_delay_frames as usize + 2;
+
+        Ok(Box::new(DelayProcessor {
+            raw_delay_secs: Arc::clone(&self.raw_delay_secs),
+            raw_feedback: Arc::clone(&self.raw_feedback),
+            raw_mix: Arc::clone(&self.raw_mix),
+            raw_damping_hz: Arc::clone(&self.raw_damping_hz),
+            delay_smoother: ParamSmoother::new(
+                self.delay_secs,
+                sample_rate,
+                max_block_frames,
+                DELAY_SMOOTHER_CONFIG,
+            ),
+            feedback_smoother: ParamSmoother::new(
+                self.feedback,
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            ),
+            mix_smoother: ParamSmoother::new(
+                self.mix,
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            ),
+            lines: (0..num_inputs).map(|_| vec![0.0; buffer_len]).collect(),
+            damping_states: vec![0.0; num_inputs],
+            write_pos: 0,
+            quiet_frames: 0,
+            is_silent: true,
+            sample_rate: sample_rate_f,
+            max_delay_frames,
+        }))
+    }
+}
+
+struct DelayProcessor {
+    raw_delay_secs: Arc<AtomicF32>,
+    raw_feedback: Arc<AtomicF32>,
+    raw_mix: Arc<AtomicF32>,
+    raw_damping_hz: Arc<AtomicF32>,
+    delay_smoother: ParamSmoother,
+    feedback_smoother: ParamSmoother,
+    mix_smoother: ParamSmoother,
+
+    lines: Vec<Vec<f32>>,
+    damping_states: Vec<f32>,
+    write_pos: usize,
+
+    /// The number of consecutive frames in which the inputs were silent
+    /// and the echoes were below the silence threshold
+    quiet_frames: usize,
+    /// Whether the delay lines contain only silence
+    is_silent: bool,
+
+    sample_rate: f32,
+    max_delay_frames: f32,
+}
+
+impl AudioNodeProcessor for DelayProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        let raw_delay_secs = self.raw_delay_secs.load(Ordering::Relaxed);
+        let raw_feedback = self.raw_feedback.load(Ordering::Relaxed);
+        let raw_mix = self.raw_mix.load(Ordering::Relaxed);
+        let inputs_silent = proc_info.in_silence_mask.all_channels_silent(inputs.len());
+
+        if inputs_silent && self.is_silent {
+            // All channels are silent and the echoes have decayed, so there
+            // is no need to process. Also reset the filters since they don't
+            // need to smooth anything.
+            self.delay_smoother.reset(raw_delay_secs);
+            self.feedback_smoother.reset(raw_feedback);
+            self.mix_smoother.reset(raw_mix);
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        }
+
+        self.is_silent = false;
+
+        let damping_hz = self.raw_damping_hz.load(Ordering::Relaxed);
+        // A one-pole lowpass filter, which is bypassed at the maximum
+        // cutoff.
+        let damping_coeff = if damping_hz >= DelayNode::MAX_DAMPING_HZ {
+            None
+        } else {
+            Some(1.0 - (-std::f32::consts::TAU * damping_hz / self.sample_rate).exp())
+        };
+
+        let delay_secs = self.delay_smoother.set_and_process(raw_delay_secs, frames);
+        let feedback = self.feedback_smoother.set_and_process(raw_feedback, frames);
+        let mix = self.mix_smoother.set_and_process(raw_mix, frames);
+
+        // Hint to the compiler to optimize loop.
+        assert!(frames <= delay_secs.values.len());
+        assert!(frames <= feedback.values.len());
+        assert!(frames <= mix.values.len());
+
+        let mut peak = 0.0f32;
+
+        for (((output, input), line), damping_state) in outputs
+            .iter_mut()
+            .zip(inputs.iter())
+            .zip(self.lines.iter_mut())
+            .zip(self.damping_states.iter_mut())
+        {
+            let output = &mut output[..frames];
+            let input = &input[..frames];
+            let line_len = line.len();
+            let mut write_pos = self.write_pos;
+
+            for i in 0..frames {
+                let delay_frames =
+                    (delay_secs[i] * self.sample_rate).clamp(1.0, self.max_delay_frames);
+
+                // Read from the delay line with linear interpolation, so that
+                // the delay time can change smoothly.
+                let mut read_pos = write_pos as f32 - delay_frames;
+                if read_pos < 0.0 {
+                    read_pos += line_len as f32;
+                }
+                let read_i = read_pos as usize;
+                let fract = read_pos - read_i as f32;
+                let s0 = line[read_i % line_len];
+                let s1 = line[(read_i + 1) % line_len];
+                let echo = s0 + (s1 - s0) * fract;
+
+                let fed_back = if let Some(coeff) = damping_coeff {
+                    *damping_state += coeff * (echo - *damping_state);
+                    *damping_state
+                } else {
+                    echo
+                };
+
+                line[write_pos] = input[i] + fed_back * feedback[i];
+                output[i] = input[i] * (1.0 - mix[i]) + echo * mix[i];
+
+                peak = peak.max(echo.abs());
+
+                write_pos += 1;
+                if write_pos == line_len {
+                    write_pos = 0;
+                }
+            }
+        }
+
+        if let Some(line_len) = self.lines.first().map(|l| l.len()) {
+            self.write_pos = (self.write_pos + frames) % line_len;
+        }
+
+        if inputs_silent && peak < SILENCE_EPSILON {
+            self.quiet_frames += frames;
+
+            // Once a full delay line's worth of quiet frames has been read,
+            // only quiet frames remain in the delay lines.
+            if self.quiet_frames >= self.max_delay_frames as usize {
+                for line in self.lines.iter_mut() {
+                    line.fill(0.0);
+                }
+                self.damping_states.fill(0.0);
+                self.quiet_frames = 0;
+                self.is_silent = true;
+            }
+        } else {
+            self.quiet_frames = 0;
+        }
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for DelayNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::TestGraphCtx, graph::AudioGraphConfig};
+
+    #[test]
+    fn echo_and_decay() {
+        let mut delay_node = DelayNode::new(0.01, 0.001);
+        delay_node.set_feedback(0.5);
+        delay_node.set_mix(1.0);
+
+        // 1 millisecond is 48 frames at 48 kHz.
+        let (mut test_cx, _) = TestGraphCtx::with_node(
+            AudioGraphConfig {
+                num_graph_inputs: 1,
+                num_graph_outputs: 1,
+                ..Default::default()
+            },
+            1,
+            1,
+            delay_node,
+        );
+
+        let mut input = vec![0.0; 64];
+        let mut output = vec![0.0; 64];
+        input[0] = 1.0;
+        test_cx.process(&input, &mut output);
+        assert_eq!(output[0], 0.0);
+        assert!((output[48] - 1.0).abs() < 0.0001);
+
+        // The echoes keep ringing after the input becomes silent.
+        input[0] = 0.0;
+        test_cx.process(&input, &mut output);
+        assert!((output[32] - 0.5).abs() < 0.0001);
+
+        // Eventually the echoes decay to silence.
+        for _ in 0..100 {
+            test_cx.process(&input, &mut output);
+        }
+        assert!(output.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn max_delay_is_restored_before_delay() {
+        // The delay time is longer than the maximum of the node constructed
+        // by the registry, so it is only restored if the maximum delay time
+        // is set first.
+        let (graph, node_id) = crate::graph::reload_node(1, 1, DelayNode::new(5.0, 4.0));
+        let node = graph
+            .node(node_id)
+            .unwrap()
+            .downcast_ref::<DelayNode>()
+            .unwrap();
+
+        assert_eq!(node.max_delay_secs(), 5.0);
+        assert_eq!(node.delay_secs(), 4.0);
+    }
+}
@@ -1,15 +1,43 @@
-use firewheel_core::node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo};
+use std::sync::{atomic::Ordering, Arc};
+
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::LinearRange,
+    },
+};
 
 pub struct HardClipNode {
-    threshold_gain: f32,
+    threshold_gain: Arc<AtomicF32>,
+    threshold_db: f32,
 }
 
 impl HardClipNode {
+    /// The ID of the threshold parameter in decibels
+    pub const PARAM_THRESHOLD: ParamID = ParamID(0);
+
     pub fn new(threshold_db: f32) -> Self {
         Self {
-            threshold_gain: firewheel_core::util::db_to_gain_clamped_neg_100_db(threshold_db),
+            threshold_gain: Arc::new(AtomicF32::new(
+                firewheel_core::util::db_to_gain_clamped_neg_100_db(threshold_db),
+            )),
+            threshold_db,
         }
     }
+
+    pub fn threshold_db(&self) -> f32 {
+        self.threshold_db
+    }
+
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        self.threshold_db = threshold_db;
+        self.threshold_gain.store(
+            firewheel_core::util::db_to_gain_clamped_neg_100_db(threshold_db),
+            Ordering::Relaxed,
+        );
+    }
 }
 
 impl AudioNode for HardClipNode {
@@ -24,9 +52,32 @@ impl AudioNode for HardClipNode {
             num_min_supported_outputs: 1,
             num_max_supported_outputs: 64,
             updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: true,
+        }
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![ParamInfo {
+            id: Self::PARAM_THRESHOLD,
+            name: "threshold",
+            range: ParamRange::Linear(LinearRange::new(-100.0, 24.0)),
+            default: 0.0,
+            unit: ParamUnit::Decibels,
+            smoothing: None,
+        }]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        if id == Self::PARAM_THRESHOLD {
+            self.set_threshold_db(val);
         }
     }
 
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        (id == Self::PARAM_THRESHOLD).then_some(self.threshold_db)
+    }
+
     fn activate(
         &mut self,
         _sample_rate: u32,
@@ -39,13 +90,13 @@ impl AudioNode for HardClipNode {
         }
 
         Ok(Box::new(HardClipProcessor {
-            threshold_gain: self.threshold_gain,
+            threshold_gain: Arc::clone(&self.threshold_gain),
         }))
     }
 }
 
 struct HardClipProcessor {
-    threshold_gain: f32,
+    threshold_gain: Arc<AtomicF32>,
 }
 
 impl AudioNodeProcessor for HardClipProcessor {
@@ -56,6 +107,8 @@ impl AudioNodeProcessor for HardClipProcessor {
         outputs: &mut [&mut [f32]],
         proc_info: ProcInfo,
     ) {
+        let threshold_gain = self.threshold_gain.load(Ordering::Relaxed);
+
         // Provide an optimized loop for stereo.
         if inputs.len() == 2
             && outputs.len() == 2
@@ -68,12 +121,8 @@ impl AudioNodeProcessor for HardClipProcessor {
             assert!(frames <= inputs[1].len());
 
             for i in 0..frames {
-                outputs[0][i] = inputs[0][i]
-                    .min(self.threshold_gain)
-                    .max(-self.threshold_gain);
-                outputs[1][i] = inputs[1][i]
-                    .min(self.threshold_gain)
-                    .max(-self.threshold_gain);
+                outputs[0][i] = inputs[0][i].min(threshold_gain).max(-threshold_gain);
+                outputs[1][i] = inputs[1][i].min(threshold_gain).max(-threshold_gain);
             }
 
             return;
@@ -86,7 +135,7 @@ impl AudioNodeProcessor for HardClipProcessor {
             }
 
             for (out_s, in_s) in output.iter_mut().zip(input.iter()) {
-                *out_s = in_s.min(self.threshold_gain).max(-self.threshold_gain);
+                *out_s = in_s.min(threshold_gain).max(-threshold_gain);
             }
         }
 
@@ -0,0 +1,660 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    param::smoother::ParamSmoother,
+    sample_resource::SampleResource,
+};
+
+use super::{
+    spatializer::{dot, length, normalize},
+    DistanceAttenuation, Listener,
+};
+
+const CHANNEL_CAPACITY: usize = 16;
+
+/// The duration of the crossfade between two HRIRs when the direction
+/// changes
+const CROSSFADE_SECS: f32 = 20.0 / 1000.0;
+
+/// Changes in direction smaller than this (the cosine of about a quarter
+/// of a degree) do not update the HRIRs.
+const MIN_DIRECTION_CHANGE_COS: f32 = 0.99999;
+
+/// The direction of a measurement in an [`HrirTable`], in degrees
+///
+/// This uses the same convention as the SOFA format: the azimuth is
+/// measured counterclockwise from the front (so `90.0` is to the left),
+/// and the elevation is measured upwards from the horizontal plane (so
+/// `90.0` is straight up).
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct HrirDirection {
+    pub azimuth: f32,
+    pub elevation: f32,
+}
+
+impl HrirDirection {
+    /// The unit vector of this direction, in the coordinate system of the
+    /// listener (where `+X` is to the right, `+Y` is up, and `+Z` is
+    /// forward)
+    fn to_vector(self) -> [f32; 3] {
+        let (az_sin, az_cos) = self.azimuth.to_radians().sin_cos();
+        let (el_sin, el_cos) = self.elevation.to_radians().sin_cos();
+
+        [-az_sin * el_cos, el_sin, az_cos * el_cos]
+    }
+}
+
+/// An error while creating an [`HrirTable`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HrtfError {
+    /// The resource must have 2 channels (the left and right ear).
+    InvalidNumChannels(usize),
+    /// The length of the resource must equal the length of each HRIR
+    /// times the number of directions.
+    LengthMismatch { len_frames: u64, expected: u64 },
+    /// There must be at least one direction.
+    NoDirections,
+}
+
+impl std::error::Error for HrtfError {}
+
+impl std::fmt::Display for HrtfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidNumChannels(n) => {
+                write!(f, "An HRIR resource must have 2 channels, got {}", n)
+            }
+            Self::LengthMismatch {
+                len_frames,
+                expected,
+            } => write!(
+                f,
+                "The length of the HRIR resource does not match the number of directions. Expected {} frames, got {}",
+                expected, len_frames
+            ),
+            Self::NoDirections => write!(f, "An HRIR table must have at least one direction"),
+        }
+    }
+}
+
+/// A table of head-related impulse responses (HRIRs) measured from
+/// different directions
+///
+/// A table can be shared between any number of [`HrtfNode`]s.
+pub struct HrirTable {
+    sample_rate: u32,
+    hrir_len: usize,
+    directions: Vec<[f32; 3]>,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+impl HrirTable {
+    /// Create a table of HRIRs from a resource.
+    ///
+    /// The resource must have 2 channels (the left and right ear), and it
+    /// must contain the HRIRs of each direction one after another (so the
+    /// HRIR of `directions[i]` starts at frame `i * hrir_len`).
+    ///
+    /// * `sample_rate` - The sample rate the HRIRs were measured at. This
+    ///   must match the sample rate of the stream.
+    pub fn new<S: SampleResource>(
+        resource: &S,
+        sample_rate: u32,
+        hrir_len: usize,
+        directions: &[HrirDirection],
+    ) -> Result<Self, HrtfError> {
+        let num_channels = resource.num_channels().get();
+        if num_channels != 2 {
+            return Err(HrtfError::InvalidNumChannels(num_channels));
+        }
+
+        if directions.is_empty() {
+            return Err(HrtfError::NoDirections);
+        }
+
+        let expected = (hrir_len * directions.len()) as u64;
+        if resource.len_frames() != expected || hrir_len == 0 {
+            return Err(HrtfError::LengthMismatch {
+                len_frames: resource.len_frames(),
+                expected,
+            });
+        }
+
+        let len_frames = expected as usize;
+        let mut left = vec![0.0; len_frames];
+        let mut right = vec![0.0; len_frames];
+        resource.fill_buffers(
+            &mut [left.as_mut_slice(), right.as_mut_slice()],
+            0..len_frames,
+            0,
+        );
+
+        Ok(Self {
+            sample_rate,
+            hrir_len,
+            directions: directions.iter().map(|d| d.to_vector()).collect(),
+            left,
+            right,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The length of each HRIR in frames
+    pub fn hrir_len(&self) -> usize {
+        self.hrir_len
+    }
+
+    pub fn num_directions(&self) -> usize {
+        self.directions.len()
+    }
+
+    /// Interpolate between the HRIRs of the (up to three) measured
+    /// directions nearest to the given direction.
+    ///
+    /// The direction is in the coordinate system of the listener (where
+    /// `+X` is to the right, `+Y` is up, and `+Z` is forward), and it does
+    /// not need to be normalized.
+    ///
+    /// `left` and `right` must have a length of [`HrirTable::hrir_len`].
+    pub fn interpolate(&self, direction: [f32; 3], left: &mut [f32], right: &mut [f32]) {
+        let direction = normalize(direction);
+
+        // (angle, index) of the nearest directions
+        let mut nearest = [(f32::MAX, usize::MAX); 3];
+        for (i, d) in self.directions.iter().enumerate() {
+            let angle = dot(direction, *d).clamp(-1.0, 1.0).acos();
+
+            if angle < nearest[2].0 {
+                nearest[2] = (angle, i);
+                nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+            }
+        }
+
+        left.fill(0.0);
+        right.fill(0.0);
+
+        // Use the exact measurement if there is one.
+        let nearest = if nearest[0].0 < 0.0001 {
+            &nearest[..1]
+        } else {
+            &nearest[..]
+        };
+
+        let total_weight: f32 = nearest
+            .iter()
+            .filter(|(_, i)| *i != usize::MAX)
+            .map(|(angle, _)| angle.max(0.0001).recip())
+            .sum();
+
+        for &(angle, i) in nearest.iter().filter(|(_, i)| *i != usize::MAX) {
+            let weight = angle.max(0.0001).recip() / total_weight;
+            let range = i * self.hrir_len..(i + 1) * self.hrir_len;
+
+            for (out, &s) in left.iter_mut().zip(self.left[range.clone()].iter()) {
+                *out += s * weight;
+            }
+            for (out, &s) in right.iter_mut().zip(self.right[range].iter()) {
+                *out += s * weight;
+            }
+        }
+    }
+}
+
+/// A pair of interpolated HRIRs for the left and right ear
+struct HrirPair {
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+enum ProcessorToNodeMsg {
+    ReturnHrirs(#[allow(unused)] Box<HrirPair>),
+}
+
+struct ActiveState {
+    to_processor_tx: rtrb::Producer<Box<HrirPair>>,
+    from_processor_rx: rtrb::Consumer<ProcessorToNodeMsg>,
+}
+
+/// A node which renders a mono sound binaurally (for headphones) by
+/// convolving it with head-related impulse responses (HRIRs)
+///
+/// The HRIRs are interpolated from an [`HrirTable`] on the main thread
+/// whenever the direction of the emitter relative to the listener changes,
+/// and the node crossfades between the old and new HRIRs.
+pub struct HrtfNode {
+    table: Arc<HrirTable>,
+    // TODO: Find a good solution for webassembly.
+    raw_gain: Arc<AtomicF32>,
+    emitter_position: [f32; 3],
+    listener: Listener,
+    attenuation: DistanceAttenuation,
+
+    active_state: Option<ActiveState>,
+    /// The direction of the HRIRs which were last sent to the processor
+    sent_direction: Option<[f32; 3]>,
+}
+
+impl HrtfNode {
+    pub fn new(table: Arc<HrirTable>, attenuation: DistanceAttenuation) -> Self {
+        let mut node = Self {
+            table,
+            raw_gain: Arc::new(AtomicF32::new(1.0)),
+            emitter_position: [0.0; 3],
+            listener: Listener::default(),
+            attenuation,
+            active_state: None,
+            sent_direction: None,
+        };
+        node.update_gain();
+        node
+    }
+
+    pub fn table(&self) -> &Arc<HrirTable> {
+        &self.table
+    }
+
+    pub fn emitter_position(&self) -> [f32; 3] {
+        self.emitter_position
+    }
+
+    pub fn set_emitter_position(&mut self, position: [f32; 3]) {
+        self.emitter_position = position;
+        self.update_gain();
+        self.send_hrirs();
+    }
+
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+        self.update_gain();
+        self.send_hrirs();
+    }
+
+    pub fn attenuation(&self) -> &DistanceAttenuation {
+        &self.attenuation
+    }
+
+    pub fn set_attenuation(&mut self, attenuation: DistanceAttenuation) {
+        self.attenuation = attenuation;
+        self.update_gain();
+    }
+
+    /// The direction of the emitter in the coordinate system of the
+    /// listener
+    fn direction(&self) -> [f32; 3] {
+        let relative = self.listener.relative_position(self.emitter_position);
+
+        if length(relative) < 0.000001 {
+            // Treat a sound at the position of the listener as being in
+            // front of the listener.
+            [0.0, 0.0, 1.0]
+        } else {
+            normalize(relative)
+        }
+    }
+
+    fn update_gain(&mut self) {
+        let distance = length(self.listener.relative_position(self.emitter_position));
+
+        self.raw_gain
+            .store(self.attenuation.gain(distance), Ordering::Relaxed);
+    }
+
+    fn interpolated_hrirs(&self, direction: [f32; 3]) -> Box<HrirPair> {
+        let mut hrirs = Box::new(HrirPair {
+            left: vec![0.0; self.table.hrir_len()],
+            right: vec![0.0; self.table.hrir_len()],
+        });
+        self.table
+            .interpolate(direction, &mut hrirs.left, &mut hrirs.right);
+        hrirs
+    }
+
+    /// Send new HRIRs to the processor if the direction has changed.
+    fn send_hrirs(&mut self) {
+        if self.active_state.is_none() {
+            return;
+        }
+
+        let direction = self.direction();
+        if let Some(sent_direction) = self.sent_direction {
+            if dot(direction, sent_direction) > MIN_DIRECTION_CHANGE_COS {
+                return;
+            }
+        }
+
+        let hrirs = self.interpolated_hrirs(direction);
+
+        // If the channel is full, then try again on the next update.
+        if let Some(state) = &mut self.active_state {
+            if state.to_processor_tx.push(hrirs).is_ok() {
+                self.sent_direction = Some(direction);
+            }
+        }
+    }
+}
+
+impl AudioNode for HrtfNode {
+    fn debug_name(&self) -> &'static str {
+        "hrtf"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 1,
+            num_max_supported_inputs: 1,
+            num_min_supported_outputs: 2,
+            num_max_supported_outputs: 2,
+            updates: true,
+            split_at_events: false,
+            // The HRIRs may still be ringing when the input becomes silent.
+            silent_when_inputs_silent: false,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if num_inputs != 1 || num_outputs != 2 {
+            return Err(format!(
+                "An Hrtf node must have 1 input and 2 outputs. Got num_inputs: {}, num_outputs: {}",
+                num_inputs, num_outputs
+            )
+            .into());
+        }
+
+        if sample_rate != self.table.sample_rate() {
+            return Err(format!("The sample rate of the HRIR table ({}) does not match the sample rate of the stream ({})", self.table.sample_rate(), sample_rate).into());
+        }
+
+        let (to_processor_tx, from_node_rx) =
+            rtrb::RingBuffer::<Box<HrirPair>>::new(CHANNEL_CAPACITY);
+        let (to_node_tx, from_processor_rx) =
+            rtrb::RingBuffer::<ProcessorToNodeMsg>::new(CHANNEL_CAPACITY);
+
+        let direction = self.direction();
+        let hrirs = self.interpolated_hrirs(direction);
+        let hrir_len = self.table.hrir_len();
+
+        self.active_state = Some(ActiveState {
+            to_processor_tx,
+            from_processor_rx,
+        });
+        self.sent_direction = Some(direction);
+
+        Ok(Box::new(HrtfProcessor {
+            raw_gain: Arc::clone(&self.raw_gain),
+            gain_smoother: ParamSmoother::new(
+                self.raw_gain.load(Ordering::Relaxed),
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            ),
+            hrirs,
+            fading_out: None,
+            crossfade_frames: (CROSSFADE_SECS * sample_rate as f32).round().max(1.0) as usize,
+            crossfade_progress: 0,
+            history: vec![0.0; hrir_len - 1 + max_block_frames],
+            fade_buf: vec![0.0; max_block_frames],
+            silent_frames: 0,
+            is_cleared: true,
+            from_node_rx,
+            to_node_tx,
+        }))
+    }
+
+    fn deactivate(&mut self, _processor: Option<Box<dyn AudioNodeProcessor>>) {
+        self.active_state = None;
+        self.sent_direction = None;
+    }
+
+    fn update(&mut self) {
+        if let Some(state) = &mut self.active_state {
+            // Drop the old HRIRs here so they are not deallocated in the
+            // audio thread.
+            while let Ok(msg) = state.from_processor_rx.pop() {
+                match msg {
+                    ProcessorToNodeMsg::ReturnHrirs(_hrirs) => {}
+                }
+            }
+        }
+
+        // Retry sending the HRIRs in case the channel was full.
+        self.send_hrirs();
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for HrtfNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+struct HrtfProcessor {
+    raw_gain: Arc<AtomicF32>,
+    gain_smoother: ParamSmoother,
+
+    hrirs: Box<HrirPair>,
+    /// The previous HRIRs while crossfading to new HRIRs
+    fading_out: Option<Box<HrirPair>>,
+    crossfade_frames: usize,
+    crossfade_progress: usize,
+
+    /// The last `hrir_len - 1` input frames followed by the current block
+    history: Vec<f32>,
+    fade_buf: Vec<f32>,
+
+    /// The number of consecutive frames in which the input was silent
+    silent_frames: usize,
+    /// Whether the input history contains only silence
+    is_cleared: bool,
+
+    from_node_rx: rtrb::Consumer<Box<HrirPair>>,
+    to_node_tx: rtrb::Producer<ProcessorToNodeMsg>,
+}
+
+impl HrtfProcessor {
+    fn return_hrirs(&mut self, hrirs: Box<HrirPair>) {
+        // The caller makes sure that there is room in the channel, so the
+        // HRIRs are never dropped in the audio thread.
+        let _ = self.to_node_tx.push(ProcessorToNodeMsg::ReturnHrirs(hrirs));
+    }
+}
+
+/// Convolve the history with the impulse response directly.
+fn convolve(hrir: &[f32], history: &[f32], out: &mut [f32]) {
+    let newest_offset = hrir.len() - 1;
+
+    for (i, out_s) in out.iter_mut().enumerate() {
+        let newest = newest_offset + i;
+
+        let mut sum = 0.0;
+        for (j, &h) in hrir.iter().enumerate() {
+            sum += h * history[newest - j];
+        }
+
+        *out_s = sum;
+    }
+}
+
+impl AudioNodeProcessor for HrtfProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        // Wait for the current crossfade to finish before switching to
+        // new HRIRs, and skip to the most recent ones. Every skipped pair is
+        // returned to the node, so stop while there is still room in the
+        // channel to return the pair that is faded out, and leave the rest
+        // for the next block.
+        if self.fading_out.is_none() {
+            let mut latest = None;
+            while self.to_node_tx.slots() > 1 {
+                let Ok(hrirs) = self.from_node_rx.pop() else {
+                    break;
+                };
+
+                if let Some(skipped) = latest.replace(hrirs) {
+                    self.return_hrirs(skipped);
+                }
+            }
+
+            if let Some(hrirs) = latest {
+                self.fading_out = Some(std::mem::replace(&mut self.hrirs, hrirs));
+                self.crossfade_progress = 0;
+            }
+        }
+
+        let raw_gain = self.raw_gain.load(Ordering::Relaxed);
+        let hrir_len = self.hrirs.left.len();
+
+        if proc_info.in_silence_mask.all_channels_silent(1) {
+            self.silent_frames += frames;
+        } else {
+            self.silent_frames = 0;
+            self.is_cleared = false;
+        }
+
+        if self.silent_frames >= hrir_len && self.fading_out.is_none() {
+            // The tail has fully decayed, so there is no need to process
+            // until the input is no longer silent. Also reset the filter
+            // since it doesn't need to smooth anything.
+            if !self.is_cleared {
+                self.history.fill(0.0);
+                self.is_cleared = true;
+            }
+
+            self.gain_smoother.reset(raw_gain);
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        }
+
+        let history_len = hrir_len - 1 + frames;
+        self.history[hrir_len - 1..history_len].copy_from_slice(&inputs[0][..frames]);
+        let history = &self.history[..history_len];
+
+        let gain = self.gain_smoother.set_and_process(raw_gain, frames);
+
+        for (output, hrir, old_hrir) in [
+            (
+                0,
+                &self.hrirs.left,
+                self.fading_out.as_ref().map(|h| &h.left),
+            ),
+            (
+                1,
+                &self.hrirs.right,
+                self.fading_out.as_ref().map(|h| &h.right),
+            ),
+        ] {
+            let out = &mut outputs[output][..frames];
+            convolve(hrir, history, out);
+
+            if let Some(old_hrir) = old_hrir {
+                let fade_buf = &mut self.fade_buf[..frames];
+                convolve(old_hrir, history, fade_buf);
+
+                let step = (self.crossfade_frames as f32).recip();
+                for (i, (out_s, &old_s)) in out.iter_mut().zip(fade_buf.iter()).enumerate() {
+                    let mix = ((self.crossfade_progress + i) as f32 * step).min(1.0);
+                    *out_s = *out_s * mix + old_s * (1.0 - mix);
+                }
+            }
+
+            // Hint to the compiler to optimize loop.
+            assert!(frames <= gain.values.len());
+
+            for (i, out_s) in out.iter_mut().enumerate() {
+                *out_s *= gain[i];
+            }
+        }
+
+        if self.fading_out.is_some() {
+            self.crossfade_progress += frames;
+
+            // If there is no room in the channel, keep the old HRIRs around
+            // (at zero gain) until there is.
+            if self.crossfade_progress >= self.crossfade_frames && self.to_node_tx.slots() > 0 {
+                if let Some(fading_out) = self.fading_out.take() {
+                    self.return_hrirs(fading_out);
+                }
+            }
+        }
+
+        // Keep the most recent input frames for the next block.
+        self.history.copy_within(frames..history_len, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::TestGraphCtx, graph::AudioGraphConfig};
+
+    #[test]
+    fn interpolate_and_render() {
+        // Four directions on the horizontal plane, where each HRIR is a
+        // single impulse with a different delay and gain per ear.
+        let directions = [0.0, 90.0, 180.0, 270.0].map(|azimuth| HrirDirection {
+            azimuth,
+            elevation: 0.0,
+        });
+        let hrir_len = 4;
+        let mut left = vec![0.0; hrir_len * 4];
+        let mut right = vec![0.0; hrir_len * 4];
+        for i in 0..4 {
+            left[i * hrir_len + i] = 1.0;
+            right[i * hrir_len] = 0.25 * (i + 1) as f32;
+        }
+        let table =
+            Arc::new(HrirTable::new(&vec![left, right], 48_000, hrir_len, &directions).unwrap());
+
+        let mut l = vec![0.0; hrir_len];
+        let mut r = vec![0.0; hrir_len];
+
+        // Exactly to the left of the listener.
+        table.interpolate([-1.0, 0.0, 0.0], &mut l, &mut r);
+        assert_eq!(l, [0.0, 1.0, 0.0, 0.0]);
+        assert_eq!(r, [0.5, 0.0, 0.0, 0.0]);
+
+        // Halfway between the front and the left.
+        table.interpolate([-1.0, 0.0, 1.0], &mut l, &mut r);
+        assert!((l[0] - l[1]).abs() < 0.0001 && l[0] > 0.3);
+
+        let mut node = HrtfNode::new(table, DistanceAttenuation::default());
+        node.set_emitter_position([0.0, 0.0, 1.0]);
+        let (mut test_cx, _) = TestGraphCtx::with_node(
+            AudioGraphConfig {
+                num_graph_inputs: 1,
+                ..Default::default()
+            },
+            1,
+            2,
+            node,
+        );
+
+        // The emitter is behind the listener (the default listener faces
+        // -Z).
+        let mut input = vec![0.0; 64];
+        input[0] = 1.0;
+        let mut output = vec![0.0; 64 * 2];
+        test_cx.process(&input, &mut output);
+        assert_eq!(&output[..6], &[0.0, 0.75, 0.0, 0.0, 1.0, 0.0]);
+    }
+}
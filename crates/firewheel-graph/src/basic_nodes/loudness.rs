@@ -0,0 +1,859 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use atomic_float::AtomicF32;
+use firewheel_core::node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo};
+
+/// The length of a gating sub-block in seconds. Momentary and short-term
+/// loudness are measured over 4 and 30 of these, respectively.
+const SUB_BLOCK_SECS: f64 = 0.1;
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+/// Blocks quieter than this are ignored by the integrated loudness and
+/// the loudness range.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// The relative gate of the integrated loudness in LU
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+/// The relative gate of the loudness range in LU
+const LRA_RELATIVE_GATE_LU: f64 = -20.0;
+
+/// The range and resolution of the histograms used to gate the blocks.
+/// Using histograms means that no memory needs to be allocated no matter
+/// how long the measurement runs for.
+const HISTOGRAM_MIN_LUFS: f64 = ABSOLUTE_GATE_LUFS;
+const HISTOGRAM_MAX_LUFS: f64 = 10.0;
+const HISTOGRAM_BINS_PER_LU: f64 = 10.0;
+const HISTOGRAM_NUM_BINS: usize =
+    ((HISTOGRAM_MAX_LUFS - HISTOGRAM_MIN_LUFS) * HISTOGRAM_BINS_PER_LU) as usize;
+
+/// The number of taps in each phase of the true-peak interpolator
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// A node which passes audio through unchanged while measuring its
+/// loudness according to ITU-R BS.1770-4 and EBU R128
+///
+/// This measures:
+/// * The momentary (400 ms), short-term (3 s), and gated integrated
+///   loudness in LUFS
+/// * The loudness range (LRA) in LU as defined by EBU Tech 3342
+/// * The true-peak level of each channel in dBTP, using 4x oversampling
+///   (2x at 96 kHz, none at 192 kHz and above)
+///
+/// The readings are updated every 100 ms. A reading which does not have
+/// a value yet (or which is measuring digital silence) is
+/// `f32::NEG_INFINITY`.
+///
+/// By default every channel has a weight of `1.0`, except when the node
+/// has exactly 6 channels, in which case they are assumed to be in the 5.1
+/// order (L, R, C, LFE, Ls, Rs) and the weights from BS.1770 are used.
+pub struct LoudnessMeterNode {
+    channel_weights: Option<Vec<f32>>,
+    // TODO: Find a good solution for webassembly.
+    readings: Arc<LoudnessReadings>,
+}
+
+impl LoudnessMeterNode {
+    pub fn new() -> Self {
+        Self {
+            channel_weights: None,
+            readings: Arc::new(LoudnessReadings::new(0)),
+        }
+    }
+
+    /// Set the weight of each channel when summing the loudness of all
+    /// channels (i.e. `0.0` for an LFE channel, `1.41` for surround
+    /// channels).
+    ///
+    /// Channels past the end of `weights` get a weight of `1.0`. Passing
+    /// `None` uses the default weights. This takes effect the next time
+    /// the node is activated.
+    pub fn set_channel_weights(&mut self, weights: Option<Vec<f32>>) {
+        self.channel_weights = weights;
+    }
+
+    /// The number of channels being measured (`0` if the node has not
+    /// been activated yet)
+    pub fn num_channels(&self) -> usize {
+        self.readings.true_peak.len()
+    }
+
+    /// The momentary loudness (over the last 400 ms) in LUFS
+    pub fn momentary_lufs(&self) -> f32 {
+        self.readings.momentary.load(Ordering::Relaxed)
+    }
+
+    /// The short-term loudness (over the last 3 seconds) in LUFS
+    pub fn short_term_lufs(&self) -> f32 {
+        self.readings.short_term.load(Ordering::Relaxed)
+    }
+
+    /// The gated integrated loudness since the node was activated (or
+    /// since the last call to [`LoudnessMeterNode::reset`]) in LUFS
+    pub fn integrated_lufs(&self) -> f32 {
+        self.readings.integrated.load(Ordering::Relaxed)
+    }
+
+    /// The loudness range since the node was activated (or since the last
+    /// call to [`LoudnessMeterNode::reset`]) in LU
+    pub fn loudness_range_lu(&self) -> f32 {
+        self.readings.loudness_range.load(Ordering::Relaxed)
+    }
+
+    /// The true-peak level of the given channel in dBTP
+    ///
+    /// Returns `f32::NEG_INFINITY` if the channel does not exist.
+    pub fn true_peak_dbtp(&self, channel: usize) -> f32 {
+        self.readings
+            .true_peak
+            .get(channel)
+            .map(|p| p.load(Ordering::Relaxed))
+            .unwrap_or(f32::NEG_INFINITY)
+    }
+
+    /// The highest true-peak level of all channels in dBTP
+    pub fn max_true_peak_dbtp(&self) -> f32 {
+        self.readings
+            .true_peak
+            .iter()
+            .map(|p| p.load(Ordering::Relaxed))
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    /// Restart the integrated loudness, loudness range, and true-peak
+    /// measurements.
+    ///
+    /// This takes effect at the start of the next processed block.
+    pub fn reset(&self) {
+        self.readings.reset.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Default for LoudnessMeterNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioNode for LoudnessMeterNode {
+    fn debug_name(&self) -> &'static str {
+        "loudness_meter"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 1,
+            num_max_supported_inputs: 64,
+            num_min_supported_outputs: 1,
+            num_max_supported_outputs: 64,
+            updates: false,
+            split_at_events: false,
+            // Silence still counts towards the measurement windows.
+            silent_when_inputs_silent: false,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        _max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if num_inputs != num_outputs {
+            return Err(format!("The number of inputs on a LoudnessMeter node must equal the number of outputs. Got num_inputs: {}, num_outputs: {}", num_inputs, num_outputs).into());
+        }
+
+        let weights: Vec<f64> = (0..num_inputs)
+            .map(|ch| match &self.channel_weights {
+                Some(weights) => weights.get(ch).copied().unwrap_or(1.0) as f64,
+                None if num_inputs == 6 => [1.0, 1.0, 1.0, 0.0, 1.41, 1.41][ch],
+                None => 1.0,
+            })
+            .collect();
+
+        self.readings = Arc::new(LoudnessReadings::new(num_inputs));
+
+        let oversampling = if sample_rate < 96_000 {
+            4
+        } else if sample_rate < 192_000 {
+            2
+        } else {
+            1
+        };
+
+        Ok(Box::new(LoudnessMeterProcessor {
+            readings: Arc::clone(&self.readings),
+            channels: weights
+                .into_iter()
+                .map(|weight| ChannelState {
+                    k_weighting: KWeighting::new(sample_rate as f64),
+                    weight,
+                    true_peak: 0.0,
+                    history: [0.0; TRUE_PEAK_TAPS],
+                })
+                .collect(),
+            interpolator: TruePeakInterpolator::new(oversampling),
+            sub_block_frames: ((sample_rate as f64 * SUB_BLOCK_SECS).round() as usize).max(1),
+            sub_block_frames_done: 0,
+            sub_block_sum: 0.0,
+            sub_blocks: [0.0; SHORT_TERM_SUB_BLOCKS],
+            sub_blocks_pos: 0,
+            num_sub_blocks: 0,
+            integrated_hist: vec![HistogramBin::default(); HISTOGRAM_NUM_BINS],
+            short_term_hist: vec![HistogramBin::default(); HISTOGRAM_NUM_BINS],
+        }))
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for LoudnessMeterNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+struct LoudnessReadings {
+    momentary: AtomicF32,
+    short_term: AtomicF32,
+    integrated: AtomicF32,
+    loudness_range: AtomicF32,
+    true_peak: Vec<AtomicF32>,
+    reset: AtomicBool,
+}
+
+impl LoudnessReadings {
+    fn new(num_channels: usize) -> Self {
+        Self {
+            momentary: AtomicF32::new(f32::NEG_INFINITY),
+            short_term: AtomicF32::new(f32::NEG_INFINITY),
+            integrated: AtomicF32::new(f32::NEG_INFINITY),
+            loudness_range: AtomicF32::new(0.0),
+            true_peak: (0..num_channels)
+                .map(|_| AtomicF32::new(f32::NEG_INFINITY))
+                .collect(),
+            reset: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Convert a mean square value into loudness in LUFS.
+fn energy_to_lufs(energy: f64) -> f64 {
+    if energy > 0.0 {
+        -0.691 + 10.0 * energy.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+fn histogram_bin(lufs: f64) -> usize {
+    (((lufs - HISTOGRAM_MIN_LUFS) * HISTOGRAM_BINS_PER_LU).max(0.0) as usize)
+        .min(HISTOGRAM_NUM_BINS - 1)
+}
+
+fn histogram_bin_lufs(bin: usize) -> f64 {
+    HISTOGRAM_MIN_LUFS + (bin as f64 + 0.5) / HISTOGRAM_BINS_PER_LU
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct HistogramBin {
+    count: u64,
+    /// The sum of the energies of all blocks in this bin
+    energy: f64,
+}
+
+impl HistogramBin {
+    fn add(&mut self, energy: f64) {
+        self.count += 1;
+        self.energy += energy;
+    }
+}
+
+/// Returns the mean energy and the number of blocks in the bins starting
+/// at `start_bin`.
+fn histogram_mean(hist: &[HistogramBin], start_bin: usize) -> (f64, u64) {
+    let (energy, count) = hist[start_bin..]
+        .iter()
+        .fold((0.0, 0), |(e, c), bin| (e + bin.energy, c + bin.count));
+
+    if count == 0 {
+        (0.0, 0)
+    } else {
+        (energy / count as f64, count)
+    }
+}
+
+/// A biquad filter in transposed direct form II
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    fn is_settled(&self) -> bool {
+        self.z1.abs() < 1e-12 && self.z2.abs() < 1e-12
+    }
+
+    fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+/// The K-weighting pre-filter from BS.1770 (a high shelf followed by a
+/// highpass filter)
+///
+/// The coefficients are derived from the analog prototypes so that any
+/// sample rate can be used. At 48 kHz they match the coefficients given in
+/// the specification.
+#[derive(Debug, Clone, Copy)]
+struct KWeighting {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        let shelf = {
+            let f0 = 1681.974450955533;
+            let gain_db = 3.999843853973347;
+            let q = 0.7071752369554196;
+
+            let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+            let vh = 10.0f64.powf(gain_db / 20.0);
+            let vb = vh.powf(0.4996667741545416);
+
+            Biquad::new(
+                [
+                    vh + vb * k / q + k * k,
+                    2.0 * (k * k - vh),
+                    vh - vb * k / q + k * k,
+                ],
+                [
+                    1.0 + k / q + k * k,
+                    2.0 * (k * k - 1.0),
+                    1.0 - k / q + k * k,
+                ],
+            )
+        };
+
+        let highpass = {
+            let f0 = 38.13547087602444;
+            let q = 0.5003270373238773;
+
+            let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+
+            Biquad::new(
+                [1.0, -2.0, 1.0],
+                [
+                    1.0 + k / q + k * k,
+                    2.0 * (k * k - 1.0),
+                    1.0 - k / q + k * k,
+                ],
+            )
+        };
+
+        Self { shelf, highpass }
+    }
+
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        self.highpass.process(self.shelf.process(x))
+    }
+
+    fn is_settled(&self) -> bool {
+        self.shelf.is_settled() && self.highpass.is_settled()
+    }
+
+    fn reset(&mut self) {
+        self.shelf.reset();
+        self.highpass.reset();
+    }
+}
+
+/// A polyphase windowed-sinc interpolator used to find the peaks in
+/// between samples
+struct TruePeakInterpolator {
+    /// The coefficients of each phase, with the coefficient for the
+    /// newest sample first. This is empty if no oversampling is done.
+    phases: Vec<[f32; TRUE_PEAK_TAPS]>,
+}
+
+impl TruePeakInterpolator {
+    fn new(oversampling: usize) -> Self {
+        if oversampling <= 1 {
+            return Self { phases: Vec::new() };
+        }
+
+        let len = TRUE_PEAK_TAPS * oversampling;
+        let center = (TRUE_PEAK_TAPS / 2) as f64;
+
+        let phases = (0..oversampling)
+            .map(|phase| {
+                let mut coeffs = [0.0; TRUE_PEAK_TAPS];
+
+                for (tap, c) in coeffs.iter_mut().enumerate() {
+                    // The position of this tap in the full-length filter
+                    let n = tap * oversampling + phase;
+                    let x = tap as f64 + phase as f64 / oversampling as f64 - center;
+
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x)
+                    };
+                    // Hann window
+                    let window =
+                        0.5 - 0.5 * (std::f64::consts::TAU * (n as f64 + 0.5) / len as f64).cos();
+
+                    *c = (sinc * window) as f32;
+                }
+
+                // Normalize each phase to unity gain at DC.
+                let sum: f32 = coeffs.iter().sum();
+                for c in coeffs.iter_mut() {
+                    *c /= sum;
+                }
+
+                coeffs
+            })
+            .collect();
+
+        Self { phases }
+    }
+}
+
+struct ChannelState {
+    k_weighting: KWeighting,
+    weight: f64,
+    /// The highest true-peak level as a raw gain value
+    true_peak: f32,
+    /// The most recent input samples, with the newest sample first
+    history: [f32; TRUE_PEAK_TAPS],
+}
+
+impl ChannelState {
+    /// Filter the given samples and return their weighted sum of squares.
+    ///
+    /// If `input` is `None`, then the input is silent.
+    fn process(
+        &mut self,
+        input: Option<&[f32]>,
+        frames: usize,
+        interpolator: &TruePeakInterpolator,
+    ) -> f64 {
+        if input.is_none()
+            && self.k_weighting.is_settled()
+            && self.history.iter().all(|&s| s == 0.0)
+        {
+            return 0.0;
+        }
+
+        let mut sum = 0.0;
+
+        for i in 0..frames {
+            let x = input.map(|input| input[i]).unwrap_or(0.0);
+
+            let y = self.k_weighting.process(x as f64);
+            sum += y * y;
+
+            self.history.copy_within(0..TRUE_PEAK_TAPS - 1, 1);
+            self.history[0] = x;
+
+            let mut peak = x.abs();
+            for coeffs in interpolator.phases.iter() {
+                let s: f32 = coeffs
+                    .iter()
+                    .zip(self.history.iter())
+                    .map(|(c, h)| c * h)
+                    .sum();
+                peak = peak.max(s.abs());
+            }
+            self.true_peak = self.true_peak.max(peak);
+        }
+
+        sum * self.weight
+    }
+}
+
+struct LoudnessMeterProcessor {
+    readings: Arc<LoudnessReadings>,
+    channels: Vec<ChannelState>,
+    interpolator: TruePeakInterpolator,
+
+    sub_block_frames: usize,
+    sub_block_frames_done: usize,
+    /// The weighted sum of squares of all channels in the current
+    /// sub-block
+    sub_block_sum: f64,
+    /// The mean square of the most recent sub-blocks
+    sub_blocks: [f64; SHORT_TERM_SUB_BLOCKS],
+    sub_blocks_pos: usize,
+    num_sub_blocks: usize,
+
+    /// The 400 ms blocks used for the integrated loudness
+    integrated_hist: Vec<HistogramBin>,
+    /// The 3 second blocks used for the loudness range
+    short_term_hist: Vec<HistogramBin>,
+}
+
+impl LoudnessMeterProcessor {
+    fn reset(&mut self) {
+        for ch in self.channels.iter_mut() {
+            ch.k_weighting.reset();
+            ch.true_peak = 0.0;
+            ch.history = [0.0; TRUE_PEAK_TAPS];
+        }
+
+        self.sub_block_frames_done = 0;
+        self.sub_block_sum = 0.0;
+        self.sub_blocks = [0.0; SHORT_TERM_SUB_BLOCKS];
+        self.sub_blocks_pos = 0;
+        self.num_sub_blocks = 0;
+        self.integrated_hist.fill(HistogramBin::default());
+        self.short_term_hist.fill(HistogramBin::default());
+
+        self.readings
+            .momentary
+            .store(f32::NEG_INFINITY, Ordering::Relaxed);
+        self.readings
+            .short_term
+            .store(f32::NEG_INFINITY, Ordering::Relaxed);
+        self.readings
+            .integrated
+            .store(f32::NEG_INFINITY, Ordering::Relaxed);
+        self.readings.loudness_range.store(0.0, Ordering::Relaxed);
+        for p in self.readings.true_peak.iter() {
+            p.store(f32::NEG_INFINITY, Ordering::Relaxed);
+        }
+    }
+
+    /// The mean energy of the most recent `num` sub-blocks
+    fn window_energy(&self, num: usize) -> f64 {
+        let sum: f64 = (1..=num)
+            .map(|i| {
+                self.sub_blocks
+                    [(self.sub_blocks_pos + SHORT_TERM_SUB_BLOCKS - i) % SHORT_TERM_SUB_BLOCKS]
+            })
+            .sum();
+
+        sum / num as f64
+    }
+
+    fn finish_sub_block(&mut self) {
+        self.sub_blocks[self.sub_blocks_pos] = self.sub_block_sum / self.sub_block_frames as f64;
+        self.sub_blocks_pos = (self.sub_blocks_pos + 1) % SHORT_TERM_SUB_BLOCKS;
+        self.num_sub_blocks = (self.num_sub_blocks + 1).min(SHORT_TERM_SUB_BLOCKS);
+        self.sub_block_sum = 0.0;
+        self.sub_block_frames_done = 0;
+
+        if self.num_sub_blocks >= MOMENTARY_SUB_BLOCKS {
+            let energy = self.window_energy(MOMENTARY_SUB_BLOCKS);
+            let lufs = energy_to_lufs(energy);
+
+            self.readings
+                .momentary
+                .store(lufs as f32, Ordering::Relaxed);
+
+            if lufs >= ABSOLUTE_GATE_LUFS {
+                self.integrated_hist[histogram_bin(lufs)].add(energy);
+            }
+        }
+
+        if self.num_sub_blocks >= SHORT_TERM_SUB_BLOCKS {
+            let energy = self.window_energy(SHORT_TERM_SUB_BLOCKS);
+            let lufs = energy_to_lufs(energy);
+
+            self.readings
+                .short_term
+                .store(lufs as f32, Ordering::Relaxed);
+
+            if lufs >= ABSOLUTE_GATE_LUFS {
+                self.short_term_hist[histogram_bin(lufs)].add(energy);
+            }
+        }
+
+        self.readings
+            .integrated
+            .store(self.integrated_lufs() as f32, Ordering::Relaxed);
+        self.readings
+            .loudness_range
+            .store(self.loudness_range_lu() as f32, Ordering::Relaxed);
+    }
+
+    fn integrated_lufs(&self) -> f64 {
+        let (abs_gated_energy, count) = histogram_mean(&self.integrated_hist, 0);
+        if count == 0 {
+            return f64::NEG_INFINITY;
+        }
+
+        let relative_gate = energy_to_lufs(abs_gated_energy) + INTEGRATED_RELATIVE_GATE_LU;
+        let (energy, _) = histogram_mean(&self.integrated_hist, histogram_bin(relative_gate));
+
+        energy_to_lufs(energy)
+    }
+
+    fn loudness_range_lu(&self) -> f64 {
+        let (abs_gated_energy, count) = histogram_mean(&self.short_term_hist, 0);
+        if count == 0 {
+            return 0.0;
+        }
+
+        let start_bin = histogram_bin(energy_to_lufs(abs_gated_energy) + LRA_RELATIVE_GATE_LU);
+        let gated = &self.short_term_hist[start_bin..];
+        let count: u64 = gated.iter().map(|bin| bin.count).sum();
+
+        let percentile = |p: f64| -> f64 {
+            let target = ((count - 1) as f64 * p).round() as u64;
+
+            let mut seen = 0;
+            for (i, bin) in gated.iter().enumerate() {
+                seen += bin.count;
+                if seen > target {
+                    return histogram_bin_lufs(start_bin + i);
+                }
+            }
+
+            histogram_bin_lufs(HISTOGRAM_NUM_BINS - 1)
+        };
+
+        percentile(0.95) - percentile(0.10)
+    }
+}
+
+impl AudioNodeProcessor for LoudnessMeterProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        if self.readings.reset.swap(false, Ordering::Relaxed) {
+            self.reset();
+        }
+
+        for (ch_i, (output, input)) in outputs.iter_mut().zip(inputs.iter()).enumerate() {
+            if proc_info.in_silence_mask.is_channel_silent(ch_i) {
+                output[..frames].fill(0.0);
+            } else {
+                output[..frames].copy_from_slice(&input[..frames]);
+            }
+        }
+        *proc_info.out_silence_mask = proc_info.in_silence_mask;
+
+        let mut frames_done = 0;
+        while frames_done < frames {
+            let block_frames =
+                (self.sub_block_frames - self.sub_block_frames_done).min(frames - frames_done);
+
+            for (ch_i, (ch, input)) in self.channels.iter_mut().zip(inputs.iter()).enumerate() {
+                let input = if proc_info.in_silence_mask.is_channel_silent(ch_i) {
+                    None
+                } else {
+                    Some(&input[frames_done..frames_done + block_frames])
+                };
+
+                self.sub_block_sum += ch.process(input, block_frames, &self.interpolator);
+            }
+
+            self.sub_block_frames_done += block_frames;
+            frames_done += block_frames;
+
+            if self.sub_block_frames_done == self.sub_block_frames {
+                self.finish_sub_block();
+            }
+        }
+
+        for (ch, reading) in self.channels.iter().zip(self.readings.true_peak.iter()) {
+            let dbtp = if ch.true_peak > 0.0 {
+                20.0 * ch.true_peak.log10()
+            } else {
+                f32::NEG_INFINITY
+            };
+            reading.store(dbtp, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firewheel_core::SilenceMask;
+
+    use super::*;
+    use crate::basic_nodes::process_block;
+
+    const SAMPLE_RATE: u32 = 16_000;
+    /// 100 ms, which is a whole number of periods of every test signal
+    const BLOCK_FRAMES: usize = 1_600;
+
+    /// Process the given block of each channel repeatedly for `secs`
+    /// seconds.
+    fn process(processor: &mut Box<dyn AudioNodeProcessor>, block: &[Vec<f32>], secs: f64) {
+        let mut outputs = vec![vec![0.0; BLOCK_FRAMES]; block.len()];
+        let inputs: Vec<&[f32]> = block.iter().map(|b| b.as_slice()).collect();
+
+        for _ in 0..(secs * SAMPLE_RATE as f64).round() as usize / BLOCK_FRAMES {
+            let mut outputs: Vec<&mut [f32]> =
+                outputs.iter_mut().map(|b| b.as_mut_slice()).collect();
+
+            process_block(
+                processor.as_mut(),
+                BLOCK_FRAMES,
+                &inputs,
+                &mut outputs,
+                SilenceMask::NONE_SILENT,
+                &mut [],
+            );
+        }
+    }
+
+    /// A block of a sine wave with the given peak level in dBFS in the
+    /// given channels, and silence in all other channels.
+    fn sine(
+        num_channels: usize,
+        channels: &[usize],
+        freq: f64,
+        phase: f64,
+        db: f64,
+    ) -> Vec<Vec<f32>> {
+        let amplitude = 10.0f64.powf(db / 20.0);
+        let sine: Vec<f32> = (0..BLOCK_FRAMES)
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE as f64;
+                (amplitude * (std::f64::consts::TAU * freq * t + phase).sin()) as f32
+            })
+            .collect();
+
+        (0..num_channels)
+            .map(|ch| {
+                if channels.contains(&ch) {
+                    sine.clone()
+                } else {
+                    vec![0.0; BLOCK_FRAMES]
+                }
+            })
+            .collect()
+    }
+
+    /// The loudness of a mono 1 kHz sine wave with the given peak level
+    fn sine_lufs(db: f32) -> f32 {
+        // The K-weighting filter boosts 1 kHz by about 0.7 dB, which
+        // almost cancels out the -0.691 dB offset in BS.1770, and the mean
+        // square of a sine wave is half of its peak squared.
+        db - 3.09
+    }
+
+    #[test]
+    fn loudness_range_of_two_levels() {
+        // EBU Tech 3342 test case: 20 seconds at one level followed by 20
+        // seconds 10 dB quieter has a loudness range of 10 LU.
+        let mut node = LoudnessMeterNode::new();
+        let mut processor = node.activate(SAMPLE_RATE, BLOCK_FRAMES, 1, 1).unwrap();
+
+        process(&mut processor, &sine(1, &[0], 1_000.0, 0.0, -20.0), 20.0);
+        assert!((node.short_term_lufs() - sine_lufs(-20.0)).abs() < 0.05);
+        assert_eq!(node.loudness_range_lu(), 0.0);
+
+        process(&mut processor, &sine(1, &[0], 1_000.0, 0.0, -30.0), 20.0);
+        assert!((node.short_term_lufs() - sine_lufs(-30.0)).abs() < 0.05);
+        assert!((node.loudness_range_lu() - 10.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn true_peak_between_samples() {
+        // A sine wave at a quarter of the sample rate which is sampled 45
+        // degrees away from its peaks, so every sample is 3 dB below the
+        // true peak.
+        let mut node = LoudnessMeterNode::new();
+        let mut processor = node.activate(SAMPLE_RATE, BLOCK_FRAMES, 1, 1).unwrap();
+
+        let block = sine(1, &[0], 4_000.0, std::f64::consts::FRAC_PI_4, -6.0);
+        let sample_peak = block[0].iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        assert!((20.0 * sample_peak.log10() + 9.01).abs() < 0.01);
+
+        process(&mut processor, &block, 0.5);
+        assert!((node.true_peak_dbtp(0) + 6.0).abs() < 0.2);
+        assert_eq!(node.max_true_peak_dbtp(), node.true_peak_dbtp(0));
+    }
+
+    #[test]
+    fn surround_channel_weights() {
+        // With 6 channels, the LFE channel is ignored and the surround
+        // channels are weighted by 1.41 (+1.5 dB).
+        let momentary_lufs = |channel: usize| {
+            let mut node = LoudnessMeterNode::new();
+            let mut processor = node.activate(SAMPLE_RATE, BLOCK_FRAMES, 6, 6).unwrap();
+            process(
+                &mut processor,
+                &sine(6, &[channel], 1_000.0, 0.0, -20.0),
+                1.0,
+            );
+            node.momentary_lufs()
+        };
+
+        for channel in 0..3 {
+            assert!((momentary_lufs(channel) - sine_lufs(-20.0)).abs() < 0.05);
+        }
+        assert_eq!(momentary_lufs(3), f32::NEG_INFINITY);
+        for channel in 4..6 {
+            assert!((momentary_lufs(channel) - sine_lufs(-20.0) - 1.49).abs() < 0.05);
+        }
+
+        // Custom weights replace the defaults.
+        let mut node = LoudnessMeterNode::new();
+        node.set_channel_weights(Some(vec![0.0]));
+        let mut processor = node.activate(SAMPLE_RATE, BLOCK_FRAMES, 6, 6).unwrap();
+        process(&mut processor, &sine(6, &[0, 3], 1_000.0, 0.0, -20.0), 1.0);
+        assert!((node.momentary_lufs() - sine_lufs(-20.0)).abs() < 0.05);
+    }
+
+    #[test]
+    fn reset_measurements() {
+        let mut node = LoudnessMeterNode::new();
+        let mut processor = node.activate(SAMPLE_RATE, BLOCK_FRAMES, 1, 1).unwrap();
+
+        process(&mut processor, &sine(1, &[0], 1_000.0, 0.0, -20.0), 5.0);
+        assert!((node.integrated_lufs() - sine_lufs(-20.0)).abs() < 0.05);
+
+        node.reset();
+        process(&mut processor, &sine(1, &[], 1_000.0, 0.0, 0.0), 0.1);
+        assert_eq!(node.momentary_lufs(), f32::NEG_INFINITY);
+        assert_eq!(node.short_term_lufs(), f32::NEG_INFINITY);
+        assert_eq!(node.integrated_lufs(), f32::NEG_INFINITY);
+        assert_eq!(node.loudness_range_lu(), 0.0);
+        assert_eq!(node.true_peak_dbtp(0), f32::NEG_INFINITY);
+
+        // Only the signal after the reset is measured.
+        process(&mut processor, &sine(1, &[0], 1_000.0, 0.0, -30.0), 5.0);
+        assert!((node.integrated_lufs() - sine_lufs(-30.0)).abs() < 0.05);
+        assert!((node.true_peak_dbtp(0) + 30.0).abs() < 0.1);
+    }
+}
@@ -0,0 +1,333 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    util::{db_to_gain, gain_to_db_clamped_neg_100_db},
+};
+
+/// Readings below this raw gain (-100 dB) are snapped to zero.
+const SILENCE_GAIN: f32 = 0.00001;
+
+/// The ballistics of a [`MeterNode`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterConfig {
+    /// How fast the peak readings fall in decibels per second
+    ///
+    /// By default this is set to `20.0`.
+    pub peak_decay_db_per_sec: f32,
+    /// How long the peak-hold reading is held before it starts to fall
+    ///
+    /// By default this is set to 1 second.
+    pub peak_hold_secs: f32,
+    /// The time constant of the RMS averaging window in seconds
+    ///
+    /// By default this is set to 300 milliseconds.
+    pub rms_window_secs: f32,
+}
+
+impl Default for MeterConfig {
+    fn default() -> Self {
+        Self {
+            peak_decay_db_per_sec: 20.0,
+            peak_hold_secs: 1.0,
+            rms_window_secs: 300.0 / 1000.0,
+        }
+    }
+}
+
+/// The readings of a single channel, stored as raw gain values
+#[derive(Default)]
+struct ChannelReadings {
+    peak: AtomicF32,
+    rms: AtomicF32,
+    peak_hold: AtomicF32,
+}
+
+/// A node which passes audio through unchanged while measuring the peak,
+/// RMS, and peak-hold levels of each channel (i.e. for level meters in a
+/// mixer)
+///
+/// The readings are updated once per processed block.
+pub struct MeterNode {
+    config: MeterConfig,
+    // TODO: Find a good solution for webassembly.
+    readings: Arc<Vec<ChannelReadings>>,
+}
+
+impl MeterNode {
+    pub fn new(config: MeterConfig) -> Self {
+        Self {
+            config,
+            readings: Arc::new(Vec::new()),
+        }
+    }
+
+    pub fn config(&self) -> &MeterConfig {
+        &self.config
+    }
+
+    /// The number of channels being measured (`0` if the node has not
+    /// been activated yet)
+    pub fn num_channels(&self) -> usize {
+        self.readings.len()
+    }
+
+    /// The peak level of the given channel in decibels
+    ///
+    /// Returns `-100.0` if the channel does not exist.
+    pub fn peak_db(&self, channel: usize) -> f32 {
+        self.reading_db(channel, |r| &r.peak)
+    }
+
+    /// The RMS level of the given channel in decibels
+    ///
+    /// Returns `-100.0` if the channel does not exist.
+    pub fn rms_db(&self, channel: usize) -> f32 {
+        self.reading_db(channel, |r| &r.rms)
+    }
+
+    /// The peak-hold level of the given channel in decibels
+    ///
+    /// Returns `-100.0` if the channel does not exist.
+    pub fn peak_hold_db(&self, channel: usize) -> f32 {
+        self.reading_db(channel, |r| &r.peak_hold)
+    }
+
+    fn reading_db(&self, channel: usize, f: impl Fn(&ChannelReadings) -> &AtomicF32) -> f32 {
+        self.readings
+            .get(channel)
+            .map(|r| gain_to_db_clamped_neg_100_db(f(r).load(Ordering::Relaxed)))
+            .unwrap_or(-100.0)
+    }
+}
+
+impl AudioNode for MeterNode {
+    fn debug_name(&self) -> &'static str {
+        "meter"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 1,
+            num_max_supported_inputs: 64,
+            num_min_supported_outputs: 1,
+            num_max_supported_outputs: 64,
+            updates: false,
+            split_at_events: false,
+            // The readings need to keep falling while the inputs are silent.
+            silent_when_inputs_silent: false,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        _max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if num_inputs != num_outputs {
+            return Err(format!("The number of inputs on a Meter node must equal the number of outputs. Got num_inputs: {}, num_outputs: {}", num_inputs, num_outputs).into());
+        }
+
+        let sample_rate = sample_rate as f32;
+
+        self.readings = Arc::new((0..num_inputs).map(|_| Default::default()).collect());
+
+        Ok(Box::new(MeterProcessor {
+            readings: Arc::clone(&self.readings),
+            channels: vec![ChannelState::default(); num_inputs],
+            peak_decay: db_to_gain(-self.config.peak_decay_db_per_sec.max(0.0) / sample_rate),
+            rms_decay: (-1.0 / (self.config.rms_window_secs.max(0.001) * sample_rate)).exp(),
+            hold_frames: (self.config.peak_hold_secs.max(0.0) * sample_rate).round() as usize,
+        }))
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for MeterNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct ChannelState {
+    peak: f32,
+    mean_square: f32,
+    peak_hold: f32,
+    /// The number of frames left before the peak-hold reading starts to
+    /// fall
+    hold_frames_left: usize,
+}
+
+impl ChannelState {
+    /// Let the readings fall over the given number of silent frames.
+    ///
+    /// This is computed for the whole block at once so that no per-sample
+    /// work is done on silent blocks.
+    fn decay_silent(&mut self, frames: usize, peak_decay: f32, rms_decay: f32) {
+        let frames_i32 = frames.min(i32::MAX as usize) as i32;
+
+        self.peak *= peak_decay.powi(frames_i32);
+        self.mean_square *= rms_decay.powi(frames_i32);
+
+        if frames > self.hold_frames_left {
+            let decay_frames = (frames - self.hold_frames_left).min(i32::MAX as usize) as i32;
+            self.peak_hold *= peak_decay.powi(decay_frames);
+            self.hold_frames_left = 0;
+        } else {
+            self.hold_frames_left -= frames;
+        }
+
+        self.snap_to_silence();
+    }
+
+    fn snap_to_silence(&mut self) {
+        if self.peak < SILENCE_GAIN {
+            self.peak = 0.0;
+        }
+        if self.mean_square < SILENCE_GAIN * SILENCE_GAIN {
+            self.mean_square = 0.0;
+        }
+        if self.peak_hold < SILENCE_GAIN {
+            self.peak_hold = 0.0;
+        }
+    }
+}
+
+struct MeterProcessor {
+    readings: Arc<Vec<ChannelReadings>>,
+    channels: Vec<ChannelState>,
+    /// The decay of the peak per frame
+    peak_decay: f32,
+    /// The decay of the mean square per frame
+    rms_decay: f32,
+    hold_frames: usize,
+}
+
+impl AudioNodeProcessor for MeterProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        for (ch_i, ((output, input), state)) in outputs
+            .iter_mut()
+            .zip(inputs.iter())
+            .zip(self.channels.iter_mut())
+            .enumerate()
+        {
+            if proc_info.in_silence_mask.is_channel_silent(ch_i) {
+                output[..frames].fill(0.0);
+                state.decay_silent(frames, self.peak_decay, self.rms_decay);
+            } else {
+                let input = &input[..frames];
+                output[..frames].copy_from_slice(input);
+
+                let rms_coeff = 1.0 - self.rms_decay;
+
+                for &s in input.iter() {
+                    let level = s.abs();
+
+                    state.peak = level.max(state.peak * self.peak_decay);
+                    state.mean_square += (s * s - state.mean_square) * rms_coeff;
+
+                    if level >= state.peak_hold {
+                        state.peak_hold = level;
+                        state.hold_frames_left = self.hold_frames;
+                    } else if state.hold_frames_left > 0 {
+                        state.hold_frames_left -= 1;
+                    } else {
+                        state.peak_hold *= self.peak_decay;
+                    }
+                }
+
+                state.snap_to_silence();
+            }
+
+            if let Some(readings) = self.readings.get(ch_i) {
+                readings.peak.store(state.peak, Ordering::Relaxed);
+                readings
+                    .rms
+                    .store(state.mean_square.sqrt(), Ordering::Relaxed);
+                readings.peak_hold.store(state.peak_hold, Ordering::Relaxed);
+            }
+        }
+
+        *proc_info.out_silence_mask = proc_info.in_silence_mask;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firewheel_core::SilenceMask;
+
+    use super::*;
+    use crate::basic_nodes::process_block;
+
+    #[test]
+    fn readings_and_decay() {
+        const FRAMES: usize = 480;
+
+        let mut node = MeterNode::new(MeterConfig {
+            peak_hold_secs: 0.1,
+            ..Default::default()
+        });
+        assert_eq!(node.peak_db(0), -100.0);
+
+        let mut processor = node.activate(48_000, FRAMES, 1, 1).unwrap();
+        assert_eq!(node.num_channels(), 1);
+
+        let mut process = |input: &[f32; FRAMES], in_silence_mask: SilenceMask| {
+            let mut output = [0.0; FRAMES];
+
+            let (out_silence_mask, _) = process_block(
+                processor.as_mut(),
+                FRAMES,
+                &[input],
+                &mut [&mut output],
+                in_silence_mask,
+                &mut [],
+            );
+
+            assert_eq!(&output, input);
+            assert_eq!(out_silence_mask, in_silence_mask);
+        };
+
+        // Three seconds of a 1 kHz sine wave with an amplitude of 0.5.
+        let mut sine = [0.0; FRAMES];
+        for (i, s) in sine.iter_mut().enumerate() {
+            *s = 0.5 * (std::f32::consts::TAU * 1_000.0 * i as f32 / 48_000.0).sin();
+        }
+        for _ in 0..300 {
+            process(&sine, SilenceMask::NONE_SILENT);
+        }
+
+        assert!((node.peak_db(0) + 6.02).abs() < 0.05);
+        assert!((node.peak_hold_db(0) + 6.02).abs() < 0.05);
+        assert!((node.rms_db(0) + 9.03).abs() < 0.1);
+
+        // After 100 ms of silence the peak falls by 2 dB, while the
+        // peak-hold reading has not started to fall yet.
+        let silence = [0.0; FRAMES];
+        for _ in 0..10 {
+            process(&silence, SilenceMask::MONO_SILENT);
+        }
+
+        assert!((node.peak_db(0) + 8.02).abs() < 0.05);
+        assert!((node.peak_hold_db(0) + 6.02).abs() < 0.05);
+
+        // Ten more seconds of silence is enough for all readings to
+        // reach the floor.
+        for _ in 0..1000 {
+            process(&silence, SilenceMask::MONO_SILENT);
+        }
+
+        assert_eq!(node.peak_db(0), -100.0);
+        assert_eq!(node.rms_db(0), -100.0);
+        assert_eq!(node.peak_hold_db(0), -100.0);
+    }
+}
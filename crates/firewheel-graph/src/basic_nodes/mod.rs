@@ -1,15 +1,76 @@
 pub mod beep_test;
+mod convolution;
+mod delay;
 mod dummy;
 mod hard_clip;
+mod hrtf;
+mod loudness;
+mod meter;
 mod mono_to_stereo;
+mod pan;
 pub mod sampler;
+mod spatializer;
 mod stereo_to_mono;
+mod stereo_width;
+mod sub_graph;
 mod sum;
+mod svf;
 mod volume;
 
+pub use convolution::{ConvolutionError, ConvolutionLatency, ConvolutionNode};
+pub use delay::DelayNode;
 pub use dummy::DummyAudioNode;
 pub use hard_clip::HardClipNode;
+pub use hrtf::{HrirDirection, HrirTable, HrtfError, HrtfNode};
+pub use loudness::LoudnessMeterNode;
+pub use meter::{MeterConfig, MeterNode};
 pub use mono_to_stereo::MonoToStereoNode;
+pub use pan::{PanLaw, PanNode};
+pub use spatializer::{DistanceAttenuation, DistanceModel, Listener, SpatializerNode};
 pub use stereo_to_mono::StereoToMonoNode;
+pub use stereo_width::StereoWidthNode;
+pub use sub_graph::SubGraphNode;
 pub use sum::SumNode;
+pub use svf::{SvfMode, SvfNode};
 pub use volume::VolumeNode;
+
+/// Run a node processor for a single block outside of a graph.
+///
+/// Returns the output silence mask along with the events which were sent
+/// by the processor.
+#[cfg(test)]
+pub(crate) fn process_block(
+    processor: &mut dyn firewheel_core::node::AudioNodeProcessor,
+    frames: usize,
+    inputs: &[&[f32]],
+    outputs: &mut [&mut [f32]],
+    in_silence_mask: firewheel_core::SilenceMask,
+    events: &mut [firewheel_core::event::NodeEvent],
+) -> (
+    firewheel_core::SilenceMask,
+    Vec<firewheel_core::event::ProcessorEvent>,
+) {
+    use firewheel_core::{event::ProcessorEventQueue, node::StreamStatus, SilenceMask};
+
+    let mut out_silence_mask = SilenceMask::NONE_SILENT;
+    let mut out_events = ProcessorEventQueue::with_capacity(16);
+    let mut cx: Box<dyn std::any::Any + Send> = Box::new(());
+
+    processor.process(
+        frames,
+        inputs,
+        outputs,
+        firewheel_core::node::ProcInfo {
+            in_silence_mask,
+            out_silence_mask: &mut out_silence_mask,
+            stream_time_secs: 0.0,
+            stream_status: StreamStatus::empty(),
+            clock_frames: 0,
+            events,
+            out_events: &mut out_events,
+            cx: &mut cx,
+        },
+    );
+
+    (out_silence_mask, out_events.drain().collect())
+}
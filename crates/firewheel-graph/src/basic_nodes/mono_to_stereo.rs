@@ -14,6 +14,8 @@ impl AudioNode for MonoToStereoNode {
             num_min_supported_outputs: 2,
             num_max_supported_outputs: 2,
             updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: true,
         }
     }
 
@@ -0,0 +1,321 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::LinearRange,
+        smoother::{ParamSmoother, SmootherConfig},
+    },
+    SilenceMask,
+};
+
+/// The law used to map a pan value to the gain of each channel
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PanLaw {
+    /// The total power stays constant across the pan range (-3 dB in the
+    /// center).
+    #[default]
+    ConstantPower,
+    /// The gain of each channel is linear with the pan value (-6 dB in
+    /// the center).
+    Linear,
+    /// A compromise between the constant power and linear laws (-4.5 dB
+    /// in the center).
+    Minus4_5dB,
+}
+
+impl PanLaw {
+    /// Get the gains of the left and right channels for the given pan
+    /// value in the range `[-1.0, 1.0]` (where `-1.0` is fully left and
+    /// `1.0` is fully right).
+    pub fn gains(&self, pan: f32) -> (f32, f32) {
+        let x = (pan.clamp(-1.0, 1.0) + 1.0) * 0.5;
+
+        match self {
+            Self::ConstantPower => {
+                let (sin, cos) = (x * std::f32::consts::FRAC_PI_2).sin_cos();
+                (cos, sin)
+            }
+            Self::Linear => (1.0 - x, x),
+            Self::Minus4_5dB => {
+                let (sin, cos) = (x * std::f32::consts::FRAC_PI_2).sin_cos();
+                (((1.0 - x) * cos).sqrt(), (x * sin).sqrt())
+            }
+        }
+    }
+
+    /// Get the gains of the left and right channels for the given pan
+    /// value when panning a stereo signal.
+    ///
+    /// A stereo signal is panned by attenuating the opposite channel (a
+    /// "balance" control), so both channels have unity gain in the center.
+    pub fn balance_gains(&self, pan: f32) -> (f32, f32) {
+        let (center, _) = self.gains(0.0);
+        let (left, right) = self.gains(pan);
+
+        ((left / center).min(1.0), (right / center).min(1.0))
+    }
+
+    fn from_u32(val: u32) -> Self {
+        match val {
+            1 => Self::Linear,
+            2 => Self::Minus4_5dB,
+            _ => Self::ConstantPower,
+        }
+    }
+
+    fn to_u32(self) -> u32 {
+        match self {
+            Self::ConstantPower => 0,
+            Self::Linear => 1,
+            Self::Minus4_5dB => 2,
+        }
+    }
+}
+
+/// A node which pans a mono or stereo signal to a stereo output
+pub struct PanNode {
+    // TODO: Find a good solution for webassembly.
+    raw_pan: Arc<AtomicF32>,
+    pan_law: Arc<AtomicU32>,
+    pan: f32,
+}
+
+impl PanNode {
+    /// The ID of the pan parameter, in the range `[-1.0, 1.0]` where
+    /// `-1.0` is fully left and `1.0` is fully right
+    pub const PARAM_PAN: ParamID = ParamID(0);
+    /// The ID of the pan law parameter, where `0.0` is
+    /// [`PanLaw::ConstantPower`], `1.0` is [`PanLaw::Linear`], and `2.0` is
+    /// [`PanLaw::Minus4_5dB`]
+    pub const PARAM_PAN_LAW: ParamID = ParamID(1);
+
+    pub fn new(pan: f32, pan_law: PanLaw) -> Self {
+        let pan = pan.clamp(-1.0, 1.0);
+
+        Self {
+            raw_pan: Arc::new(AtomicF32::new(pan)),
+            pan_law: Arc::new(AtomicU32::new(pan_law.to_u32())),
+            pan,
+        }
+    }
+
+    pub fn pan(&self) -> f32 {
+        self.pan
+    }
+
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = pan.clamp(-1.0, 1.0);
+        self.raw_pan.store(self.pan, Ordering::Relaxed);
+    }
+
+    pub fn pan_law(&self) -> PanLaw {
+        PanLaw::from_u32(self.pan_law.load(Ordering::Relaxed))
+    }
+
+    pub fn set_pan_law(&mut self, pan_law: PanLaw) {
+        self.pan_law.store(pan_law.to_u32(), Ordering::Relaxed);
+    }
+}
+
+impl AudioNode for PanNode {
+    fn debug_name(&self) -> &'static str {
+        "pan"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 1,
+            num_max_supported_inputs: 2,
+            num_min_supported_outputs: 2,
+            num_max_supported_outputs: 2,
+            updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: true,
+        }
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo {
+                id: Self::PARAM_PAN,
+                name: "pan",
+                range: ParamRange::Linear(LinearRange::new(-1.0, 1.0)),
+                default: 0.0,
+                unit: ParamUnit::Generic,
+                smoothing: Some(SmootherConfig::default()),
+            },
+            ParamInfo {
+                id: Self::PARAM_PAN_LAW,
+                name: "pan law",
+                range: ParamRange::Linear(LinearRange::new(0.0, 2.0)),
+                default: 0.0,
+                unit: ParamUnit::Generic,
+                smoothing: None,
+            },
+        ]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        match id {
+            Self::PARAM_PAN => self.set_pan(val),
+            Self::PARAM_PAN_LAW => self.set_pan_law(PanLaw::from_u32(val.round() as u32)),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        match id {
+            Self::PARAM_PAN => Some(self.pan),
+            Self::PARAM_PAN_LAW => Some(self.pan_law().to_u32() as f32),
+            _ => None,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if !(1..=2).contains(&num_inputs) || num_outputs != 2 {
+            return Err(format!("A Pan node must have either 1 or 2 inputs and 2 outputs. Got num_inputs: {}, num_outputs: {}", num_inputs, num_outputs).into());
+        }
+
+        Ok(Box::new(PanProcessor {
+            raw_pan: Arc::clone(&self.raw_pan),
+            pan_law: Arc::clone(&self.pan_law),
+            pan_smoother: ParamSmoother::new(
+                self.pan,
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            ),
+        }))
+    }
+}
+
+struct PanProcessor {
+    raw_pan: Arc<AtomicF32>,
+    pan_law: Arc<AtomicU32>,
+    pan_smoother: ParamSmoother,
+}
+
+impl AudioNodeProcessor for PanProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        let raw_pan = self.raw_pan.load(Ordering::Relaxed);
+
+        if proc_info.in_silence_mask.all_channels_silent(inputs.len()) {
+            // All channels are silent, so there is no need to process. Also reset
+            // the filter since it doesn't need to smooth anything.
+            self.pan_smoother.reset(raw_pan);
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        }
+
+        let pan_law = PanLaw::from_u32(self.pan_law.load(Ordering::Relaxed));
+        let pan = self.pan_smoother.set_and_process(raw_pan, frames);
+        let is_stereo = inputs.len() == 2;
+
+        let gains = |pan: f32| {
+            if is_stereo {
+                pan_law.balance_gains(pan)
+            } else {
+                pan_law.gains(pan)
+            }
+        };
+
+        let (out_l, out_r) = outputs.split_at_mut(1);
+        let out_l = &mut out_l[0][..frames];
+        let out_r = &mut out_r[0][..frames];
+        let in_l = &inputs[0][..frames];
+        let in_r = &inputs[inputs.len() - 1][..frames];
+
+        // Hint to the compiler to optimize loop.
+        assert!(frames <= pan.values.len());
+
+        if pan.is_smoothing() {
+            for i in 0..frames {
+                let (gain_l, gain_r) = gains(pan[i]);
+
+                out_l[i] = in_l[i] * gain_l;
+                out_r[i] = in_r[i] * gain_r;
+            }
+        } else {
+            let (gain_l, gain_r) = gains(pan.values[0]);
+
+            for i in 0..frames {
+                out_l[i] = in_l[i] * gain_l;
+                out_r[i] = in_r[i] * gain_r;
+            }
+        }
+
+        *proc_info.out_silence_mask = if is_stereo {
+            // Panning a stereo signal only attenuates each channel.
+            proc_info.in_silence_mask
+        } else {
+            SilenceMask::NONE_SILENT
+        };
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for PanNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pan_law_gains() {
+        let center_db = |law: PanLaw| {
+            let (left, right) = law.gains(0.0);
+            assert!((left - right).abs() < 0.0001);
+            firewheel_core::util::gain_to_db(left)
+        };
+
+        assert!((center_db(PanLaw::ConstantPower) + 3.01).abs() < 0.01);
+        assert!((center_db(PanLaw::Linear) + 6.02).abs() < 0.01);
+        assert!((center_db(PanLaw::Minus4_5dB) + 4.52).abs() < 0.01);
+
+        for law in [PanLaw::ConstantPower, PanLaw::Linear, PanLaw::Minus4_5dB] {
+            let (left, right) = law.gains(-1.0);
+            assert!((left - 1.0).abs() < 0.0001 && right.abs() < 0.0001);
+            let (left, right) = law.gains(1.0);
+            assert!(left.abs() < 0.0001 && (right - 1.0).abs() < 0.0001);
+
+            assert_eq!(law.balance_gains(0.0), (1.0, 1.0));
+            let (left, right) = law.balance_gains(-0.5);
+            assert_eq!(left, 1.0);
+            assert!(right < 1.0);
+        }
+    }
+
+    #[test]
+    fn description_roundtrip() {
+        let (graph, node_id) = crate::graph::reload_node(1, 2, PanNode::new(0.5, PanLaw::Linear));
+        let node = graph
+            .node(node_id)
+            .unwrap()
+            .downcast_ref::<PanNode>()
+            .unwrap();
+
+        assert_eq!(node.pan(), 0.5);
+        assert_eq!(node.pan_law(), PanLaw::Linear);
+    }
+}
@@ -1,18 +1,133 @@
 use std::{
     fmt::Debug,
     ops::Range,
-    sync::{atomic::Ordering, Arc},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
 };
 
+use arrayvec::ArrayVec;
 use atomic_float::AtomicF32;
 use firewheel_core::{
+    event::{NodeEventType, ProcessorEvent},
     node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
-    param::{range::percent_volume_to_raw_gain, smoother::ParamSmoother},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::{percent_volume_to_raw_gain, LinearRange, NormToPowRange},
+        smoother::{ParamSmoother, SmootherConfig},
+    },
     sample_resource::SampleResource,
 };
 
 const CHANNEL_CAPACITY: usize = 128;
 
+/// The number of frames of the sample resource that are read into the
+/// processor at a time when the sample is being resampled
+const SOURCE_CACHE_FRAMES: usize = 512;
+/// The number of frames on each side of the playhead that are used by
+/// [`SamplerInterpolation::WindowedSinc`]
+const SINC_HALF_TAPS: usize = 8;
+
+/// How a [`SamplerNode`] reads in between the frames of a sample when the
+/// sample is not being played back at its original speed
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SamplerInterpolation {
+    /// The cheapest, but it dulls the high frequencies and adds aliasing
+    Linear,
+    /// A 4-point Catmull-Rom spline, a good tradeoff between quality and
+    /// performance
+    #[default]
+    CubicHermite,
+    /// A 16-point windowed sinc filter, which also filters out aliasing
+    /// when the sample is pitched up
+    WindowedSinc,
+}
+
+impl SamplerInterpolation {
+    fn from_u32(val: u32) -> Self {
+        match val {
+            0 => Self::Linear,
+            2 => Self::WindowedSinc,
+            _ => Self::CubicHermite,
+        }
+    }
+
+    fn to_u32(self) -> u32 {
+        match self {
+            Self::Linear => 0,
+            Self::CubicHermite => 1,
+            Self::WindowedSinc => 2,
+        }
+    }
+
+    /// The number of frames on each side of the playhead that are read
+    fn half_taps(self) -> usize {
+        match self {
+            Self::Linear => 1,
+            Self::CubicHermite => 2,
+            Self::WindowedSinc => SINC_HALF_TAPS,
+        }
+    }
+
+    /// Compute the weight of each of the `2 * half_taps()` frames around the
+    /// playhead, starting with the frame at `-(half_taps() - 1)`.
+    ///
+    /// * `frac` - The fractional part of the playhead
+    /// * `step` - How many frames the playhead moves per output frame
+    fn weights(self, frac: f32, step: f64, weights: &mut [f32]) {
+        match self {
+            Self::Linear => {
+                weights[0] = 1.0 - frac;
+                weights[1] = frac;
+            }
+            Self::CubicHermite => {
+                let t = frac;
+                let t2 = t * t;
+                let t3 = t2 * t;
+
+                weights[0] = -0.5 * t3 + t2 - 0.5 * t;
+                weights[1] = 1.5 * t3 - 2.5 * t2 + 1.0;
+                weights[2] = -1.5 * t3 + 2.0 * t2 + 0.5 * t;
+                weights[3] = 0.5 * t3 - 0.5 * t2;
+            }
+            Self::WindowedSinc => {
+                // Lower the cutoff when the sample is pitched up so that it
+                // doesn't alias.
+                let cutoff = (1.0 / step.abs()).min(1.0) as f32;
+
+                let mut sum = 0.0;
+                for (i, w) in weights.iter_mut().enumerate() {
+                    let x = (i as f32 - (SINC_HALF_TAPS as f32 - 1.0)) - frac;
+
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        let px = std::f32::consts::PI * x * cutoff;
+                        px.sin() / px
+                    };
+
+                    // Blackman window
+                    let t = x / SINC_HALF_TAPS as f32;
+                    let window = 0.42
+                        + 0.5 * (std::f32::consts::PI * t).cos()
+                        + 0.08 * (std::f32::consts::TAU * t).cos();
+
+                    *w = sinc * window;
+                    sum += *w;
+                }
+
+                // Normalize to unity gain.
+                if sum > 0.0 {
+                    for w in weights.iter_mut() {
+                        *w /= sum;
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub enum LoopRange {
     Full,
     RangeSecs(Range<f64>),
@@ -35,6 +150,10 @@ impl<S: SampleResource> Debug for NodeToProcessorMsg<S> {
 
 enum ProcessorToNodeMsg<S: SampleResource> {
     ReturnSample(S),
+    /// The sample has finished playing.
+    Finished,
+    /// Playback was started or stopped by a scheduled event.
+    PlayingChanged(bool),
 }
 
 struct ActiveState<S: SampleResource> {
@@ -47,17 +166,35 @@ pub struct SamplerNode<S: SampleResource> {
     active_state: Option<ActiveState<S>>,
 
     raw_gain: Arc<AtomicF32>,
+    raw_playback_rate: Arc<AtomicF32>,
+    interpolation: Arc<AtomicU32>,
     percent_volume: f32,
+    playback_rate: f32,
     playing: bool,
 }
 
 impl<S: SampleResource> SamplerNode<S> {
+    /// The ID of the volume parameter in percent
+    pub const PARAM_VOLUME: ParamID = ParamID(0);
+    /// The ID of the playback rate parameter, where `1.0` is the original
+    /// speed and pitch of the sample, `2.0` is twice as fast and an octave
+    /// higher, and so on
+    pub const PARAM_PLAYBACK_RATE: ParamID = ParamID(1);
+
+    /// The minimum playback rate
+    pub const MIN_PLAYBACK_RATE: f32 = 0.0;
+    /// The maximum playback rate
+    pub const MAX_PLAYBACK_RATE: f32 = 8.0;
+
     pub fn new(percent_volume: f32) -> Self {
         let percent_volume = percent_volume.max(0.0);
 
         Self {
             raw_gain: Arc::new(AtomicF32::new(percent_volume_to_raw_gain(percent_volume))),
+            raw_playback_rate: Arc::new(AtomicF32::new(1.0)),
+            interpolation: Arc::new(AtomicU32::new(SamplerInterpolation::default().to_u32())),
             percent_volume,
+            playback_rate: 1.0,
             active_state: None,
             playing: false,
         }
@@ -72,63 +209,66 @@ impl<S: SampleResource> SamplerNode<S> {
                     sample,
                     stop_playback,
                 })
-                .map_err(|_| ())
+                .map_err(|_| ())?;
         } else {
             todo!()
         }
+
+        if stop_playback {
+            self.playing = false;
+        }
+
+        Ok(())
     }
 
     // TODO: Error type
     pub fn play(&mut self) -> Result<(), ()> {
-        if !self.playing {
-            if let Some(state) = &mut self.active_state {
-                state
-                    .to_processor_tx
-                    .push(NodeToProcessorMsg::Play)
-                    .map_err(|_| ())?;
-            } else {
-                todo!()
-            }
-
-            self.playing = true;
+        // Always send the message since the playback state may have been
+        // changed by the processor (i.e. the sample finished playing or a
+        // scheduled event was processed) since `update()` was last called.
+        if let Some(state) = &mut self.active_state {
+            state
+                .to_processor_tx
+                .push(NodeToProcessorMsg::Play)
+                .map_err(|_| ())?;
+        } else {
+            todo!()
         }
 
+        self.playing = true;
+
         Ok(())
     }
 
     // TODO: Error type
     pub fn pause(&mut self) -> Result<(), ()> {
-        if self.playing {
-            if let Some(state) = &mut self.active_state {
-                state
-                    .to_processor_tx
-                    .push(NodeToProcessorMsg::Pause)
-                    .map_err(|_| ())?;
-            } else {
-                todo!()
-            }
-
-            self.playing = false;
+        if let Some(state) = &mut self.active_state {
+            state
+                .to_processor_tx
+                .push(NodeToProcessorMsg::Pause)
+                .map_err(|_| ())?;
+        } else {
+            todo!()
         }
 
+        self.playing = false;
+
         Ok(())
     }
 
     // TODO: Error type
     pub fn stop(&mut self) -> Result<(), ()> {
-        if self.playing {
-            if let Some(state) = &mut self.active_state {
-                state
-                    .to_processor_tx
-                    .push(NodeToProcessorMsg::Stop)
-                    .map_err(|_| ())?;
-            } else {
-                todo!()
-            }
-
-            self.playing = false;
+        if let Some(state) = &mut self.active_state {
+            state
+                .to_processor_tx
+                .push(NodeToProcessorMsg::Stop)
+                .map_err(|_| ())?;
+        } else {
+            todo!()
         }
 
+        self.playing = false;
+
         Ok(())
     }
 
@@ -160,6 +300,23 @@ impl<S: SampleResource> SamplerNode<S> {
         Ok(())
     }
 
+    fn poll_processor(&mut self) {
+        if let Some(active_state) = &mut self.active_state {
+            while let Ok(msg) = active_state.from_processor_rx.pop() {
+                match msg {
+                    ProcessorToNodeMsg::ReturnSample(_smp) => {}
+                    ProcessorToNodeMsg::Finished => self.playing = false,
+                    ProcessorToNodeMsg::PlayingChanged(playing) => self.playing = playing,
+                }
+            }
+        }
+    }
+
+    /// Whether or not the sample is playing
+    ///
+    /// This is updated when a sample finishes playing or when playback is
+    /// started or stopped by a scheduled event on the next call to
+    /// [`AudioNode::update`].
     pub fn is_playing(&self) -> bool {
         self.playing
     }
@@ -179,11 +336,35 @@ impl<S: SampleResource> SamplerNode<S> {
     pub fn raw_gain(&self) -> f32 {
         self.raw_gain.load(Ordering::Relaxed)
     }
+
+    pub fn playback_rate(&self) -> f32 {
+        self.playback_rate
+    }
+
+    /// Set the playback rate, where `1.0` is the original speed and pitch
+    /// of the sample.
+    ///
+    /// Changes to the playback rate are smoothed (i.e. for doppler
+    /// effects).
+    pub fn set_playback_rate(&mut self, playback_rate: f32) {
+        self.playback_rate = playback_rate.clamp(Self::MIN_PLAYBACK_RATE, Self::MAX_PLAYBACK_RATE);
+        self.raw_playback_rate
+            .store(self.playback_rate, Ordering::Relaxed);
+    }
+
+    pub fn interpolation(&self) -> SamplerInterpolation {
+        SamplerInterpolation::from_u32(self.interpolation.load(Ordering::Relaxed))
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: SamplerInterpolation) {
+        self.interpolation
+            .store(interpolation.to_u32(), Ordering::Relaxed);
+    }
 }
 
 impl<S: SampleResource> AudioNode for SamplerNode<S> {
     fn debug_name(&self) -> &'static str {
-        "beep_test"
+        "sampler"
     }
 
     fn info(&self) -> AudioNodeInfo {
@@ -191,16 +372,61 @@ impl<S: SampleResource> AudioNode for SamplerNode<S> {
             num_min_supported_outputs: 1,
             num_max_supported_outputs: 64,
             updates: true,
+            split_at_events: true,
+            silent_when_inputs_silent: false,
             ..Default::default()
         }
     }
 
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo {
+                id: Self::PARAM_VOLUME,
+                name: "volume",
+                range: ParamRange::Linear(LinearRange::new(0.0, 200.0)),
+                default: 100.0,
+                unit: ParamUnit::Percent,
+                smoothing: Some(SmootherConfig::default()),
+            },
+            ParamInfo {
+                id: Self::PARAM_PLAYBACK_RATE,
+                name: "playback_rate",
+                // An exponent of 3 puts a rate of `1.0` in the middle of the
+                // range.
+                range: ParamRange::Pow(NormToPowRange::new(
+                    Self::MIN_PLAYBACK_RATE,
+                    Self::MAX_PLAYBACK_RATE,
+                    3.0,
+                )),
+                default: 1.0,
+                unit: ParamUnit::Generic,
+                smoothing: Some(SmootherConfig::default()),
+            },
+        ]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        match id {
+            Self::PARAM_VOLUME => self.set_percent_volume(val),
+            Self::PARAM_PLAYBACK_RATE => self.set_playback_rate(val),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        match id {
+            Self::PARAM_VOLUME => Some(self.percent_volume),
+            Self::PARAM_PLAYBACK_RATE => Some(self.playback_rate),
+            _ => None,
+        }
+    }
+
     fn activate(
         &mut self,
         sample_rate: u32,
         max_block_frames: usize,
         _num_inputs: usize,
-        _num_outputs: usize,
+        num_outputs: usize,
     ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
         let (to_processor_tx, from_node_rx) =
             rtrb::RingBuffer::<NodeToProcessorMsg<S>>::new(CHANNEL_CAPACITY);
@@ -214,76 +440,177 @@ impl<S: SampleResource> AudioNode for SamplerNode<S> {
 
         Ok(Box::new(SamplerProcessor::new(
             Arc::clone(&self.raw_gain),
+            Arc::clone(&self.raw_playback_rate),
+            Arc::clone(&self.interpolation),
             sample_rate,
             max_block_frames,
+            num_outputs,
             from_node_rx,
             to_node_tx,
         )))
     }
 
     fn update(&mut self) {
-        if let Some(active_state) = &mut self.active_state {
-            while let Ok(msg) = active_state.from_processor_rx.pop() {
-                match msg {
-                    ProcessorToNodeMsg::ReturnSample(_smp) => {}
-                }
-            }
-        }
+        self.poll_processor();
     }
 }
 
+/// The sample rate of the given sample, or the sample rate of the stream
+/// if the sample doesn't specify one
+fn resource_sample_rate<S: SampleResource>(sample: &Option<S>, stream_sample_rate: u32) -> u32 {
+    sample
+        .as_ref()
+        .and_then(|s| s.sample_rate())
+        .map(|sr| sr.get())
+        .unwrap_or(stream_sample_rate)
+}
+
 struct ProcLoopRange {
+    loop_range: LoopRange,
     playhead_range: Range<u64>,
-    full_range: bool,
 }
 
 impl ProcLoopRange {
-    fn new<S: SampleResource>(loop_range: LoopRange, sample_rate: u32, sample: &Option<S>) -> Self {
-        let (start_frame, end_frame, full_range) = match &loop_range {
-            LoopRange::Full => {
-                let end_frame = if let Some(sample) = sample {
-                    sample.len_frames()
-                } else {
-                    0
-                };
+    fn new<S: SampleResource>(
+        loop_range: LoopRange,
+        stream_sample_rate: u32,
+        sample: &Option<S>,
+    ) -> Self {
+        let mut new_self = Self {
+            loop_range,
+            playhead_range: 0..0,
+        };
+        new_self.update_sample(sample, stream_sample_rate);
+        new_self
+    }
+
+    fn update_sample<S: SampleResource>(&mut self, sample: &Option<S>, stream_sample_rate: u32) {
+        let Some(smp) = sample else {
+            return;
+        };
 
-                (0, end_frame, true)
+        self.playhead_range = match &self.loop_range {
+            LoopRange::Full => 0..smp.len_frames(),
+            LoopRange::RangeSecs(range) => {
+                // The range is in the time of the sample, which may differ
+                // from the time of the stream.
+                let sample_rate = f64::from(resource_sample_rate(sample, stream_sample_rate));
+
+                (range.start * sample_rate).round() as u64..(range.end * sample_rate).round() as u64
             }
-            LoopRange::RangeSecs(range) => (
-                (range.start * f64::from(sample_rate)).round() as u64,
-                (range.end * f64::from(sample_rate)).round() as u64,
-                false,
-            ),
         };
+    }
+}
 
+/// Reads frames of a sample resource in between its frames
+struct Resampler {
+    /// The cached frames of the sample, with the frames of each channel
+    /// stored contiguously
+    cache: Vec<f32>,
+    num_channels: usize,
+    /// The frame in the sample of the first frame in the cache, or `None`
+    /// if the cache needs to be refilled
+    cache_start: Option<i64>,
+}
+
+impl Resampler {
+    fn new(num_channels: usize) -> Self {
         Self {
-            playhead_range: start_frame..end_frame,
-            full_range,
+            cache: vec![0.0; SOURCE_CACHE_FRAMES * num_channels],
+            num_channels,
+            cache_start: None,
         }
     }
 
-    fn update_sample<S: SampleResource>(&mut self, sample: &Option<S>) {
-        let Some(sample) = sample else {
-            return;
-        };
+    fn invalidate(&mut self) {
+        self.cache_start = None;
+    }
 
-        if !self.full_range {
-            return;
+    /// Make sure that the `len` frames starting from `start_frame` are in
+    /// the cache, and return the offset of `start_frame` into the cache.
+    ///
+    /// Frames past the end of the loop range wrap around to the start of
+    /// the loop range, and frames outside of the sample are silent.
+    fn prepare<S: SampleResource>(
+        &mut self,
+        sample: &S,
+        start_frame: i64,
+        len: usize,
+        loop_range: Option<&Range<u64>>,
+    ) -> usize {
+        if let Some(cache_start) = self.cache_start {
+            if start_frame >= cache_start
+                && start_frame + len as i64 <= cache_start + SOURCE_CACHE_FRAMES as i64
+            {
+                return (start_frame - cache_start) as usize;
+            }
         }
 
-        let end_frame = sample.len_frames();
+        let mut buffers: ArrayVec<&mut [f32], 64> = self
+            .cache
+            .chunks_exact_mut(SOURCE_CACHE_FRAMES)
+            .take(64)
+            .collect();
+
+        let len_frames = sample.len_frames().min(i64::MAX as u64) as i64;
+        let loop_range = loop_range.map(|r| r.start as i64..r.end as i64);
+        let limit = loop_range.as_ref().map(|r| r.end).unwrap_or(i64::MAX);
+
+        let mut frame = start_frame;
+        let mut i = 0;
+        while i < SOURCE_CACHE_FRAMES {
+            if let Some(r) = &loop_range {
+                if frame >= r.end {
+                    frame = r.start + (frame - r.start) % (r.end - r.start);
+                }
+            }
+
+            let frames_left = (SOURCE_CACHE_FRAMES - i) as i64;
+
+            let frames = if frame >= 0 && frame < len_frames {
+                let frames = (limit.min(len_frames) - frame).min(frames_left) as usize;
+                sample.fill_buffers(&mut buffers, i..i + frames, frame as u64);
+                frames
+            } else {
+                let frames = if frame < 0 {
+                    (-frame).min(frames_left)
+                } else {
+                    (limit - frame).min(frames_left)
+                } as usize;
+
+                for buf in buffers.iter_mut() {
+                    buf[i..i + frames].fill(0.0);
+                }
 
-        self.playhead_range = 0..end_frame;
+                frames
+            };
+
+            i += frames;
+            frame += frames as i64;
+        }
+
+        self.cache_start = Some(start_frame);
+
+        0
     }
 }
 
 struct SamplerProcessor<S: SampleResource> {
     raw_gain: Arc<AtomicF32>,
+    raw_playback_rate: Arc<AtomicF32>,
+    interpolation: Arc<AtomicU32>,
     gain_smoother: ParamSmoother,
+    playback_rate_smoother: ParamSmoother,
     playing: bool,
     sample_rate: u32,
-    playhead: u64,
+    /// The position of the playhead in frames of the sample
+    playhead: f64,
+    /// How far the playhead moves per frame at a playback rate of `1.0`
+    /// (the sample rate of the sample divided by the sample rate of the
+    /// stream)
+    base_step: f64,
     loop_range: Option<ProcLoopRange>,
+    resampler: Resampler,
 
     sample: Option<S>,
 
@@ -292,32 +619,80 @@ struct SamplerProcessor<S: SampleResource> {
 }
 
 impl<S: SampleResource> SamplerProcessor<S> {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         raw_gain: Arc<AtomicF32>,
+        raw_playback_rate: Arc<AtomicF32>,
+        interpolation: Arc<AtomicU32>,
         sample_rate: u32,
         max_block_frames: usize,
+        num_outputs: usize,
         from_node_rx: rtrb::Consumer<NodeToProcessorMsg<S>>,
         to_node_tx: rtrb::Producer<ProcessorToNodeMsg<S>>,
     ) -> Self {
         let gain_val = raw_gain.load(Ordering::Relaxed);
+        let playback_rate_val = raw_playback_rate.load(Ordering::Relaxed);
 
         Self {
             raw_gain,
+            raw_playback_rate,
+            interpolation,
             gain_smoother: ParamSmoother::new(
                 gain_val,
                 sample_rate,
                 max_block_frames,
                 Default::default(),
             ),
+            playback_rate_smoother: ParamSmoother::new(
+                playback_rate_val,
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            ),
             playing: false,
             sample_rate,
-            playhead: 0,
+            playhead: 0.0,
+            base_step: 1.0,
             loop_range: None,
+            resampler: Resampler::new(num_outputs),
             sample: None,
             from_node_rx,
             to_node_tx,
         }
     }
+
+    fn loop_start(&self) -> f64 {
+        self.loop_range
+            .as_ref()
+            .map(|l| l.playhead_range.start as f64)
+            .unwrap_or(0.0)
+    }
+
+    fn play(&mut self) {
+        if !self.playing {
+            self.playing = true;
+
+            // TODO: Declick
+        }
+    }
+
+    fn pause(&mut self) {
+        if self.playing {
+            self.playing = false;
+
+            // TODO: Declick
+        }
+    }
+
+    fn stop(&mut self) {
+        self.playhead = self.loop_start();
+
+        if self.playing {
+            self.playing = false;
+
+            // TODO: Declick
+        }
+    }
 }
 
 impl<S: SampleResource> AudioNodeProcessor for SamplerProcessor<S> {
@@ -341,17 +716,17 @@ impl<S: SampleResource> AudioNodeProcessor for SamplerProcessor<S> {
                     }
 
                     self.sample = Some(sample);
+                    self.base_step =
+                        f64::from(resource_sample_rate(&self.sample, self.sample_rate))
+                            / f64::from(self.sample_rate);
+                    self.resampler.invalidate();
 
                     if let Some(loop_range) = &mut self.loop_range {
-                        loop_range.update_sample(&self.sample);
+                        loop_range.update_sample(&self.sample, self.sample_rate);
                     }
 
                     if stop_playback {
-                        self.playhead = self
-                            .loop_range
-                            .as_ref()
-                            .map(|l| l.playhead_range.start)
-                            .unwrap_or(0);
+                        self.playhead = self.loop_start();
 
                         if self.playing {
                             self.playing = false;
@@ -362,38 +737,15 @@ impl<S: SampleResource> AudioNodeProcessor for SamplerProcessor<S> {
 
                     // TODO: Declick
                 }
-                NodeToProcessorMsg::Play => {
-                    if !self.playing {
-                        self.playing = true;
-
-                        // TODO: Declick
-                    }
-                }
-                NodeToProcessorMsg::Pause => {
-                    if self.playing {
-                        self.playing = false;
-
-                        // TODO: Declick
-                    }
-                }
-                NodeToProcessorMsg::Stop => {
-                    self.playhead = self
-                        .loop_range
-                        .as_ref()
-                        .map(|l| l.playhead_range.start)
-                        .unwrap_or(0);
-
-                    if self.playing {
-                        self.playing = false;
-
-                        // TODO: Declick
-                    }
-                }
+                NodeToProcessorMsg::Play => self.play(),
+                NodeToProcessorMsg::Pause => self.pause(),
+                NodeToProcessorMsg::Stop => self.stop(),
                 NodeToProcessorMsg::SetPlayheadSecs(playhead_secs) => {
-                    let sample = (playhead_secs * f64::from(self.sample_rate)).round() as u64;
+                    let playhead = playhead_secs.max(0.0)
+                        * f64::from(resource_sample_rate(&self.sample, self.sample_rate));
 
-                    if sample != self.playhead {
-                        self.playhead = sample;
+                    if playhead != self.playhead {
+                        self.playhead = playhead;
                         // TODO: Declick
                     }
                 }
@@ -401,10 +753,12 @@ impl<S: SampleResource> AudioNodeProcessor for SamplerProcessor<S> {
                     self.loop_range = loop_range.map(|loop_range| {
                         ProcLoopRange::new(loop_range, self.sample_rate, &self.sample)
                     });
+                    self.resampler.invalidate();
 
                     if let Some(loop_range) = &self.loop_range {
-                        if loop_range.playhead_range.contains(&self.playhead) {
-                            self.playhead = loop_range.playhead_range.start;
+                        let range = &loop_range.playhead_range;
+                        if self.playhead >= range.start as f64 && self.playhead < range.end as f64 {
+                            self.playhead = range.start as f64;
 
                             // TODO: Declick
                         }
@@ -413,6 +767,25 @@ impl<S: SampleResource> AudioNodeProcessor for SamplerProcessor<S> {
             }
         }
 
+        // Since this node splits the block at events, all events occur
+        // on the first frame.
+        for event in proc_info.events.iter() {
+            let was_playing = self.playing;
+
+            match event.event {
+                NodeEventType::Play => self.play(),
+                NodeEventType::Pause => self.pause(),
+                NodeEventType::Stop => self.stop(),
+                NodeEventType::Custom(_) => {}
+            }
+
+            if self.playing != was_playing {
+                let _ = self
+                    .to_node_tx
+                    .push(ProcessorToNodeMsg::PlayingChanged(self.playing));
+            }
+        }
+
         let Some(sample) = &self.sample else {
             // TODO: Declick
 
@@ -429,6 +802,16 @@ impl<S: SampleResource> AudioNodeProcessor for SamplerProcessor<S> {
             return;
         }
 
+        if self
+            .loop_range
+            .as_ref()
+            .is_some_and(|l| l.playhead_range.is_empty())
+        {
+            // The loop range is empty, so there is nothing to play.
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        }
+
         let raw_gain = self.raw_gain.load(Ordering::Relaxed);
         let gain = self.gain_smoother.set_and_process(raw_gain, frames);
         // Hint to the compiler to optimize loop.
@@ -442,82 +825,167 @@ impl<S: SampleResource> AudioNodeProcessor for SamplerProcessor<S> {
             return;
         }
 
-        if let Some(loop_range) = &self.loop_range {
-            if self.playhead >= loop_range.playhead_range.end {
-                // Playhead is out of range. Return to the start.
-                self.playhead = self
-                    .loop_range
-                    .as_ref()
-                    .map(|l| l.playhead_range.start)
-                    .unwrap_or(0);
-            }
+        let raw_playback_rate = self.raw_playback_rate.load(Ordering::Relaxed);
+        let playback_rate = self
+            .playback_rate_smoother
+            .set_and_process(raw_playback_rate, frames);
 
-            // Copy first block of samples.
+        let sample_channels = sample.num_channels().get();
 
-            let frames_left = if loop_range.playhead_range.end - self.playhead <= usize::MAX as u64
-            {
-                (loop_range.playhead_range.end - self.playhead) as usize
-            } else {
-                usize::MAX
-            };
-            let first_copy_frames = frames.min(frames_left);
+        if self.base_step == 1.0
+            && !playback_rate.is_smoothing()
+            && playback_rate.values[0] == 1.0
+            && self.playhead.fract() == 0.0
+        {
+            // The sample is being played back at its original speed and
+            // the playhead lies exactly on a frame, so the frames can be
+            // copied directly.
+            let mut playhead = self.playhead as u64;
+
+            if let Some(loop_range) = &self.loop_range {
+                if playhead >= loop_range.playhead_range.end {
+                    // Playhead is out of range. Return to the start.
+                    playhead = loop_range.playhead_range.start;
+                }
 
-            sample.fill_buffers(outputs, 0..first_copy_frames, self.playhead);
+                // Copy first block of samples.
 
-            if first_copy_frames < frames {
-                // Loop back to the start.
-                self.playhead = self
-                    .loop_range
-                    .as_ref()
-                    .map(|l| l.playhead_range.start)
-                    .unwrap_or(0);
+                let frames_left = if loop_range.playhead_range.end - playhead <= usize::MAX as u64 {
+                    (loop_range.playhead_range.end - playhead) as usize
+                } else {
+                    usize::MAX
+                };
+                let first_copy_frames = frames.min(frames_left);
 
-                // Copy second block of samples.
+                sample.fill_buffers(outputs, 0..first_copy_frames, playhead);
 
-                let second_copy_frames = frames - first_copy_frames;
+                if first_copy_frames < frames {
+                    // Loop back to the start.
+                    let _ = proc_info.out_events.push(ProcessorEvent::LoopWrapped);
+                    playhead = loop_range.playhead_range.start;
 
-                sample.fill_buffers(outputs, first_copy_frames..frames, self.playhead);
+                    // Copy second block of samples.
+
+                    let second_copy_frames = frames - first_copy_frames;
+
+                    sample.fill_buffers(outputs, first_copy_frames..frames, playhead);
+
+                    playhead += second_copy_frames as u64;
+                } else {
+                    playhead += frames as u64;
 
-                self.playhead += second_copy_frames as u64;
+                    if playhead == loop_range.playhead_range.end {
+                        // Reached the end of the loop range on the last frame
+                        // of this block.
+                        let _ = proc_info.out_events.push(ProcessorEvent::LoopWrapped);
+                        playhead = loop_range.playhead_range.start;
+                    }
+                }
             } else {
-                self.playhead += frames as u64;
+                if playhead >= sample.len_frames() {
+                    // Playhead is out of range. Output silence.
+                    self.playing = false;
+                    firewheel_core::util::clear_all_outputs(
+                        frames,
+                        outputs,
+                        proc_info.out_silence_mask,
+                    );
+
+                    let _ = proc_info.out_events.push(ProcessorEvent::SampleFinished);
+                    let _ = self.to_node_tx.push(ProcessorToNodeMsg::Finished);
+                    return;
+                }
+
+                let copy_frames = frames.min((sample.len_frames() - playhead) as usize);
+
+                sample.fill_buffers(outputs, 0..copy_frames, playhead);
+
+                if copy_frames < frames {
+                    // Finished playing sample.
+                    self.playing = false;
+                    playhead = 0;
+
+                    // Fill any remaining frames with zeros
+                    for out_ch in outputs.iter_mut() {
+                        out_ch[copy_frames..].fill(0.0);
+                    }
+
+                    let _ = proc_info.out_events.push(ProcessorEvent::SampleFinished);
+                    let _ = self.to_node_tx.push(ProcessorToNodeMsg::Finished);
+                } else {
+                    playhead += frames as u64;
+                }
             }
+
+            self.playhead = playhead as f64;
         } else {
-            if self.playhead >= sample.len_frames() {
-                // Playhead is out of range. Output silence.
-                self.playing = false;
-                firewheel_core::util::clear_all_outputs(
-                    frames,
-                    outputs,
-                    proc_info.out_silence_mask,
-                );
-                return;
+            let interpolation =
+                SamplerInterpolation::from_u32(self.interpolation.load(Ordering::Relaxed));
+            let half_taps = interpolation.half_taps();
+            let num_taps = half_taps * 2;
+            let mut weights = [0.0; SINC_HALF_TAPS * 2];
+
+            let loop_range = self.loop_range.as_ref().map(|l| &l.playhead_range);
+            let num_channels = outputs
+                .len()
+                .min(sample_channels)
+                .min(self.resampler.num_channels);
 
-                // TODO: Notify node that sample has finished.
-            }
+            for i in 0..frames {
+                if let Some(loop_range) = loop_range {
+                    if self.playhead >= loop_range.end as f64 {
+                        // Loop back to the start.
+                        let _ = proc_info.out_events.push(ProcessorEvent::LoopWrapped);
+                        self.playhead -= (loop_range.end - loop_range.start) as f64;
+
+                        if self.playhead >= loop_range.end as f64 {
+                            self.playhead = loop_range.start as f64;
+                        }
+                    }
+                } else if self.playhead >= sample.len_frames() as f64 {
+                    // Finished playing sample.
+                    self.playing = false;
+                    self.playhead = 0.0;
+
+                    // Fill any remaining frames with zeros
+                    for out_ch in outputs.iter_mut() {
+                        out_ch[i..frames].fill(0.0);
+                    }
+
+                    let _ = proc_info.out_events.push(ProcessorEvent::SampleFinished);
+                    let _ = self.to_node_tx.push(ProcessorToNodeMsg::Finished);
+                    break;
+                }
 
-            let copy_frames = frames.min((sample.len_frames() - self.playhead) as usize);
+                let step = self.base_step * f64::from(playback_rate.values[i]);
 
-            sample.fill_buffers(outputs, 0..copy_frames, self.playhead);
+                let frame = self.playhead.floor();
+                let frac = (self.playhead - frame) as f32;
+                interpolation.weights(frac, step, &mut weights[..num_taps]);
 
-            if copy_frames < frames {
-                // Finished playing sample.
-                self.playing = false;
-                self.playhead = 0;
+                let offset = self.resampler.prepare(
+                    sample,
+                    frame as i64 - half_taps as i64 + 1,
+                    num_taps,
+                    loop_range,
+                );
 
-                // Fill any remaining frames with zeros
-                for out_ch in outputs.iter_mut() {
-                    out_ch[copy_frames..].fill(0.0);
+                for (out_ch, cache_ch) in outputs
+                    .iter_mut()
+                    .zip(self.resampler.cache.chunks_exact(SOURCE_CACHE_FRAMES))
+                    .take(num_channels)
+                {
+                    out_ch[i] = cache_ch[offset..offset + num_taps]
+                        .iter()
+                        .zip(weights.iter())
+                        .map(|(s, w)| s * w)
+                        .sum();
                 }
 
-                // TODO: Notify node that sample has finished.
-            } else {
-                self.playhead += frames as u64;
+                self.playhead += step;
             }
         }
 
-        let sample_channels = sample.num_channels().get();
-
         // Apply gain and declick
         // TODO: Declick
         if outputs.len() >= 2 && sample_channels == 2 {
@@ -575,3 +1043,230 @@ impl<S: SampleResource> Into<Box<dyn AudioNode>> for SamplerNode<S> {
         Box::new(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::{NonZeroU32, NonZeroUsize};
+
+    use firewheel_core::{
+        event::NodeEvent, sample_resource::fill_buffers_deinterleaved_f32, SilenceMask,
+    };
+
+    use super::*;
+    use crate::basic_nodes::process_block;
+
+    /// A mono ramp (`0.0, 1.0, 2.0, ...`) with a custom sample rate
+    struct Ramp {
+        data: [Vec<f32>; 1],
+        sample_rate: u32,
+    }
+
+    impl Ramp {
+        fn new(len: usize, sample_rate: u32) -> Self {
+            Self {
+                data: [(0..len).map(|i| i as f32).collect()],
+                sample_rate,
+            }
+        }
+    }
+
+    impl SampleResource for Ramp {
+        fn num_channels(&self) -> NonZeroUsize {
+            NonZeroUsize::new(1).unwrap()
+        }
+
+        fn len_frames(&self) -> u64 {
+            self.data[0].len() as u64
+        }
+
+        fn sample_rate(&self) -> Option<NonZeroU32> {
+            NonZeroU32::new(self.sample_rate)
+        }
+
+        fn fill_buffers(
+            &self,
+            buffers: &mut [&mut [f32]],
+            buffer_range: Range<usize>,
+            start_frame: u64,
+        ) {
+            fill_buffers_deinterleaved_f32(buffers, buffer_range, start_frame, &self.data);
+        }
+    }
+
+    /// Process a single block and return the output along with whether or
+    /// not the sample finished playing.
+    fn process(processor: &mut Box<dyn AudioNodeProcessor>, frames: usize) -> (Vec<f32>, bool) {
+        process_with_events(processor, frames, &mut [])
+    }
+
+    /// Process a single block with the given scheduled events and return
+    /// the output along with whether or not the sample finished playing.
+    fn process_with_events(
+        processor: &mut Box<dyn AudioNodeProcessor>,
+        frames: usize,
+        events: &mut [NodeEvent],
+    ) -> (Vec<f32>, bool) {
+        let mut output = vec![0.0; frames];
+
+        let (_, out_events) = process_block(
+            processor.as_mut(),
+            frames,
+            &[],
+            &mut [&mut output],
+            SilenceMask::NONE_SILENT,
+            events,
+        );
+
+        let finished = out_events
+            .iter()
+            .any(|e| matches!(e, ProcessorEvent::SampleFinished));
+
+        (output, finished)
+    }
+
+    /// Play the sample for a single block and return the output along with
+    /// whether or not the sample finished playing.
+    fn play(
+        sample: Ramp,
+        playback_rate: f32,
+        interpolation: SamplerInterpolation,
+        frames: usize,
+    ) -> (Vec<f32>, bool) {
+        let mut node = SamplerNode::new(100.0);
+        node.set_playback_rate(playback_rate);
+        node.set_interpolation(interpolation);
+
+        let mut processor = node.activate(48_000, frames, 0, 1).unwrap();
+        node.set_sample(sample, false).unwrap();
+        node.play().unwrap();
+
+        process(&mut processor, frames)
+    }
+
+    #[test]
+    fn replay_after_finished() {
+        let mut node = SamplerNode::new(100.0);
+        let mut processor = node.activate(48_000, 16, 0, 1).unwrap();
+        node.set_sample(Ramp::new(8, 48_000), false).unwrap();
+        node.play().unwrap();
+
+        let (_, finished) = process(&mut processor, 16);
+        assert!(finished);
+
+        // The node finds out that the sample finished on the next update.
+        assert!(node.is_playing());
+        node.update();
+        assert!(!node.is_playing());
+
+        node.play().unwrap();
+        assert!(node.is_playing());
+
+        let (output, finished) = process(&mut processor, 16);
+        assert_eq!(&output[..8], &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        assert!(finished);
+
+        // Playing again before the node was updated also restarts the
+        // sample.
+        node.play().unwrap();
+        let (output, _) = process(&mut processor, 16);
+        assert_eq!(&output[..8], &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    fn scheduled_events_update_playing() {
+        let mut node = SamplerNode::new(100.0);
+        let mut processor = node.activate(48_000, 16, 0, 1).unwrap();
+        node.set_sample(Ramp::new(64, 48_000), false).unwrap();
+
+        let (output, _) = process_with_events(
+            &mut processor,
+            16,
+            &mut [NodeEvent {
+                clock_frame: 0,
+                event: NodeEventType::Play,
+            }],
+        );
+        assert_eq!(output[1], 1.0);
+
+        node.update();
+        assert!(node.is_playing());
+
+        // Pausing after playback was started by a scheduled event must
+        // reach the processor.
+        node.pause().unwrap();
+        assert!(!node.is_playing());
+        let (output, _) = process(&mut processor, 16);
+        assert_eq!(output, [0.0; 16]);
+
+        node.play().unwrap();
+        let (output, _) = process(&mut processor, 16);
+        assert_eq!(output[0], 16.0);
+
+        // Playing after playback was stopped by a scheduled event must
+        // also reach the processor.
+        process_with_events(
+            &mut processor,
+            16,
+            &mut [NodeEvent {
+                clock_frame: 0,
+                event: NodeEventType::Stop,
+            }],
+        );
+        node.update();
+        assert!(!node.is_playing());
+
+        node.play().unwrap();
+        let (output, _) = process(&mut processor, 16);
+        assert_eq!(output[..4], [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn playback_rate_and_interpolation() {
+        // Playing back at the original speed copies the frames directly.
+        let (output, finished) = play(Ramp::new(8, 48_000), 1.0, Default::default(), 10);
+        assert_eq!(&output[..8], &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        assert!(finished);
+
+        // A sample at half the sample rate of the stream is stretched to
+        // twice the length.
+        let (output, finished) = play(
+            Ramp::new(512, 24_000),
+            1.0,
+            SamplerInterpolation::Linear,
+            64,
+        );
+        for (i, &s) in output.iter().enumerate() {
+            assert_eq!(s, i as f32 * 0.5);
+        }
+        assert!(!finished);
+
+        // The playback rate and the sample rate of the resource are
+        // combined, so this lands exactly halfway in between each frame.
+        // (Skip the first frames, which read from before the start of the
+        // sample.)
+        for interpolation in [
+            SamplerInterpolation::Linear,
+            SamplerInterpolation::CubicHermite,
+            SamplerInterpolation::WindowedSinc,
+        ] {
+            let (output, _) = play(Ramp::new(1024, 96_000), 0.25, interpolation, 64);
+            for (i, &s) in output.iter().enumerate().skip(SINC_HALF_TAPS * 2) {
+                assert!((s - i as f32 * 0.5).abs() < 0.001);
+            }
+        }
+
+        // Playing back at 1.5 times the speed reads in between the frames
+        // and finishes early.
+        let (output, finished) = play(
+            Ramp::new(30, 48_000),
+            1.5,
+            SamplerInterpolation::CubicHermite,
+            24,
+        );
+        for (i, &s) in output.iter().enumerate().take(19).skip(2) {
+            assert!((s - i as f32 * 1.5).abs() < 0.001);
+        }
+        assert!(finished);
+        assert_eq!(output[20..], [0.0; 4]);
+    }
+}
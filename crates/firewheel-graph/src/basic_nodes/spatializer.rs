@@ -0,0 +1,624 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::{LinearRange, NormToFreqRange},
+        smoother::ParamSmoother,
+    },
+};
+
+use super::PanLaw;
+
+/// The cutoff frequency of the air absorption filter at the maximum
+/// distance in Hz
+const MIN_AIR_ABSORPTION_HZ: f32 = 2_000.0;
+/// The cutoff frequency of the air absorption filter at the minimum
+/// distance in Hz (which disables the filter)
+const MAX_AIR_ABSORPTION_HZ: f32 = 20_000.0;
+
+/// How the volume of a [`SpatializerNode`] is attenuated with distance
+///
+/// The distance is clamped to the range `[min_distance, max_distance]`
+/// before the attenuation is calculated.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum DistanceModel {
+    /// `min_distance / (min_distance + rolloff * (distance - min_distance))`
+    #[default]
+    Inverse,
+    /// `1.0 - rolloff * (distance - min_distance) / (max_distance - min_distance)`
+    Linear,
+    /// `(distance / min_distance) ^ -rolloff`
+    Exponential,
+}
+
+impl DistanceModel {
+    fn from_u32(val: u32) -> Self {
+        match val {
+            1 => Self::Linear,
+            2 => Self::Exponential,
+            _ => Self::Inverse,
+        }
+    }
+
+    fn to_u32(self) -> u32 {
+        match self {
+            Self::Inverse => 0,
+            Self::Linear => 1,
+            Self::Exponential => 2,
+        }
+    }
+}
+
+/// The settings of the distance attenuation of a [`SpatializerNode`]
+///
+/// The distances are clamped to the range
+/// `[0.0, SpatializerNode::MAX_DISTANCE]`, and the rolloff is clamped to the
+/// range `[0.0, SpatializerNode::MAX_ROLLOFF]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceAttenuation {
+    pub model: DistanceModel,
+    /// The distance at which the sound starts to be attenuated
+    pub min_distance: f32,
+    /// The distance after which the sound is no longer attenuated any
+    /// further
+    pub max_distance: f32,
+    /// How quickly the sound is attenuated with distance
+    pub rolloff: f32,
+}
+
+impl DistanceAttenuation {
+    /// The raw gain (not decibels) at the given distance
+    pub fn gain(&self, distance: f32) -> f32 {
+        let min_distance = self.min_distance.max(0.0001);
+        let max_distance = self.max_distance.max(min_distance);
+        let distance = distance.clamp(min_distance, max_distance);
+
+        let gain = match self.model {
+            DistanceModel::Inverse => {
+                min_distance / (min_distance + self.rolloff * (distance - min_distance))
+            }
+            DistanceModel::Linear => {
+                if max_distance == min_distance {
+                    1.0
+                } else {
+                    1.0 - self.rolloff * (distance - min_distance) / (max_distance - min_distance)
+                }
+            }
+            DistanceModel::Exponential => (distance / min_distance).powf(-self.rolloff),
+        };
+
+        gain.clamp(0.0, 1.0)
+    }
+
+    /// The normalized distance in the range `[0.0, 1.0]`, where `0.0` is
+    /// the minimum distance and `1.0` is the maximum distance
+    fn normalized(&self, distance: f32) -> f32 {
+        if self.max_distance <= self.min_distance {
+            0.0
+        } else {
+            ((distance - self.min_distance) / (self.max_distance - self.min_distance))
+                .clamp(0.0, 1.0)
+        }
+    }
+}
+
+impl Default for DistanceAttenuation {
+    fn default() -> Self {
+        Self {
+            model: DistanceModel::default(),
+            min_distance: 1.0,
+            max_distance: 100.0,
+            rolloff: 1.0,
+        }
+    }
+}
+
+/// The position and orientation of the listener of a [`SpatializerNode`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Listener {
+    pub position: [f32; 3],
+    /// The direction the listener is facing
+    pub forward: [f32; 3],
+    /// The direction of the top of the listener's head
+    pub up: [f32; 3],
+}
+
+impl Listener {
+    /// The position relative to the listener, in the coordinate system of
+    /// the listener (where `+X` is to the right, `+Y` is up, and `+Z` is
+    /// forward)
+    pub fn relative_position(&self, position: [f32; 3]) -> [f32; 3] {
+        let dir = sub(position, self.position);
+        let forward = normalize(self.forward);
+        let right = normalize(cross(forward, self.up));
+        let up = cross(right, forward);
+
+        [dot(dir, right), dot(dir, up), dot(dir, forward)]
+    }
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            forward: [0.0, 0.0, -1.0],
+            up: [0.0, 1.0, 0.0],
+        }
+    }
+}
+
+/// A node which positions a mono sound in 3D space, with distance
+/// attenuation and panning relative to a listener
+///
+/// The listener is set separately on each spatializer node.
+///
+/// The distance attenuation and air absorption settings are parameters,
+/// but the positions of the emitter and the listener are not, since they
+/// are expected to be updated continuously at runtime. These are not saved
+/// in a [`GraphDescription`].
+///
+/// [`GraphDescription`]: crate::graph::GraphDescription
+pub struct SpatializerNode {
+    // TODO: Find a good solution for webassembly.
+    raw_gain: Arc<AtomicF32>,
+    raw_pan: Arc<AtomicF32>,
+    raw_absorption_norm: Arc<AtomicF32>,
+    emitter_position: [f32; 3],
+    listener: Listener,
+    attenuation: DistanceAttenuation,
+    air_absorption: bool,
+}
+
+impl SpatializerNode {
+    /// The ID of the distance model parameter, where `0.0` is
+    /// [`DistanceModel::Inverse`], `1.0` is [`DistanceModel::Linear`], and
+    /// `2.0` is [`DistanceModel::Exponential`]
+    pub const PARAM_DISTANCE_MODEL: ParamID = ParamID(0);
+    /// The ID of the minimum distance parameter (see
+    /// [`DistanceAttenuation::min_distance`])
+    pub const PARAM_MIN_DISTANCE: ParamID = ParamID(1);
+    /// The ID of the maximum distance parameter (see
+    /// [`DistanceAttenuation::max_distance`])
+    pub const PARAM_MAX_DISTANCE: ParamID = ParamID(2);
+    /// The ID of the rolloff parameter (see
+    /// [`DistanceAttenuation::rolloff`])
+    pub const PARAM_ROLLOFF: ParamID = ParamID(3);
+    /// The ID of the air absorption parameter, where `1.0` is enabled and
+    /// `0.0` is disabled
+    pub const PARAM_AIR_ABSORPTION: ParamID = ParamID(4);
+
+    /// The largest minimum and maximum distance
+    pub const MAX_DISTANCE: f32 = 10_000.0;
+    /// The largest rolloff
+    pub const MAX_ROLLOFF: f32 = 10.0;
+
+    pub fn new(attenuation: DistanceAttenuation, air_absorption: bool) -> Self {
+        let mut node = Self {
+            raw_gain: Arc::new(AtomicF32::new(1.0)),
+            raw_pan: Arc::new(AtomicF32::new(0.0)),
+            raw_absorption_norm: Arc::new(AtomicF32::new(1.0)),
+            emitter_position: [0.0; 3],
+            listener: Listener::default(),
+            attenuation: DistanceAttenuation::default(),
+            air_absorption,
+        };
+        node.set_attenuation(attenuation);
+        node
+    }
+
+    pub fn emitter_position(&self) -> [f32; 3] {
+        self.emitter_position
+    }
+
+    pub fn set_emitter_position(&mut self, position: [f32; 3]) {
+        self.emitter_position = position;
+        self.update_raw_params();
+    }
+
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+        self.update_raw_params();
+    }
+
+    pub fn attenuation(&self) -> &DistanceAttenuation {
+        &self.attenuation
+    }
+
+    pub fn set_attenuation(&mut self, attenuation: DistanceAttenuation) {
+        self.attenuation = DistanceAttenuation {
+            model: attenuation.model,
+            min_distance: attenuation.min_distance.clamp(0.0, Self::MAX_DISTANCE),
+            max_distance: attenuation.max_distance.clamp(0.0, Self::MAX_DISTANCE),
+            rolloff: attenuation.rolloff.clamp(0.0, Self::MAX_ROLLOFF),
+        };
+        self.update_raw_params();
+    }
+
+    pub fn air_absorption(&self) -> bool {
+        self.air_absorption
+    }
+
+    /// Enable/disable the lowpass filter which simulates the absorption of
+    /// high frequencies by the air over long distances.
+    pub fn set_air_absorption(&mut self, air_absorption: bool) {
+        self.air_absorption = air_absorption;
+        self.update_raw_params();
+    }
+
+    /// The distance from the listener to the emitter
+    pub fn distance(&self) -> f32 {
+        length(sub(self.emitter_position, self.listener.position))
+    }
+
+    /// The pan of the emitter relative to the listener in the range
+    /// `[-1.0, 1.0]`, where `-1.0` is fully left and `1.0` is fully right
+    pub fn pan(&self) -> f32 {
+        let [x, _, z] = self.listener.relative_position(self.emitter_position);
+
+        // Project the direction onto the horizontal plane of the listener,
+        // and use the sine of the azimuth as the pan.
+        let horizontal = (x * x + z * z).sqrt();
+
+        if horizontal < 0.000001 {
+            0.0
+        } else {
+            (x / horizontal).clamp(-1.0, 1.0)
+        }
+    }
+
+    fn update_raw_params(&mut self) {
+        let distance = self.distance();
+
+        self.raw_gain
+            .store(self.attenuation.gain(distance), Ordering::Relaxed);
+        self.raw_pan.store(self.pan(), Ordering::Relaxed);
+
+        let absorption_norm = if self.air_absorption {
+            1.0 - self.attenuation.normalized(distance)
+        } else {
+            1.0
+        };
+        self.raw_absorption_norm
+            .store(absorption_norm, Ordering::Relaxed);
+    }
+}
+
+impl AudioNode for SpatializerNode {
+    fn debug_name(&self) -> &'static str {
+        "spatializer"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 1,
+            num_max_supported_inputs: 1,
+            num_min_supported_outputs: 2,
+            num_max_supported_outputs: 2,
+            updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: true,
+        }
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        let default = DistanceAttenuation::default();
+
+        vec![
+            ParamInfo {
+                id: Self::PARAM_DISTANCE_MODEL,
+                name: "distance model",
+                range: ParamRange::Linear(LinearRange::new(0.0, 2.0)),
+                default: default.model.to_u32() as f32,
+                unit: ParamUnit::Generic,
+                smoothing: None,
+            },
+            ParamInfo {
+                id: Self::PARAM_MIN_DISTANCE,
+                name: "min distance",
+                range: ParamRange::Linear(LinearRange::new(0.0, Self::MAX_DISTANCE)),
+                default: default.min_distance,
+                unit: ParamUnit::Generic,
+                smoothing: None,
+            },
+            ParamInfo {
+                id: Self::PARAM_MAX_DISTANCE,
+                name: "max distance",
+                range: ParamRange::Linear(LinearRange::new(0.0, Self::MAX_DISTANCE)),
+                default: default.max_distance,
+                unit: ParamUnit::Generic,
+                smoothing: None,
+            },
+            ParamInfo {
+                id: Self::PARAM_ROLLOFF,
+                name: "rolloff",
+                range: ParamRange::Linear(LinearRange::new(0.0, Self::MAX_ROLLOFF)),
+                default: default.rolloff,
+                unit: ParamUnit::Generic,
+                smoothing: None,
+            },
+            ParamInfo {
+                id: Self::PARAM_AIR_ABSORPTION,
+                name: "air absorption",
+                range: ParamRange::Linear(LinearRange::new(0.0, 1.0)),
+                default: 0.0,
+                unit: ParamUnit::Toggle,
+                smoothing: None,
+            },
+        ]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        let mut attenuation = self.attenuation;
+
+        match id {
+            Self::PARAM_DISTANCE_MODEL => {
+                attenuation.model = DistanceModel::from_u32(val.round() as u32)
+            }
+            Self::PARAM_MIN_DISTANCE => attenuation.min_distance = val,
+            Self::PARAM_MAX_DISTANCE => attenuation.max_distance = val,
+            Self::PARAM_ROLLOFF => attenuation.rolloff = val,
+            Self::PARAM_AIR_ABSORPTION => {
+                self.set_air_absorption(val >= 0.5);
+                return;
+            }
+            _ => return,
+        }
+
+        self.set_attenuation(attenuation);
+    }
+
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        match id {
+            Self::PARAM_DISTANCE_MODEL => Some(self.attenuation.model.to_u32() as f32),
+            Self::PARAM_MIN_DISTANCE => Some(self.attenuation.min_distance),
+            Self::PARAM_MAX_DISTANCE => Some(self.attenuation.max_distance),
+            Self::PARAM_ROLLOFF => Some(self.attenuation.rolloff),
+            Self::PARAM_AIR_ABSORPTION => Some(if self.air_absorption { 1.0 } else { 0.0 }),
+            _ => None,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if num_inputs != 1 || num_outputs != 2 {
+            return Err(format!("A Spatializer node must have 1 input and 2 outputs. Got num_inputs: {}, num_outputs: {}", num_inputs, num_outputs).into());
+        }
+
+        let new_smoother = |raw: &Arc<AtomicF32>| {
+            ParamSmoother::new(
+                raw.load(Ordering::Relaxed),
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            )
+        };
+
+        Ok(Box::new(SpatializerProcessor {
+            gain_smoother: new_smoother(&self.raw_gain),
+            pan_smoother: new_smoother(&self.raw_pan),
+            absorption_smoother: new_smoother(&self.raw_absorption_norm),
+            raw_gain: Arc::clone(&self.raw_gain),
+            raw_pan: Arc::clone(&self.raw_pan),
+            raw_absorption_norm: Arc::clone(&self.raw_absorption_norm),
+            absorption_range: NormToFreqRange::new(MIN_AIR_ABSORPTION_HZ, MAX_AIR_ABSORPTION_HZ),
+            absorption_state: 0.0,
+            sample_rate_recip: (sample_rate as f64).recip() as f32,
+        }))
+    }
+}
+
+struct SpatializerProcessor {
+    raw_gain: Arc<AtomicF32>,
+    raw_pan: Arc<AtomicF32>,
+    raw_absorption_norm: Arc<AtomicF32>,
+    gain_smoother: ParamSmoother,
+    pan_smoother: ParamSmoother,
+    absorption_smoother: ParamSmoother,
+    absorption_range: NormToFreqRange,
+    absorption_state: f32,
+    sample_rate_recip: f32,
+}
+
+impl AudioNodeProcessor for SpatializerProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        let raw_gain = self.raw_gain.load(Ordering::Relaxed);
+        let raw_pan = self.raw_pan.load(Ordering::Relaxed);
+        let raw_absorption_norm = self.raw_absorption_norm.load(Ordering::Relaxed);
+
+        if proc_info.in_silence_mask.all_channels_silent(1) {
+            // All channels are silent, so there is no need to process. Also reset
+            // the filters since they don't need to smooth anything.
+            self.gain_smoother.reset(raw_gain);
+            self.pan_smoother.reset(raw_pan);
+            self.absorption_smoother.reset(raw_absorption_norm);
+            self.absorption_state = 0.0;
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        }
+
+        let absorption_norm = self
+            .absorption_smoother
+            .set_and_process(raw_absorption_norm, frames);
+        let absorption_smoothing = absorption_norm.is_smoothing();
+        let absorption_const = absorption_norm.values[0];
+
+        let const_coeff = one_pole_coeff(
+            self.absorption_range.to_hz(absorption_const),
+            self.sample_rate_recip,
+        );
+        let bypass_absorption = !absorption_smoothing && absorption_const >= 1.0;
+
+        let gain = self.gain_smoother.set_and_process(raw_gain, frames);
+        let pan = self.pan_smoother.set_and_process(raw_pan, frames);
+
+        let (out_l, out_r) = outputs.split_at_mut(1);
+        let out_l = &mut out_l[0][..frames];
+        let out_r = &mut out_r[0][..frames];
+        let input = &inputs[0][..frames];
+
+        // Hint to the compiler to optimize loop.
+        assert!(frames <= gain.values.len());
+        assert!(frames <= pan.values.len());
+
+        let (const_gain_l, const_gain_r) = PanLaw::ConstantPower.gains(pan.values[0]);
+        let pan_smoothing = pan.is_smoothing();
+
+        for i in 0..frames {
+            let mut s = input[i];
+
+            if !bypass_absorption {
+                let coeff = if absorption_smoothing {
+                    one_pole_coeff(
+                        self.absorption_range.to_hz(absorption_norm[i]),
+                        self.sample_rate_recip,
+                    )
+                } else {
+                    const_coeff
+                };
+
+                self.absorption_state += coeff * (s - self.absorption_state);
+                s = self.absorption_state;
+            }
+
+            let (gain_l, gain_r) = if pan_smoothing {
+                PanLaw::ConstantPower.gains(pan[i])
+            } else {
+                (const_gain_l, const_gain_r)
+            };
+
+            s *= gain[i];
+            out_l[i] = s * gain_l;
+            out_r[i] = s * gain_r;
+        }
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for SpatializerNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+/// The coefficient of a one-pole lowpass filter
+fn one_pole_coeff(cutoff_hz: f32, sample_rate_recip: f32) -> f32 {
+    1.0 - (-std::f32::consts::TAU * cutoff_hz * sample_rate_recip).exp()
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(super) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(super) fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub(super) fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = length(a);
+    if len < 0.000001 {
+        a
+    } else {
+        [a[0] / len, a[1] / len, a[2] / len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_and_pan() {
+        let attenuation = DistanceAttenuation {
+            min_distance: 1.0,
+            max_distance: 10.0,
+            rolloff: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(attenuation.gain(0.5), 1.0);
+        assert_eq!(attenuation.gain(2.0), 0.5);
+        assert_eq!(attenuation.gain(100.0), 0.1);
+
+        let linear = DistanceAttenuation {
+            model: DistanceModel::Linear,
+            ..attenuation
+        };
+        assert_eq!(linear.gain(5.5), 0.5);
+        assert_eq!(linear.gain(10.0), 0.0);
+
+        let exponential = DistanceAttenuation {
+            model: DistanceModel::Exponential,
+            rolloff: 2.0,
+            ..attenuation
+        };
+        assert_eq!(exponential.gain(2.0), 0.25);
+
+        let mut node = SpatializerNode::new(attenuation, false);
+
+        // The default listener faces -Z with +Y up, so +X is to the right.
+        node.set_emitter_position([3.0, 0.0, 0.0]);
+        assert!((node.pan() - 1.0).abs() < 0.0001);
+        node.set_emitter_position([0.0, 5.0, -3.0]);
+        assert!(node.pan().abs() < 0.0001);
+
+        // Turn the listener to face +X, so the emitter is now on the left.
+        node.set_listener(Listener {
+            position: [1.0, 0.0, 0.0],
+            forward: [1.0, 0.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+        });
+        node.set_emitter_position([1.0, 0.0, -2.0]);
+        assert!((node.pan() + 1.0).abs() < 0.0001);
+        assert_eq!(node.distance(), 2.0);
+        assert_eq!(node.raw_gain.load(Ordering::Relaxed), 0.5);
+    }
+
+    #[test]
+    fn description_roundtrip() {
+        let attenuation = DistanceAttenuation {
+            model: DistanceModel::Exponential,
+            min_distance: 2.0,
+            max_distance: 50.0,
+            rolloff: 1.5,
+        };
+
+        let (graph, node_id) =
+            crate::graph::reload_node(1, 2, SpatializerNode::new(attenuation, true));
+        let node = graph
+            .node(node_id)
+            .unwrap()
+            .downcast_ref::<SpatializerNode>()
+            .unwrap();
+
+        assert_eq!(node.attenuation(), &attenuation);
+        assert!(node.air_absorption());
+    }
+}
@@ -14,6 +14,8 @@ impl AudioNode for StereoToMonoNode {
             num_min_supported_outputs: 1,
             num_max_supported_outputs: 1,
             updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: true,
         }
     }
 
@@ -0,0 +1,213 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::LinearRange,
+        smoother::{ParamSmoother, SmootherConfig},
+    },
+};
+
+/// A node which adjusts the stereo width of a stereo signal
+///
+/// The signal is converted to mid/side, the side signal is scaled by the
+/// width, and the result is converted back to left/right.
+pub struct StereoWidthNode {
+    // TODO: Find a good solution for webassembly.
+    raw_width: Arc<AtomicF32>,
+    width: f32,
+}
+
+impl StereoWidthNode {
+    /// The ID of the width parameter, in the range `[0.0, 2.0]` where
+    /// `0.0` is mono, `1.0` leaves the signal unchanged, and `2.0` doubles
+    /// the side signal
+    pub const PARAM_WIDTH: ParamID = ParamID(0);
+
+    pub fn new(width: f32) -> Self {
+        let width = width.clamp(0.0, 2.0);
+
+        Self {
+            raw_width: Arc::new(AtomicF32::new(width)),
+            width,
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.clamp(0.0, 2.0);
+        self.raw_width.store(self.width, Ordering::Relaxed);
+    }
+}
+
+impl AudioNode for StereoWidthNode {
+    fn debug_name(&self) -> &'static str {
+        "stereo_width"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 2,
+            num_max_supported_inputs: 2,
+            num_min_supported_outputs: 2,
+            num_max_supported_outputs: 2,
+            updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: true,
+        }
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![ParamInfo {
+            id: Self::PARAM_WIDTH,
+            name: "width",
+            range: ParamRange::Linear(LinearRange::new(0.0, 2.0)),
+            default: 1.0,
+            unit: ParamUnit::Generic,
+            smoothing: Some(SmootherConfig::default()),
+        }]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        if id == Self::PARAM_WIDTH {
+            self.set_width(val);
+        }
+    }
+
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        (id == Self::PARAM_WIDTH).then_some(self.width)
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if num_inputs != 2 || num_outputs != 2 {
+            return Err(format!("The number of inputs and outputs on a StereoWidth node must be 2. Got num_inputs: {}, num_outputs: {}", num_inputs, num_outputs).into());
+        }
+
+        Ok(Box::new(StereoWidthProcessor {
+            raw_width: Arc::clone(&self.raw_width),
+            width_smoother: ParamSmoother::new(
+                self.width,
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            ),
+        }))
+    }
+}
+
+struct StereoWidthProcessor {
+    raw_width: Arc<AtomicF32>,
+    width_smoother: ParamSmoother,
+}
+
+impl AudioNodeProcessor for StereoWidthProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        let raw_width = self.raw_width.load(Ordering::Relaxed);
+
+        if proc_info.in_silence_mask.all_channels_silent(2) {
+            // All channels are silent, so there is no need to process. Also reset
+            // the filter since it doesn't need to smooth anything.
+            self.width_smoother.reset(raw_width);
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        }
+
+        let width = self.width_smoother.set_and_process(raw_width, frames);
+
+        let (out_l, out_r) = outputs.split_at_mut(1);
+        let out_l = &mut out_l[0][..frames];
+        let out_r = &mut out_r[0][..frames];
+        let in_l = &inputs[0][..frames];
+        let in_r = &inputs[1][..frames];
+
+        // Hint to the compiler to optimize loop.
+        assert!(frames <= width.values.len());
+
+        if width.is_smoothing() {
+            for i in 0..frames {
+                let mid = (in_l[i] + in_r[i]) * 0.5;
+                let side = (in_l[i] - in_r[i]) * 0.5 * width[i];
+
+                out_l[i] = mid + side;
+                out_r[i] = mid - side;
+            }
+        } else if width.values[0] == 1.0 {
+            out_l.copy_from_slice(in_l);
+            out_r.copy_from_slice(in_r);
+
+            *proc_info.out_silence_mask = proc_info.in_silence_mask;
+        } else {
+            let width = width.values[0];
+
+            for i in 0..frames {
+                let mid = (in_l[i] + in_r[i]) * 0.5;
+                let side = (in_l[i] - in_r[i]) * 0.5 * width;
+
+                out_l[i] = mid + side;
+                out_r[i] = mid - side;
+            }
+        }
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for StereoWidthNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firewheel_core::SilenceMask;
+
+    use super::*;
+    use crate::basic_nodes::process_block;
+
+    #[test]
+    fn scale_side_signal() {
+        let process = |width: f32| {
+            let mut node = StereoWidthNode::new(width);
+            let mut processor = node.activate(48_000, 4, 2, 2).unwrap();
+
+            let in_l = [1.0, 0.5, 0.0, -1.0];
+            let in_r = [0.0, 0.5, 1.0, 1.0];
+            let mut out_l = [0.0; 4];
+            let mut out_r = [0.0; 4];
+
+            process_block(
+                processor.as_mut(),
+                4,
+                &[&in_l, &in_r],
+                &mut [&mut out_l, &mut out_r],
+                SilenceMask::NONE_SILENT,
+                &mut [],
+            );
+
+            (out_l, out_r)
+        };
+
+        assert_eq!(process(1.0), ([1.0, 0.5, 0.0, -1.0], [0.0, 0.5, 1.0, 1.0]));
+        assert_eq!(process(0.0), ([0.5, 0.5, 0.5, 0.0], [0.5, 0.5, 0.5, 0.0]));
+        assert_eq!(
+            process(2.0),
+            ([1.5, 0.5, -0.5, -2.0], [-0.5, 0.5, 1.5, 2.0])
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use firewheel_core::node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo};
+
+use crate::{
+    graph::{AudioGraph, AudioGraphConfig},
+    processor::{FirewheelProcessor, NodeProcessorEvent},
+    FirewheelGraphCtx, UpdateStatus,
+};
+
+/// A node which wraps an entire audio graph, so that a group of nodes can
+/// be used as a single unit in a parent graph.
+///
+/// The inputs of this node are sent to the graph input node of the inner
+/// graph, and the outputs of the graph output node of the inner graph are
+/// the outputs of this node. The number of inputs and outputs is set by
+/// the [`AudioGraphConfig`] of the inner graph.
+///
+/// The inner graph can be edited at any time (i.e. by downcasting the node
+/// from [`AudioGraph::node_mut`] of the parent graph). Changes are sent to
+/// the audio thread when the parent context is updated.
+pub struct SubGraphNode {
+    cx: FirewheelGraphCtx,
+    events: Vec<NodeProcessorEvent>,
+}
+
+impl SubGraphNode {
+    pub fn new(graph_config: AudioGraphConfig) -> Self {
+        Self {
+            cx: FirewheelGraphCtx::new(graph_config),
+            events: Vec::new(),
+        }
+    }
+
+    /// The inner audio graph
+    pub fn graph(&self) -> &AudioGraph {
+        &self.cx.graph
+    }
+
+    /// The inner audio graph
+    pub fn graph_mut(&mut self) -> &mut AudioGraph {
+        &mut self.cx.graph
+    }
+
+    /// Drain the events which were sent from the node processors in the
+    /// inner graph.
+    ///
+    /// The node IDs in the events refer to nodes in the inner graph.
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, NodeProcessorEvent> {
+        self.events.drain(..)
+    }
+
+    fn num_graph_ports(&self) -> (u32, u32) {
+        let graph = &self.cx.graph;
+
+        (
+            graph.node_info(graph.graph_in_node()).unwrap().num_outputs,
+            graph.node_info(graph.graph_out_node()).unwrap().num_inputs,
+        )
+    }
+}
+
+impl AudioNode for SubGraphNode {
+    fn debug_name(&self) -> &'static str {
+        "sub_graph"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        let (num_inputs, num_outputs) = self.num_graph_ports();
+
+        AudioNodeInfo {
+            num_min_supported_inputs: num_inputs,
+            num_max_supported_inputs: num_inputs,
+            num_min_supported_outputs: num_outputs,
+            num_max_supported_outputs: num_outputs,
+            updates: true,
+            split_at_events: false,
+            silent_when_inputs_silent: false,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        let (num_graph_inputs, num_graph_outputs) = self.num_graph_ports();
+        if num_inputs != num_graph_inputs as usize || num_outputs != num_graph_outputs as usize {
+            return Err(format!("The number of inputs and outputs on a SubGraph node must equal the number of ports on the inner graph. Expected num_inputs: {}, num_outputs: {}, got num_inputs: {}, num_outputs: {}", num_graph_inputs, num_graph_outputs, num_inputs, num_outputs).into());
+        }
+
+        let Some(processor) = self.cx.activate(
+            sample_rate,
+            num_inputs,
+            num_outputs,
+            max_block_frames,
+            Box::new(()),
+        ) else {
+            return Err("The inner graph of the SubGraph node is already activated".into());
+        };
+
+        // Compile the inner graph so that the schedule is ready before the
+        // first process cycle.
+        if let UpdateStatus::Active {
+            graph_error: Some(e),
+            ..
+        } = self.cx.update()
+        {
+            drop(processor);
+            self.cx.deactivate(false);
+
+            return Err(Box::new(e));
+        }
+
+        Ok(Box::new(SubGraphProcessor { processor }))
+    }
+
+    fn deactivate(&mut self, processor: Option<Box<dyn AudioNodeProcessor>>) {
+        if let Some(processor) = processor {
+            // Dropping the processor returns the inner nodes to the inner
+            // context.
+            drop(processor);
+            self.cx.deactivate(false);
+        } else {
+            self.cx.deactivate_immediately();
+        }
+    }
+
+    fn update(&mut self) {
+        if let UpdateStatus::Active {
+            graph_error,
+            events,
+        } = self.cx.update()
+        {
+            if let Some(e) = graph_error {
+                log::error!(
+                    "Failed to compile the inner graph of a SubGraph node: {}",
+                    e
+                );
+            }
+
+            self.events.extend(events);
+        }
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for SubGraphNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+struct SubGraphProcessor {
+    processor: FirewheelProcessor,
+}
+
+impl AudioNodeProcessor for SubGraphProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        self.processor
+            .process_sub_graph(frames, inputs, outputs, proc_info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        basic_nodes::{beep_test::BeepTestNode, VolumeNode},
+        context::TestGraphCtx,
+    };
+
+    #[test]
+    fn process_and_edit_sub_graph() {
+        let mut sub_graph = SubGraphNode::new(AudioGraphConfig {
+            num_graph_inputs: 1,
+            num_graph_outputs: 1,
+            ..Default::default()
+        });
+        let inner_graph = sub_graph.graph_mut();
+        let inner_in = inner_graph.graph_in_node();
+        let inner_out = inner_graph.graph_out_node();
+        let volume_node = VolumeNode::new(50.0);
+        let gain = volume_node.raw_gain();
+        let volume = inner_graph.add_node(1, 1, volume_node);
+        inner_graph.connect(inner_in, 0, volume, 0, true).unwrap();
+        inner_graph.connect(volume, 0, inner_out, 0, true).unwrap();
+
+        let mut sub_graph_id = None;
+        let mut test_cx = TestGraphCtx::new(AudioGraphConfig::default(), |graph| {
+            let graph_out = graph.graph_out_node();
+            let beep = graph.add_node(0, 2, BeepTestNode::new(440.0, -12.0, true));
+            let sub_graph = graph.add_node(1, 1, sub_graph);
+            graph.connect(beep, 0, sub_graph, 0, true).unwrap();
+            graph.connect(sub_graph, 0, graph_out, 0, true).unwrap();
+            graph.connect(beep, 1, graph_out, 1, true).unwrap();
+
+            sub_graph_id = Some(sub_graph);
+        });
+        let sub_graph = sub_graph_id.unwrap();
+
+        let mut output = vec![0.0; 128 * 2];
+        test_cx.process(&[], &mut output);
+        for frame in output.chunks(2) {
+            assert!((frame[0] - frame[1] * gain).abs() < 0.0001);
+        }
+        assert!(output.iter().any(|&s| s != 0.0));
+
+        // Bypass the volume node in the inner graph.
+        let inner_graph = test_cx
+            .cx
+            .graph
+            .node_mut(sub_graph)
+            .unwrap()
+            .downcast_mut::<SubGraphNode>()
+            .unwrap()
+            .graph_mut();
+        inner_graph.remove_node(volume).unwrap();
+        inner_graph
+            .connect(inner_in, 0, inner_out, 0, true)
+            .unwrap();
+        test_cx.cx.update();
+
+        test_cx.process(&[], &mut output);
+        for frame in output.chunks(2) {
+            assert_eq!(frame[0], frame[1]);
+        }
+
+        let TestGraphCtx {
+            processor, mut cx, ..
+        } = test_cx;
+        drop(processor);
+        cx.update();
+        assert!(!cx
+            .graph
+            .node(sub_graph)
+            .unwrap()
+            .downcast_ref::<SubGraphNode>()
+            .unwrap()
+            .cx
+            .is_activated());
+    }
+}
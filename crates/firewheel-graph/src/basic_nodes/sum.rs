@@ -14,6 +14,8 @@ impl AudioNode for SumNode {
             num_min_supported_outputs: 1,
             num_max_supported_outputs: 64,
             updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: true,
         }
     }
 
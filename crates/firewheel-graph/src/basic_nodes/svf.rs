@@ -0,0 +1,445 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use atomic_float::AtomicF32;
+use firewheel_core::{
+    node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::{LinearRange, NormToFreqRange, NormToPowRange},
+        smoother::{ParamSmoother, SmootherConfig},
+    },
+};
+
+/// The state of a filter is considered settled (silent) once it falls
+/// below this value (about -120 dB).
+const SETTLE_EPSILON: f32 = 0.000001;
+
+/// The response of an [`SvfNode`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SvfMode {
+    #[default]
+    Lowpass,
+    Highpass,
+    Bandpass,
+    Notch,
+    /// A resonant peak at the cutoff frequency (the lowpass response minus
+    /// the highpass response)
+    Peak,
+}
+
+impl SvfMode {
+    fn from_u32(val: u32) -> Self {
+        match val {
+            1 => Self::Highpass,
+            2 => Self::Bandpass,
+            3 => Self::Notch,
+            4 => Self::Peak,
+            _ => Self::Lowpass,
+        }
+    }
+
+    fn to_u32(self) -> u32 {
+        match self {
+            Self::Lowpass => 0,
+            Self::Highpass => 1,
+            Self::Bandpass => 2,
+            Self::Notch => 3,
+            Self::Peak => 4,
+        }
+    }
+}
+
+/// A multi-channel state variable filter
+///
+/// Once the inputs become silent, the node keeps processing until the
+/// filter has decayed, so the tail of the signal is not cut off.
+pub struct SvfNode {
+    // TODO: Find a good solution for webassembly.
+    raw_cutoff_norm: Arc<AtomicF32>,
+    raw_q: Arc<AtomicF32>,
+    mode: Arc<AtomicU32>,
+    cutoff_hz: f32,
+    q: f32,
+}
+
+impl SvfNode {
+    /// The ID of the cutoff frequency parameter in Hz
+    pub const PARAM_CUTOFF: ParamID = ParamID(0);
+    /// The ID of the Q (resonance) parameter
+    pub const PARAM_Q: ParamID = ParamID(1);
+    /// The ID of the mode parameter, where `0.0` is [`SvfMode::Lowpass`],
+    /// `1.0` is [`SvfMode::Highpass`], `2.0` is [`SvfMode::Bandpass`], `3.0`
+    /// is [`SvfMode::Notch`], and `4.0` is [`SvfMode::Peak`]
+    pub const PARAM_MODE: ParamID = ParamID(2);
+
+    /// The minimum cutoff frequency in Hz
+    pub const MIN_CUTOFF_HZ: f32 = 20.0;
+    /// The maximum cutoff frequency in Hz
+    pub const MAX_CUTOFF_HZ: f32 = 20_000.0;
+    /// The minimum Q
+    pub const MIN_Q: f32 = 0.1;
+    /// The maximum Q
+    pub const MAX_Q: f32 = 18.0;
+
+    pub fn new(mode: SvfMode, cutoff_hz: f32, q: f32) -> Self {
+        let cutoff_hz = cutoff_hz.clamp(Self::MIN_CUTOFF_HZ, Self::MAX_CUTOFF_HZ);
+        let q = q.clamp(Self::MIN_Q, Self::MAX_Q);
+
+        Self {
+            raw_cutoff_norm: Arc::new(AtomicF32::new(cutoff_range().to_normalized(cutoff_hz))),
+            raw_q: Arc::new(AtomicF32::new(q)),
+            mode: Arc::new(AtomicU32::new(mode.to_u32())),
+            cutoff_hz,
+            q,
+        }
+    }
+
+    pub fn cutoff_hz(&self) -> f32 {
+        self.cutoff_hz
+    }
+
+    pub fn set_cutoff_hz(&mut self, cutoff_hz: f32) {
+        self.cutoff_hz = cutoff_hz.clamp(Self::MIN_CUTOFF_HZ, Self::MAX_CUTOFF_HZ);
+        self.raw_cutoff_norm.store(
+            cutoff_range().to_normalized(self.cutoff_hz),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn q(&self) -> f32 {
+        self.q
+    }
+
+    pub fn set_q(&mut self, q: f32) {
+        self.q = q.clamp(Self::MIN_Q, Self::MAX_Q);
+        self.raw_q.store(self.q, Ordering::Relaxed);
+    }
+
+    pub fn mode(&self) -> SvfMode {
+        SvfMode::from_u32(self.mode.load(Ordering::Relaxed))
+    }
+
+    pub fn set_mode(&mut self, mode: SvfMode) {
+        self.mode.store(mode.to_u32(), Ordering::Relaxed);
+    }
+}
+
+fn cutoff_range() -> NormToFreqRange {
+    NormToFreqRange::new(SvfNode::MIN_CUTOFF_HZ, SvfNode::MAX_CUTOFF_HZ)
+}
+
+impl AudioNode for SvfNode {
+    fn debug_name(&self) -> &'static str {
+        "svf"
+    }
+
+    fn info(&self) -> AudioNodeInfo {
+        AudioNodeInfo {
+            num_min_supported_inputs: 1,
+            num_max_supported_inputs: 64,
+            num_min_supported_outputs: 1,
+            num_max_supported_outputs: 64,
+            updates: false,
+            split_at_events: false,
+            // The filter may still be ringing when the inputs become silent.
+            silent_when_inputs_silent: false,
+        }
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo {
+                id: Self::PARAM_CUTOFF,
+                name: "cutoff",
+                range: ParamRange::Freq(cutoff_range()),
+                default: 1_000.0,
+                unit: ParamUnit::Hz,
+                smoothing: Some(SmootherConfig::default()),
+            },
+            ParamInfo {
+                id: Self::PARAM_Q,
+                name: "q",
+                range: ParamRange::Pow(NormToPowRange::new(Self::MIN_Q, Self::MAX_Q, 2.0)),
+                default: std::f32::consts::FRAC_1_SQRT_2,
+                unit: ParamUnit::Generic,
+                smoothing: Some(SmootherConfig::default()),
+            },
+            ParamInfo {
+                id: Self::PARAM_MODE,
+                name: "mode",
+                range: ParamRange::Linear(LinearRange::new(0.0, 4.0)),
+                default: 0.0,
+                unit: ParamUnit::Generic,
+                smoothing: None,
+            },
+        ]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        match id {
+            Self::PARAM_CUTOFF => self.set_cutoff_hz(val),
+            Self::PARAM_Q => self.set_q(val),
+            Self::PARAM_MODE => self.set_mode(SvfMode::from_u32(val.round() as u32)),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        match id {
+            Self::PARAM_CUTOFF => Some(self.cutoff_hz),
+            Self::PARAM_Q => Some(self.q),
+            Self::PARAM_MODE => Some(self.mode().to_u32() as f32),
+            _ => None,
+        }
+    }
+
+    fn activate(
+        &mut self,
+        sample_rate: u32,
+        max_block_frames: usize,
+        num_inputs: usize,
+        num_outputs: usize,
+    ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn std::error::Error>> {
+        if num_inputs != num_outputs {
+            return Err(format!("The number of inputs on a Svf node must equal the number of outputs. Got num_inputs: {}, num_outputs: {}", num_inputs, num_outputs).into());
+        }
+
+        let raw_cutoff_norm = self.raw_cutoff_norm.load(Ordering::Relaxed);
+
+        Ok(Box::new(SvfProcessor {
+            raw_cutoff_norm: Arc::clone(&self.raw_cutoff_norm),
+            raw_q: Arc::clone(&self.raw_q),
+            mode: Arc::clone(&self.mode),
+            cutoff_smoother: ParamSmoother::new(
+                raw_cutoff_norm,
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            ),
+            q_smoother: ParamSmoother::new(
+                self.q,
+                sample_rate,
+                max_block_frames,
+                Default::default(),
+            ),
+            cutoff_range: cutoff_range(),
+            // Keep the cutoff safely below the Nyquist frequency.
+            max_cutoff_hz: Self::MAX_CUTOFF_HZ.min(sample_rate as f32 * 0.49),
+            sample_rate_recip: (sample_rate as f64).recip() as f32,
+            coeffs: vec![SvfCoeffs::default(); max_block_frames],
+            states: vec![SvfState::default(); num_inputs],
+        }))
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct SvfCoeffs {
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    k: f32,
+}
+
+impl SvfCoeffs {
+    fn new(cutoff_hz: f32, q: f32, sample_rate_recip: f32) -> Self {
+        let g = (std::f32::consts::PI * cutoff_hz * sample_rate_recip).tan();
+        let k = q.recip();
+
+        let a1 = (1.0 + g * (g + k)).recip();
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        Self { a1, a2, a3, k }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct SvfState {
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl SvfState {
+    #[inline(always)]
+    fn tick(&mut self, v0: f32, coeffs: &SvfCoeffs, mode: SvfMode) -> f32 {
+        let v3 = v0 - self.ic2eq;
+        let v1 = coeffs.a1 * self.ic1eq + coeffs.a2 * v3;
+        let v2 = self.ic2eq + coeffs.a2 * self.ic1eq + coeffs.a3 * v3;
+
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+
+        match mode {
+            SvfMode::Lowpass => v2,
+            SvfMode::Highpass => v0 - coeffs.k * v1 - v2,
+            SvfMode::Bandpass => v1,
+            SvfMode::Notch => v0 - coeffs.k * v1,
+            SvfMode::Peak => 2.0 * v2 - v0 + coeffs.k * v1,
+        }
+    }
+
+    fn is_settled(&self) -> bool {
+        self.ic1eq.abs() < SETTLE_EPSILON && self.ic2eq.abs() < SETTLE_EPSILON
+    }
+}
+
+struct SvfProcessor {
+    raw_cutoff_norm: Arc<AtomicF32>,
+    raw_q: Arc<AtomicF32>,
+    mode: Arc<AtomicU32>,
+    cutoff_smoother: ParamSmoother,
+    q_smoother: ParamSmoother,
+    cutoff_range: NormToFreqRange,
+    max_cutoff_hz: f32,
+    sample_rate_recip: f32,
+    coeffs: Vec<SvfCoeffs>,
+    states: Vec<SvfState>,
+}
+
+impl AudioNodeProcessor for SvfProcessor {
+    fn process(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        let raw_cutoff_norm = self.raw_cutoff_norm.load(Ordering::Relaxed);
+        let raw_q = self.raw_q.load(Ordering::Relaxed);
+
+        if proc_info.in_silence_mask.all_channels_silent(inputs.len())
+            && self.states.iter().all(|s| s.is_settled())
+        {
+            // All channels are silent and the tail has decayed, so there is
+            // no need to process. Also reset the filters since they don't
+            // need to smooth anything.
+            self.cutoff_smoother.reset(raw_cutoff_norm);
+            self.q_smoother.reset(raw_q);
+            self.states.fill(SvfState::default());
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+            return;
+        }
+
+        let mode = SvfMode::from_u32(self.mode.load(Ordering::Relaxed));
+        let cutoff_norm = self
+            .cutoff_smoother
+            .set_and_process(raw_cutoff_norm, frames);
+        let q = self.q_smoother.set_and_process(raw_q, frames);
+
+        // Only compute the coefficients for every frame while smoothing.
+        let num_coeffs = if cutoff_norm.is_smoothing() || q.is_smoothing() {
+            frames
+        } else {
+            1
+        };
+
+        for i in 0..num_coeffs {
+            let cutoff_hz = self
+                .cutoff_range
+                .to_hz(cutoff_norm[i])
+                .min(self.max_cutoff_hz);
+
+            self.coeffs[i] = SvfCoeffs::new(cutoff_hz, q[i], self.sample_rate_recip);
+        }
+
+        for (ch_i, ((output, input), state)) in outputs
+            .iter_mut()
+            .zip(inputs.iter())
+            .zip(self.states.iter_mut())
+            .enumerate()
+        {
+            if proc_info.in_silence_mask.is_channel_silent(ch_i) && state.is_settled() {
+                *state = SvfState::default();
+                output[..frames].fill(0.0);
+                proc_info.out_silence_mask.set_channel(ch_i, true);
+                continue;
+            }
+
+            let output = &mut output[..frames];
+            let input = &input[..frames];
+
+            if num_coeffs == 1 {
+                let coeffs = self.coeffs[0];
+
+                for (out_s, &in_s) in output.iter_mut().zip(input.iter()) {
+                    *out_s = state.tick(in_s, &coeffs, mode);
+                }
+            } else {
+                for ((out_s, &in_s), coeffs) in
+                    output.iter_mut().zip(input.iter()).zip(self.coeffs.iter())
+                {
+                    *out_s = state.tick(in_s, coeffs, mode);
+                }
+            }
+        }
+    }
+}
+
+impl Into<Box<dyn AudioNode>> for SvfNode {
+    fn into(self) -> Box<dyn AudioNode> {
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svf_response_and_tail() {
+        let coeffs = SvfCoeffs::new(1_000.0, std::f32::consts::FRAC_1_SQRT_2, 1.0 / 48_000.0);
+
+        // Measure the steady-state amplitude of a sine wave at the given
+        // frequency.
+        let amplitude = |mode: SvfMode, freq: f32| {
+            let mut state = SvfState::default();
+            let mut peak = 0.0f32;
+
+            for i in 0..48_000 {
+                let t = i as f32 / 48_000.0;
+                let out = state.tick((std::f32::consts::TAU * freq * t).sin(), &coeffs, mode);
+
+                if i >= 24_000 {
+                    peak = peak.max(out.abs());
+                }
+            }
+
+            peak
+        };
+
+        assert!(amplitude(SvfMode::Lowpass, 100.0) > 0.99);
+        assert!(amplitude(SvfMode::Lowpass, 10_000.0) < 0.02);
+        assert!(amplitude(SvfMode::Highpass, 100.0) < 0.02);
+        assert!(amplitude(SvfMode::Highpass, 10_000.0) > 0.95);
+        assert!(amplitude(SvfMode::Bandpass, 100.0) < 0.2);
+        assert!(amplitude(SvfMode::Notch, 1_000.0) < 0.01);
+        assert!(amplitude(SvfMode::Peak, 1_000.0) > 1.35);
+
+        // The filter keeps ringing after an impulse, and eventually settles.
+        let mut state = SvfState::default();
+        state.tick(1.0, &coeffs, SvfMode::Lowpass);
+        assert!(!state.is_settled());
+        for _ in 0..48_000 {
+            state.tick(0.0, &coeffs, SvfMode::Lowpass);
+        }
+        assert!(state.is_settled());
+    }
+
+    #[test]
+    fn description_roundtrip() {
+        let (graph, node_id) =
+            crate::graph::reload_node(1, 1, SvfNode::new(SvfMode::Highpass, 500.0, 1.0));
+        let node = graph
+            .node(node_id)
+            .unwrap()
+            .downcast_ref::<SvfNode>()
+            .unwrap();
+
+        assert_eq!(node.mode(), SvfMode::Highpass);
+        assert_eq!(node.cutoff_hz(), 500.0);
+        assert_eq!(node.q(), 1.0);
+    }
+}
@@ -1,7 +1,11 @@
 use atomic_float::AtomicF32;
 use firewheel_core::{
     node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
-    param::{range::percent_volume_to_raw_gain, smoother::ParamSmoother},
+    param::{
+        info::{ParamID, ParamInfo, ParamRange, ParamUnit},
+        range::{percent_volume_to_raw_gain, LinearRange},
+        smoother::{ParamSmoother, SmootherConfig},
+    },
 };
 use std::sync::{atomic::Ordering, Arc};
 
@@ -12,6 +16,9 @@ pub struct VolumeNode {
 }
 
 impl VolumeNode {
+    /// The ID of the volume parameter in percent
+    pub const PARAM_VOLUME: ParamID = ParamID(0);
+
     pub fn new(percent_volume: f32) -> Self {
         let percent_volume = percent_volume.max(0.0);
 
@@ -50,9 +57,32 @@ impl AudioNode for VolumeNode {
             num_min_supported_outputs: 1,
             num_max_supported_outputs: 64,
             updates: false,
+            split_at_events: false,
+            silent_when_inputs_silent: true,
+        }
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![ParamInfo {
+            id: Self::PARAM_VOLUME,
+            name: "volume",
+            range: ParamRange::Linear(LinearRange::new(0.0, 200.0)),
+            default: 100.0,
+            unit: ParamUnit::Percent,
+            smoothing: Some(SmootherConfig::default()),
+        }]
+    }
+
+    fn set_param(&mut self, id: ParamID, val: f32) {
+        if id == Self::PARAM_VOLUME {
+            self.set_percent_volume(val);
         }
     }
 
+    fn get_param(&self, id: ParamID) -> Option<f32> {
+        (id == Self::PARAM_VOLUME).then_some(self.percent_volume)
+    }
+
     fn activate(
         &mut self,
         sample_rate: u32,
@@ -1,14 +1,26 @@
 use std::{
     any::Any,
     error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
+use firewheel_core::event::{NodeEvent, NodeEventType};
 use rtrb::PushError;
 
+#[cfg(feature = "profiling")]
+use crate::profiling::{DspProfile, ProfileReport};
 use crate::{
+    backend::{AudioBackend, DeviceInfo, StreamInfo},
     graph::{AudioGraph, AudioGraphConfig, CompileGraphError},
-    processor::{ContextToProcessorMsg, FirewheelProcessor, ProcessorToContextMsg},
+    processor::{
+        ContextToProcessorMsg, FirewheelProcessor, NodeProcessorEvent, ProcessorToContextMsg,
+        ScheduledEvent,
+    },
 };
 
 const CHANNEL_CAPACITY: usize = 16;
@@ -21,15 +33,35 @@ struct ActiveState {
     // use a different channel type when targeting webassembly.
     to_executor_tx: rtrb::Producer<ContextToProcessorMsg>,
     from_executor_rx: rtrb::Consumer<ProcessorToContextMsg>,
+    to_executor_event_tx: rtrb::Producer<ScheduledEvent>,
+    from_executor_event_rx: rtrb::Consumer<NodeEvent>,
+    from_executor_out_event_rx: rtrb::Consumer<NodeProcessorEvent>,
+
+    /// The number of custom events which were sent to the processor and
+    /// have not been returned yet. This is never greater than the capacity
+    /// of the return channel, so the processor never has to drop one in
+    /// the audio thread.
+    custom_events_in_flight: usize,
 
+    clock_frames: Arc<AtomicU64>,
     sample_rate: u32,
     max_block_frames: usize,
+
+    /// A profile report which could not be sent back to the processor
+    /// because the message channel was full.
+    #[cfg(feature = "profiling")]
+    unsent_profile_report: Option<Box<ProfileReport>>,
 }
 
 pub struct FirewheelGraphCtx {
     pub graph: AudioGraph,
 
     active_state: Option<ActiveState>,
+    event_queue_capacity: usize,
+    node_event_capacity: usize,
+
+    #[cfg(feature = "profiling")]
+    profile: DspProfile,
 }
 
 impl FirewheelGraphCtx {
@@ -37,6 +69,10 @@ impl FirewheelGraphCtx {
         Self {
             graph: AudioGraph::new(&graph_config),
             active_state: None,
+            event_queue_capacity: graph_config.event_queue_capacity,
+            node_event_capacity: graph_config.node_event_capacity,
+            #[cfg(feature = "profiling")]
+            profile: DspProfile::default(),
         }
     }
 
@@ -62,23 +98,55 @@ impl FirewheelGraphCtx {
             rtrb::RingBuffer::<ContextToProcessorMsg>::new(CHANNEL_CAPACITY);
         let (to_graph_tx, from_executor_rx) =
             rtrb::RingBuffer::<ProcessorToContextMsg>::new(CHANNEL_CAPACITY);
+        let (to_executor_event_tx, from_graph_event_rx) =
+            rtrb::RingBuffer::<ScheduledEvent>::new(self.event_queue_capacity);
+        let (to_graph_event_tx, from_executor_event_rx) =
+            rtrb::RingBuffer::<NodeEvent>::new(self.event_queue_capacity);
+        let (to_graph_out_event_tx, from_executor_out_event_rx) =
+            rtrb::RingBuffer::<NodeProcessorEvent>::new(self.event_queue_capacity);
+
+        let clock_frames = Arc::new(AtomicU64::new(0));
 
         self.active_state = Some(ActiveState {
             to_executor_tx,
             from_executor_rx,
+            to_executor_event_tx,
+            from_executor_event_rx,
+            from_executor_out_event_rx,
+            custom_events_in_flight: 0,
+            clock_frames: Arc::clone(&clock_frames),
             sample_rate,
             max_block_frames,
+            #[cfg(feature = "profiling")]
+            unsent_profile_report: None,
         });
 
-        Some(FirewheelProcessor::new(
+        #[cfg_attr(not(feature = "profiling"), allow(unused_mut))]
+        let mut processor = FirewheelProcessor::new(
             from_graph_rx,
             to_graph_tx,
+            from_graph_event_rx,
+            to_graph_event_tx,
+            to_graph_out_event_tx,
+            self.node_event_capacity,
+            clock_frames,
             self.graph.current_node_capacity(),
+            sample_rate,
             num_stream_in_channels,
             num_stream_out_channels,
             max_block_frames,
             user_cx,
-        ))
+        );
+
+        #[cfg(feature = "profiling")]
+        {
+            self.profile = DspProfile::default();
+            processor.set_profile_report(Box::new(ProfileReport::new(
+                self.graph.current_node_capacity(),
+            )));
+        }
+
+        Some(processor)
     }
 
     /// Returns whether or not this context is currently activated.
@@ -86,6 +154,30 @@ impl FirewheelGraphCtx {
         self.active_state.is_some()
     }
 
+    /// The number of frames that have been processed since the context
+    /// was activated.
+    ///
+    /// This is the clock used to schedule events with
+    /// [`EventTime::ClockFrames`] and [`EventTime::ClockSecs`]. It is
+    /// updated once per process cycle, and it is reset to `0` every time
+    /// the context is activated.
+    ///
+    /// [`EventTime::ClockFrames`]: firewheel_core::event::EventTime::ClockFrames
+    /// [`EventTime::ClockSecs`]: firewheel_core::event::EventTime::ClockSecs
+    pub fn clock_frames(&self) -> u64 {
+        self.active_state
+            .as_ref()
+            .map(|s| s.clock_frames.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    /// Statistics on the time spent processing the audio graph, as of
+    /// the last call to [`FirewheelGraphCtx::update`].
+    #[cfg(feature = "profiling")]
+    pub fn profile(&self) -> &DspProfile {
+        &self.profile
+    }
+
     /// Update the firewheel context.
     ///
     /// This must be called reguarly once the context has been activated
@@ -115,6 +207,11 @@ impl FirewheelGraphCtx {
             return UpdateStatus::Inactive;
         };
 
+        let mut events = Vec::new();
+        while let Ok(event) = state.from_executor_out_event_rx.pop() {
+            events.push(event);
+        }
+
         if self.graph.needs_compile() {
             match self
                 .graph
@@ -139,12 +236,53 @@ impl FirewheelGraphCtx {
                 Err(e) => {
                     return UpdateStatus::Active {
                         graph_error: Some(e),
+                        events,
                     };
                 }
             }
         }
 
-        UpdateStatus::Active { graph_error: None }
+        // Send the events after the new schedule so that events for newly
+        // added nodes are not discarded.
+        let event_queue_capacity = self.event_queue_capacity;
+        let event_queue = self.graph.event_queue_mut();
+        let mut slots = state.to_executor_event_tx.slots();
+        let num_events = event_queue
+            .iter()
+            .take_while(|(_, _, event)| {
+                if slots == 0 {
+                    return false;
+                }
+
+                if let NodeEventType::Custom(_) = event {
+                    if state.custom_events_in_flight == event_queue_capacity {
+                        return false;
+                    }
+
+                    state.custom_events_in_flight += 1;
+                }
+
+                slots -= 1;
+                true
+            })
+            .count();
+        if num_events < event_queue.len() {
+            log::warn!("Firewheel event channel is full. Some events will be sent later.");
+        }
+        for (node_id, time, event) in event_queue.drain(..num_events) {
+            let _ = state.to_executor_event_tx.push(ScheduledEvent {
+                node_id,
+                event: NodeEvent {
+                    clock_frame: time.to_clock_frame(state.sample_rate),
+                    event,
+                },
+            });
+        }
+
+        UpdateStatus::Active {
+            graph_error: None,
+            events,
+        }
     }
 
     /// Deactivate the firewheel context.
@@ -210,6 +348,15 @@ impl FirewheelGraphCtx {
         dropped_user_cx
     }
 
+    /// Deactivate the context without waiting for the processor to be
+    /// dropped (i.e. because the processor was lost along with the
+    /// processor of a parent graph).
+    pub(crate) fn deactivate_immediately(&mut self) {
+        if self.active_state.take().is_some() {
+            self.graph.deactivate();
+        }
+    }
+
     fn update_internal(
         &mut self,
         dropped: &mut bool,
@@ -222,6 +369,15 @@ impl FirewheelGraphCtx {
         while let Ok(msg) = state.from_executor_rx.pop() {
             match msg {
                 ProcessorToContextMsg::ReturnSchedule(schedule_data) => {
+                    // Custom events which were still queued in removed nodes
+                    // are returned along with them.
+                    state.custom_events_in_flight -= schedule_data
+                        .removed_node_processors
+                        .iter()
+                        .flat_map(|(_, node)| node.events.iter())
+                        .filter(|e| matches!(e.event, NodeEventType::Custom(_)))
+                        .count();
+
                     self.graph.on_schedule_returned(schedule_data);
                 }
                 ProcessorToContextMsg::Dropped { nodes, user_cx, .. } => {
@@ -229,8 +385,40 @@ impl FirewheelGraphCtx {
                     *dropped = true;
                     *dropped_user_cx = user_cx;
                 }
+                #[cfg(feature = "profiling")]
+                ProcessorToContextMsg::ProfileReport(mut report) => {
+                    let graph = &self.graph;
+                    self.profile.update(&mut report, |idx| {
+                        graph
+                            .node_info(crate::graph::NodeID {
+                                idx,
+                                debug_name: "",
+                            })
+                            .map(|n| n.id)
+                    });
+
+                    report.reserve_nodes(self.graph.current_node_capacity());
+                    state.unsent_profile_report = Some(report);
+                }
+            }
+        }
+
+        // Send the report back to the processor to be filled in again.
+        #[cfg(feature = "profiling")]
+        if let Some(report) = state.unsent_profile_report.take() {
+            if let Err(PushError::Full(ContextToProcessorMsg::ProfileReport(report))) = state
+                .to_executor_tx
+                .push(ContextToProcessorMsg::ProfileReport(report))
+            {
+                state.unsent_profile_report = Some(report);
             }
         }
+
+        // Drop processed events here so that they are not deallocated in
+        // the audio thread.
+        while state.from_executor_event_rx.pop().is_ok() {
+            state.custom_events_in_flight -= 1;
+        }
     }
 }
 
@@ -246,9 +434,750 @@ pub enum UpdateStatus {
     Inactive,
     Active {
         graph_error: Option<CompileGraphError>,
+        /// The events which were sent from node processors since the last
+        /// update.
+        events: Vec<NodeProcessorEvent>,
+    },
+    /// The audio stream was restarted by the auto-reconnect mode of a
+    /// [`FirewheelCtx`]. The graph was reactivated with all of its nodes
+    /// and edges preserved.
+    Reconnected {
+        /// The error which caused the stream to stop, or `None` if the
+        /// stream was restarted because the default device changed.
+        stream_error: Option<Box<dyn Error>>,
     },
+    /// The auto-reconnect mode of a [`FirewheelCtx`] failed to restart
+    /// the audio stream. Another attempt will be made once the retry
+    /// interval has elapsed.
+    ReconnectFailed {
+        error: Box<dyn Error>,
+    },
+    /// The auto-reconnect mode of a [`FirewheelCtx`] is waiting for the
+    /// retry interval to elapse before attempting to restart the audio
+    /// stream again.
+    Reconnecting,
     Deactivated {
         error: Option<Box<dyn Error>>,
         returned_user_cx: Option<Box<dyn Any + Send>>,
     },
 }
+
+struct ActiveBackendState<B: AudioBackend> {
+    backend: B,
+    stream_info: StreamInfo,
+}
+
+struct ReconnectState {
+    user_cx: Box<dyn Any + Send>,
+    last_attempt: Option<Instant>,
+}
+
+/// The configuration of the auto-reconnect mode of a [`FirewheelCtx`].
+///
+/// When enabled, the context will restart the audio stream (and
+/// reactivate the graph without losing any nodes or edges) if the stream
+/// stops unexpectedly or if the default device changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoReconnectConfig {
+    /// How long to wait between attempts to reconnect.
+    ///
+    /// By default this is set to one second.
+    pub retry_interval: Duration,
+    /// How often to check whether the default device has changed.
+    ///
+    /// Set to `None` to not follow changes to the default device.
+    ///
+    /// By default this is set to one second.
+    pub default_device_poll_interval: Option<Duration>,
+}
+
+impl Default for AutoReconnectConfig {
+    fn default() -> Self {
+        Self {
+            retry_interval: Duration::from_secs(1),
+            default_device_poll_interval: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// A Firewheel context which runs the audio graph on an [`AudioBackend`].
+pub struct FirewheelCtx<B: AudioBackend> {
+    cx: FirewheelGraphCtx,
+    active_state: Option<ActiveBackendState<B>>,
+    config: Option<B::Config>,
+    auto_reconnect: Option<AutoReconnectConfig>,
+    reconnect_state: Option<ReconnectState>,
+    last_default_device_poll: Instant,
+}
+
+impl<B: AudioBackend> FirewheelCtx<B> {
+    pub fn new(graph_config: AudioGraphConfig) -> Self {
+        Self {
+            cx: FirewheelGraphCtx::new(graph_config),
+            active_state: None,
+            config: None,
+            auto_reconnect: None,
+            reconnect_state: None,
+            last_default_device_poll: Instant::now(),
+        }
+    }
+
+    pub fn graph(&self) -> &AudioGraph {
+        &self.cx.graph
+    }
+
+    pub fn graph_mut(&mut self) -> &mut AudioGraph {
+        &mut self.cx.graph
+    }
+
+    /// Get a list of the available audio input devices.
+    pub fn available_input_devices(&self) -> Vec<DeviceInfo> {
+        B::available_input_devices()
+    }
+
+    /// Get a list of the available audio output devices.
+    pub fn available_output_devices(&self) -> Vec<DeviceInfo> {
+        B::available_output_devices()
+    }
+
+    /// Enable or disable the auto-reconnect mode.
+    ///
+    /// Set to `None` to disable (the default). When disabled, an error in
+    /// the audio stream deactivates the context.
+    pub fn set_auto_reconnect(&mut self, auto_reconnect: Option<AutoReconnectConfig>) {
+        self.auto_reconnect = auto_reconnect;
+    }
+
+    /// The configuration of the auto-reconnect mode.
+    ///
+    /// Returns `None` if auto-reconnect is disabled.
+    pub fn auto_reconnect(&self) -> Option<&AutoReconnectConfig> {
+        self.auto_reconnect.as_ref()
+    }
+
+    /// Activate the context and start the audio stream.
+    ///
+    /// Returns an error if the context is already active.
+    #[allow(clippy::type_complexity)]
+    pub fn activate(
+        &mut self,
+        config: B::Config,
+        user_cx: Option<Box<dyn Any + Send>>,
+    ) -> Result<
+        (),
+        (
+            ActivateError<B::StartStreamError>,
+            Option<Box<dyn Any + Send>>,
+        ),
+    > {
+        if self.is_activated() {
+            return Err((ActivateError::AlreadyActivated, user_cx));
+        }
+
+        match self.start_stream(config.clone(), user_cx.unwrap_or(Box::new(()))) {
+            Ok(()) => {
+                self.config = Some(config);
+                Ok(())
+            }
+            Err((e, user_cx)) => Err((ActivateError::BackendError(e), Some(user_cx))),
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn start_stream(
+        &mut self,
+        config: B::Config,
+        user_cx: Box<dyn Any + Send>,
+    ) -> Result<(), (B::StartStreamError, Box<dyn Any + Send>)> {
+        let (mut backend, stream_info) = match B::start_stream(config) {
+            Ok(b) => b,
+            Err(e) => return Err((e, user_cx)),
+        };
+
+        let processor = self
+            .cx
+            .activate(
+                stream_info.sample_rate,
+                stream_info.num_stream_in_channels,
+                stream_info.num_stream_out_channels,
+                stream_info.max_block_frames,
+                user_cx,
+            )
+            .unwrap();
+
+        backend.set_processor(processor);
+
+        self.active_state = Some(ActiveBackendState {
+            backend,
+            stream_info,
+        });
+        self.last_default_device_poll = Instant::now();
+
+        Ok(())
+    }
+
+    /// Returns whether or not this context is currently activated.
+    ///
+    /// This also returns `true` while the context is trying to reconnect.
+    pub fn is_activated(&self) -> bool {
+        self.cx.is_activated() || self.reconnect_state.is_some()
+    }
+
+    /// The number of frames that have been processed since the audio
+    /// stream was started.
+    ///
+    /// See [`FirewheelGraphCtx::clock_frames`].
+    pub fn clock_frames(&self) -> u64 {
+        self.cx.clock_frames()
+    }
+
+    /// Statistics on the time spent processing the audio graph.
+    ///
+    /// See [`FirewheelGraphCtx::profile`].
+    #[cfg(feature = "profiling")]
+    pub fn profile(&self) -> &DspProfile {
+        self.cx.profile()
+    }
+
+    /// Get information about the running audio stream.
+    ///
+    /// Returns `None` if the context is not currently activated.
+    pub fn stream_info(&self) -> Option<&StreamInfo> {
+        self.active_state.as_ref().map(|s| &s.stream_info)
+    }
+
+    /// Get the running audio backend.
+    ///
+    /// Returns `None` if the context is not currently activated.
+    pub fn active_backend(&self) -> Option<&B> {
+        self.active_state.as_ref().map(|s| &s.backend)
+    }
+
+    /// Get the running audio backend.
+    ///
+    /// Returns `None` if the context is not currently activated.
+    pub fn active_backend_mut(&mut self) -> Option<&mut B> {
+        self.active_state.as_mut().map(|s| &mut s.backend)
+    }
+
+    /// Update the firewheel context.
+    ///
+    /// This must be called reguarly once the context has been activated
+    /// (i.e. once every frame).
+    pub fn update(&mut self) -> UpdateStatus {
+        if let Some(reconnect_state) = &self.reconnect_state {
+            let retry_interval = self
+                .auto_reconnect
+                .map(|c| c.retry_interval)
+                .unwrap_or_default();
+
+            if reconnect_state
+                .last_attempt
+                .map(|t| t.elapsed() >= retry_interval)
+                .unwrap_or(true)
+            {
+                return self.reconnect(None);
+            } else {
+                return UpdateStatus::Reconnecting;
+            }
+        }
+
+        let mut stream_error: Option<Box<dyn Error>> = None;
+        let mut default_device_changed = false;
+
+        if let Some(state) = &mut self.active_state {
+            if let Err(e) = state.backend.poll_for_errors() {
+                stream_error = Some(Box::new(e));
+            } else if let Some(poll_interval) = self
+                .auto_reconnect
+                .and_then(|c| c.default_device_poll_interval)
+            {
+                if self.last_default_device_poll.elapsed() >= poll_interval {
+                    self.last_default_device_poll = Instant::now();
+                    default_device_changed = state.backend.default_device_changed();
+                }
+            }
+        }
+
+        if let Some(e) = stream_error {
+            // Stopping the stream first drops the processor, so the
+            // context doesn't need to wait for a timeout.
+            self.stop_backend();
+            let user_cx = self.cx.deactivate(false);
+
+            if self.auto_reconnect.is_some() {
+                if let Some(user_cx) = user_cx {
+                    self.reconnect_state = Some(ReconnectState {
+                        user_cx,
+                        last_attempt: None,
+                    });
+
+                    return self.reconnect(Some(e));
+                }
+
+                log::error!(
+                    "Audio stream stopped unexpectedly: {}. Could not reconnect because the user context was not returned",
+                    e
+                );
+            }
+
+            self.config = None;
+
+            return UpdateStatus::Deactivated {
+                error: Some(e),
+                returned_user_cx: user_cx,
+            };
+        }
+
+        if default_device_changed {
+            log::info!("Default audio device changed. Restarting audio stream...");
+
+            let Some(user_cx) = self.deactivate_internal() else {
+                log::error!(
+                    "Could not restart audio stream because the user context was not returned"
+                );
+
+                self.config = None;
+
+                return UpdateStatus::Deactivated {
+                    error: None,
+                    returned_user_cx: None,
+                };
+            };
+
+            self.reconnect_state = Some(ReconnectState {
+                user_cx,
+                last_attempt: None,
+            });
+
+            return self.reconnect(None);
+        }
+
+        let status = self.cx.update();
+
+        if let UpdateStatus::Deactivated { .. } = &status {
+            self.stop_backend();
+        }
+
+        status
+    }
+
+    fn reconnect(&mut self, stream_error: Option<Box<dyn Error>>) -> UpdateStatus {
+        let Some(reconnect_state) = self.reconnect_state.take() else {
+            return UpdateStatus::Inactive;
+        };
+
+        if let Some(e) = &stream_error {
+            log::error!("Audio stream stopped unexpectedly: {}. Reconnecting...", e);
+        }
+
+        let config = B::reconnect_config(self.config.as_ref().unwrap());
+
+        match self.start_stream(config, reconnect_state.user_cx) {
+            Ok(()) => UpdateStatus::Reconnected { stream_error },
+            Err((e, user_cx)) => {
+                log::error!("Failed to reconnect audio stream: {}", e);
+
+                self.reconnect_state = Some(ReconnectState {
+                    user_cx,
+                    last_attempt: Some(Instant::now()),
+                });
+
+                UpdateStatus::ReconnectFailed { error: Box::new(e) }
+            }
+        }
+    }
+
+    /// Deactivate the firewheel context and stop the audio stream.
+    ///
+    /// This will block the thread until either the processor has
+    /// been successfully dropped or a timeout has been reached.
+    ///
+    /// If the stream is still currently running, then the context
+    /// will attempt to cleanly deactivate the processor. If not,
+    /// then the context will wait for either the processor to be
+    /// dropped or a timeout being reached.
+    ///
+    /// If the context is already deactivated, then this will do
+    /// nothing and return `None`.
+    pub fn deactivate(&mut self) -> Option<Box<dyn Any + Send>> {
+        if let Some(reconnect_state) = self.reconnect_state.take() {
+            self.config = None;
+            return Some(reconnect_state.user_cx);
+        }
+
+        if self.cx.is_activated() {
+            self.config = None;
+            self.deactivate_internal()
+        } else {
+            None
+        }
+    }
+
+    fn deactivate_internal(&mut self) -> Option<Box<dyn Any + Send>> {
+        let stream_is_running = self
+            .active_state
+            .as_ref()
+            .map(|s| s.backend.is_running())
+            .unwrap_or(false);
+
+        if stream_is_running {
+            let user_cx = self.cx.deactivate(true);
+            self.stop_backend();
+            user_cx
+        } else {
+            // Stopping the stream drops the processor if it is still
+            // owned by the backend.
+            self.stop_backend();
+            self.cx.deactivate(false)
+        }
+    }
+
+    fn stop_backend(&mut self) {
+        if let Some(mut state) = self.active_state.take() {
+            state.backend.stop_stream();
+        }
+    }
+}
+
+// Implement Debug so `unwrap()` can be used.
+impl<B: AudioBackend> fmt::Debug for FirewheelCtx<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FirewheelCtx")
+    }
+}
+
+impl<B: AudioBackend> Drop for FirewheelCtx<B> {
+    fn drop(&mut self) {
+        self.deactivate();
+    }
+}
+
+/// An error occured while trying to activate a [`FirewheelCtx`]
+#[derive(Debug)]
+pub enum ActivateError<E: Error> {
+    /// The context is already activated.
+    AlreadyActivated,
+    /// The backend failed to start the audio stream.
+    BackendError(E),
+}
+
+impl<E: Error + 'static> Error for ActivateError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::AlreadyActivated => None,
+            Self::BackendError(e) => Some(e),
+        }
+    }
+}
+
+impl<E: Error> fmt::Display for ActivateError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyActivated => write!(f, "The firewheel context is already activated"),
+            Self::BackendError(e) => write!(f, "Failed to start audio stream: {}", e),
+        }
+    }
+}
+
+/// A [`FirewheelGraphCtx`] along with its processor for use in tests.
+///
+/// The context is activated at 48 kHz with a maximum block size of 64
+/// frames.
+#[cfg(test)]
+pub(crate) struct TestGraphCtx {
+    // The processor is dropped before the context so that the context
+    // doesn't have to wait for a timeout.
+    pub processor: FirewheelProcessor,
+    pub cx: FirewheelGraphCtx,
+    num_in_channels: usize,
+    num_out_channels: usize,
+}
+
+#[cfg(test)]
+impl TestGraphCtx {
+    /// Build the graph with `build_graph` and then activate the context.
+    pub fn new(graph_config: AudioGraphConfig, build_graph: impl FnOnce(&mut AudioGraph)) -> Self {
+        let mut cx = FirewheelGraphCtx::new(graph_config);
+        build_graph(&mut cx.graph);
+
+        let num_in_channels = graph_config.num_graph_inputs;
+        let num_out_channels = graph_config.num_graph_outputs;
+
+        let processor = cx
+            .activate(48_000, num_in_channels, num_out_channels, 64, Box::new(()))
+            .unwrap();
+        assert!(matches!(
+            cx.update(),
+            UpdateStatus::Active {
+                graph_error: None,
+                ..
+            }
+        ));
+
+        Self {
+            processor,
+            cx,
+            num_in_channels,
+            num_out_channels,
+        }
+    }
+
+    /// Activate a context with a single node which is connected in between
+    /// the graph input and the graph output.
+    pub fn with_node(
+        graph_config: AudioGraphConfig,
+        num_inputs: usize,
+        num_outputs: usize,
+        node: impl Into<Box<dyn firewheel_core::node::AudioNode>>,
+    ) -> (Self, crate::graph::NodeID) {
+        let mut node_id = None;
+
+        let test_cx = Self::new(graph_config, |graph| {
+            let graph_in = graph.graph_in_node();
+            let graph_out = graph.graph_out_node();
+            let node = graph.add_node(num_inputs, num_outputs, node);
+
+            for port in 0..num_inputs.min(graph_config.num_graph_inputs) {
+                graph.connect(graph_in, port, node, port, false).unwrap();
+            }
+            for port in 0..num_outputs.min(graph_config.num_graph_outputs) {
+                graph.connect(node, port, graph_out, port, false).unwrap();
+            }
+
+            node_id = Some(node);
+        });
+
+        (test_cx, node_id.unwrap())
+    }
+
+    /// Process the given interleaved input into the given interleaved
+    /// output.
+    ///
+    /// The number of frames is the length of the output divided by the
+    /// number of graph outputs.
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) {
+        let frames = output.len() / self.num_out_channels;
+
+        self.processor.process_interleaved(
+            input,
+            output,
+            self.num_in_channels,
+            self.num_out_channels,
+            frames,
+            0.0,
+            firewheel_core::node::StreamStatus::empty(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct TestStreamError;
+
+    impl Error for TestStreamError {}
+
+    impl fmt::Display for TestStreamError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "test stream error")
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct TestConfig {
+        fail_stream: bool,
+        fail_start: bool,
+        fail_reconnect: bool,
+    }
+
+    struct TestBackend {
+        processor: Option<FirewheelProcessor>,
+        fail: bool,
+    }
+
+    impl AudioBackend for TestBackend {
+        type Config = TestConfig;
+        type StartStreamError = TestStreamError;
+        type StreamError = TestStreamError;
+
+        fn available_input_devices() -> Vec<DeviceInfo> {
+            Vec::new()
+        }
+
+        fn available_output_devices() -> Vec<DeviceInfo> {
+            Vec::new()
+        }
+
+        fn start_stream(config: TestConfig) -> Result<(Self, StreamInfo), TestStreamError> {
+            if config.fail_start {
+                return Err(TestStreamError);
+            }
+
+            Ok((
+                Self {
+                    processor: None,
+                    fail: config.fail_stream,
+                },
+                StreamInfo {
+                    sample_rate: 44100,
+                    max_block_frames: 256,
+                    num_stream_in_channels: 0,
+                    num_stream_out_channels: 2,
+                    input_device_name: None,
+                    output_device_name: None,
+                },
+            ))
+        }
+
+        fn set_processor(&mut self, processor: FirewheelProcessor) {
+            self.processor = Some(processor);
+        }
+
+        fn stop_stream(&mut self) {
+            self.processor = None;
+        }
+
+        fn poll_for_errors(&mut self) -> Result<(), TestStreamError> {
+            if self.fail {
+                Err(TestStreamError)
+            } else {
+                Ok(())
+            }
+        }
+
+        fn is_running(&self) -> bool {
+            false
+        }
+
+        fn reconnect_config(config: &TestConfig) -> TestConfig {
+            TestConfig {
+                fail_start: config.fail_reconnect,
+                ..config.clone()
+            }
+        }
+    }
+
+    #[test]
+    fn backend_lifecycle() {
+        let mut cx = FirewheelCtx::<TestBackend>::new(AudioGraphConfig::default());
+
+        cx.activate(TestConfig::default(), None).unwrap();
+        assert!(cx.is_activated());
+        assert_eq!(cx.stream_info().unwrap().sample_rate, 44100);
+        assert!(cx.active_backend().unwrap().processor.is_some());
+        assert!(matches!(
+            cx.activate(TestConfig::default(), None),
+            Err((ActivateError::AlreadyActivated, None))
+        ));
+        assert!(matches!(
+            cx.update(),
+            UpdateStatus::Active {
+                graph_error: None,
+                ..
+            }
+        ));
+
+        let user_cx = cx.deactivate();
+        assert!(user_cx.unwrap().downcast::<()>().is_ok());
+        assert!(!cx.is_activated());
+        assert!(cx.active_backend().is_none());
+    }
+
+    #[test]
+    fn stream_error_deactivates() {
+        let mut cx = FirewheelCtx::<TestBackend>::new(AudioGraphConfig::default());
+
+        cx.activate(
+            TestConfig {
+                fail_stream: true,
+                ..Default::default()
+            },
+            Some(Box::new(5u32)),
+        )
+        .unwrap();
+
+        let UpdateStatus::Deactivated {
+            error,
+            returned_user_cx,
+        } = cx.update()
+        else {
+            panic!("expected the context to be deactivated");
+        };
+
+        assert!(error.unwrap().is::<TestStreamError>());
+        assert_eq!(*returned_user_cx.unwrap().downcast::<u32>().unwrap(), 5);
+        assert!(!cx.is_activated());
+        assert!(matches!(cx.update(), UpdateStatus::Inactive));
+    }
+
+    #[test]
+    fn auto_reconnect_preserves_graph() {
+        let mut cx = FirewheelCtx::<TestBackend>::new(AudioGraphConfig::default());
+        cx.set_auto_reconnect(Some(AutoReconnectConfig::default()));
+
+        let graph = cx.graph_mut();
+        let node = graph.add_node(
+            0,
+            2,
+            crate::basic_nodes::beep_test::BeepTestNode::new(440.0, -12.0, true),
+        );
+        graph
+            .connect(node, 0, graph.graph_out_node(), 0, false)
+            .unwrap();
+
+        cx.activate(
+            TestConfig {
+                fail_stream: true,
+                ..Default::default()
+            },
+            Some(Box::new(5u32)),
+        )
+        .unwrap();
+
+        let UpdateStatus::Reconnected { stream_error } = cx.update() else {
+            panic!("expected the context to reconnect");
+        };
+        assert!(stream_error.unwrap().is::<TestStreamError>());
+        assert!(cx.is_activated());
+        assert!(cx.active_backend().unwrap().processor.is_some());
+        assert_eq!(cx.graph().nodes().count(), 3);
+        assert_eq!(cx.graph().edges().count(), 1);
+
+        let user_cx = cx.deactivate();
+        assert_eq!(*user_cx.unwrap().downcast::<u32>().unwrap(), 5);
+    }
+
+    #[test]
+    fn auto_reconnect_retries() {
+        let mut cx = FirewheelCtx::<TestBackend>::new(AudioGraphConfig::default());
+        cx.set_auto_reconnect(Some(AutoReconnectConfig {
+            retry_interval: Duration::from_secs(1000),
+            ..Default::default()
+        }));
+
+        cx.activate(
+            TestConfig {
+                fail_stream: true,
+                fail_reconnect: true,
+                ..Default::default()
+            },
+            Some(Box::new(5u32)),
+        )
+        .unwrap();
+
+        assert!(matches!(cx.update(), UpdateStatus::ReconnectFailed { .. }));
+        assert!(cx.is_activated());
+        assert!(cx.stream_info().is_none());
+
+        // Wait for the retry interval before trying again.
+        assert!(matches!(cx.update(), UpdateStatus::Reconnecting));
+        assert!(cx.is_activated());
+
+        let user_cx = cx.deactivate();
+        assert_eq!(*user_cx.unwrap().downcast::<u32>().unwrap(), 5);
+        assert!(!cx.is_activated());
+    }
+}
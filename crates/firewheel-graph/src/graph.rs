@@ -1,5 +1,7 @@
 mod compiler;
+mod description;
 mod error;
+mod export;
 
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -7,13 +9,23 @@ use std::hash::Hash;
 use ahash::{AHashMap, AHashSet};
 use thunderdome::Arena;
 
-use crate::basic_nodes::DummyAudioNode;
-use firewheel_core::node::{AudioNode, AudioNodeProcessor};
+use crate::{basic_nodes::DummyAudioNode, processor::ProcessorNode};
+use firewheel_core::{
+    event::{EventTime, NodeEventType},
+    node::AudioNode,
+    param::info::{ParamID, ParamInfo},
+};
 
 pub(crate) use self::compiler::{CompiledSchedule, ScheduleHeapData};
 
 pub use self::compiler::{Edge, EdgeID, InPortIdx, NodeEntry, OutPortIdx};
-pub use self::error::{AddEdgeError, CompileGraphError};
+pub use self::description::{
+    EdgeDescription, GraphDescription, NodeDescription, NodeRef, NodeRegistry, ParamValue,
+};
+pub use self::error::{
+    AddEdgeError, CompileGraphError, LoadGraphError, ParamError, SaveGraphError,
+};
+pub use self::export::{InBufferReport, OutBufferReport, ScheduleReport, ScheduledNodeReport};
 
 /// A globally unique identifier for a node.
 #[derive(Clone, Copy)]
@@ -77,6 +89,8 @@ pub struct NodeWeight {
     pub node: Box<dyn AudioNode>,
     pub activated: bool,
     pub updates: bool,
+    pub split_at_events: bool,
+    pub silent_when_inputs_silent: bool,
 }
 
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
@@ -93,6 +107,15 @@ pub struct AudioGraphConfig {
     pub num_graph_outputs: usize,
     pub initial_node_capacity: usize,
     pub initial_edge_capacity: usize,
+    /// The maximum number of scheduled events that can be sent to the
+    /// audio thread in a single update.
+    ///
+    /// This is also the maximum number of [`NodeEventType::Custom`] events
+    /// which can be queued in the audio thread at once.
+    pub event_queue_capacity: usize,
+    /// The maximum number of scheduled events that can be queued for a
+    /// single node in the audio thread.
+    pub node_event_capacity: usize,
 }
 
 impl Default for AudioGraphConfig {
@@ -102,6 +125,8 @@ impl Default for AudioGraphConfig {
             num_graph_outputs: 2,
             initial_node_capacity: 64,
             initial_edge_capacity: 256,
+            event_queue_capacity: 512,
+            node_event_capacity: 128,
         }
     }
 }
@@ -119,6 +144,9 @@ pub struct AudioGraph {
     nodes_to_remove_from_schedule: Vec<NodeID>,
     nodes_to_activate: Vec<NodeID>,
     active_nodes_to_remove: AHashMap<NodeID, NodeEntry<NodeWeight>>,
+
+    event_queue: Vec<(NodeID, EventTime, NodeEventType)>,
+    node_event_capacity: usize,
 }
 
 impl AudioGraph {
@@ -133,6 +161,8 @@ impl AudioGraph {
                     node: Box::new(DummyAudioNode),
                     activated: false,
                     updates: false,
+                    split_at_events: false,
+                    silent_when_inputs_silent: false,
                 },
             )),
             debug_name: "graph_in",
@@ -147,6 +177,8 @@ impl AudioGraph {
                     node: Box::new(DummyAudioNode),
                     activated: false,
                     updates: false,
+                    split_at_events: false,
+                    silent_when_inputs_silent: false,
                 },
             )),
             debug_name: "graph_out",
@@ -164,6 +196,8 @@ impl AudioGraph {
             nodes_to_remove_from_schedule: Vec::new(),
             nodes_to_activate: vec![graph_in_id, graph_out_id],
             active_nodes_to_remove: AHashMap::with_capacity(config.initial_edge_capacity),
+            event_queue: Vec::with_capacity(config.event_queue_capacity),
+            node_event_capacity: config.node_event_capacity,
         }
     }
 
@@ -217,6 +251,8 @@ impl AudioGraph {
                     node,
                     activated: false,
                     updates: info.updates,
+                    split_at_events: info.split_at_events,
+                    silent_when_inputs_silent: info.silent_when_inputs_silent,
                 },
             )),
             debug_name,
@@ -254,6 +290,85 @@ impl AudioGraph {
         self.nodes.get(node_id.idx)
     }
 
+    /// Get a description of the parameters on a node.
+    ///
+    /// This will return `None` if a node with the given ID does not
+    /// exist in the graph.
+    pub fn node_params(&self, node_id: NodeID) -> Option<Vec<ParamInfo>> {
+        self.nodes.get(node_id.idx).map(|n| n.weight.node.params())
+    }
+
+    /// Set the value of a parameter on a node.
+    ///
+    /// The value is clamped to the range of the parameter.
+    pub fn set_param(
+        &mut self,
+        node_id: NodeID,
+        param_id: ParamID,
+        val: f32,
+    ) -> Result<(), ParamError> {
+        let node_entry = self
+            .nodes
+            .get_mut(node_id.idx)
+            .ok_or(ParamError::NodeNotFound(node_id))?;
+
+        let info = node_entry
+            .weight
+            .node
+            .params()
+            .into_iter()
+            .find(|p| p.id == param_id)
+            .ok_or(ParamError::ParamNotFound(node_id, param_id))?;
+
+        node_entry
+            .weight
+            .node
+            .set_param(param_id, info.range.clamp(val));
+
+        Ok(())
+    }
+
+    /// Get the current value of a parameter on a node.
+    pub fn get_param(&self, node_id: NodeID, param_id: ParamID) -> Result<f32, ParamError> {
+        let node_entry = self
+            .nodes
+            .get(node_id.idx)
+            .ok_or(ParamError::NodeNotFound(node_id))?;
+
+        node_entry
+            .weight
+            .node
+            .get_param(param_id)
+            .ok_or(ParamError::ParamNotFound(node_id, param_id))
+    }
+
+    /// Schedule an event to be sent to the processor of the given node.
+    ///
+    /// The event is sent to the audio thread the next time the context
+    /// is updated. Events which are scheduled for a time that has already
+    /// passed occur at the start of the next process cycle.
+    ///
+    /// This will return an error if a node with the given ID does not
+    /// exist in the graph.
+    pub fn queue_event(
+        &mut self,
+        node_id: NodeID,
+        time: EventTime,
+        event: NodeEventType,
+    ) -> Result<(), ()> {
+        if !self.nodes.contains(node_id.idx) {
+            return Err(());
+        }
+
+        self.event_queue.push((node_id, time, event));
+
+        Ok(())
+    }
+
+    pub(crate) fn event_queue_mut(&mut self) -> &mut Vec<(NodeID, EventTime, NodeEventType)> {
+        &mut self.event_queue
+    }
+
     /// Remove the given node from the graph.
     ///
     /// This will automatically remove all edges from the graph that
@@ -308,6 +423,171 @@ impl AudioGraph {
         self.edges.iter().map(|(_, e)| e)
     }
 
+    /// Export the topology of the graph (along with the values of the
+    /// parameters on each node) to a serializable description.
+    ///
+    /// The type of every node in the graph must be registered in the
+    /// given registry.
+    pub fn to_description(
+        &self,
+        registry: &NodeRegistry,
+    ) -> Result<GraphDescription, SaveGraphError> {
+        let mut node_indices: AHashMap<NodeID, u32> = AHashMap::new();
+        let mut nodes = Vec::new();
+
+        for (_, node_entry) in self.nodes.iter() {
+            if node_entry.id == self.graph_in_id || node_entry.id == self.graph_out_id {
+                continue;
+            }
+
+            let node = node_entry.weight.node.as_ref();
+
+            let type_key = registry
+                .type_key(node)
+                .ok_or(SaveGraphError::UnregisteredNodeType(node_entry.id))?;
+
+            let params = node
+                .params()
+                .iter()
+                .filter_map(|info| {
+                    node.get_param(info.id).map(|value| ParamValue {
+                        id: info.id.0,
+                        value,
+                    })
+                })
+                .collect();
+
+            node_indices.insert(node_entry.id, nodes.len() as u32);
+            nodes.push(NodeDescription {
+                type_key: type_key.into(),
+                num_inputs: node_entry.num_inputs,
+                num_outputs: node_entry.num_outputs,
+                params,
+            });
+        }
+
+        let node_ref = |node_id: NodeID| -> NodeRef {
+            if node_id == self.graph_in_id {
+                NodeRef::GraphIn
+            } else if node_id == self.graph_out_id {
+                NodeRef::GraphOut
+            } else {
+                NodeRef::Node(node_indices[&node_id])
+            }
+        };
+
+        let edges = self
+            .edges
+            .iter()
+            .map(|(_, edge)| EdgeDescription {
+                src_node: node_ref(edge.src_node),
+                src_port: edge.src_port.0,
+                dst_node: node_ref(edge.dst_node),
+                dst_port: edge.dst_port.0,
+            })
+            .collect();
+
+        Ok(GraphDescription { nodes, edges })
+    }
+
+    /// Replace all existing nodes in the graph with the nodes and edges
+    /// in the given description.
+    ///
+    /// Each node is constructed using the given registry. If successful,
+    /// this returns the IDs of the new nodes, in the same order as
+    /// [`GraphDescription::nodes`].
+    ///
+    /// If this returns an error, then the graph will be empty.
+    pub fn load_description(
+        &mut self,
+        description: &GraphDescription,
+        registry: &NodeRegistry,
+    ) -> Result<Vec<NodeID>, LoadGraphError> {
+        // Make sure that every node type is registered and supports the
+        // given number of ports before modifying the graph.
+        let mut new_nodes = Vec::with_capacity(description.nodes.len());
+        for node_desc in description.nodes.iter() {
+            let node = registry
+                .construct(&node_desc.type_key)
+                .ok_or_else(|| LoadGraphError::UnknownNodeType(node_desc.type_key.clone()))?;
+
+            let info = node.info();
+            if !(info.num_min_supported_inputs..=info.num_max_supported_inputs.min(64))
+                .contains(&node_desc.num_inputs)
+                || !(info.num_min_supported_outputs..=info.num_max_supported_outputs.min(64))
+                    .contains(&node_desc.num_outputs)
+            {
+                return Err(LoadGraphError::InvalidPortCount {
+                    type_key: node_desc.type_key.clone(),
+                    num_inputs: node_desc.num_inputs,
+                    num_outputs: node_desc.num_outputs,
+                });
+            }
+
+            new_nodes.push(node);
+        }
+
+        self.reset();
+
+        let res = self.load_description_inner(description, new_nodes);
+        if res.is_err() {
+            self.reset();
+        }
+
+        res
+    }
+
+    fn load_description_inner(
+        &mut self,
+        description: &GraphDescription,
+        new_nodes: Vec<Box<dyn AudioNode>>,
+    ) -> Result<Vec<NodeID>, LoadGraphError> {
+        let mut node_ids = Vec::with_capacity(new_nodes.len());
+
+        for (node, node_desc) in new_nodes.into_iter().zip(description.nodes.iter()) {
+            let node_id = self.add_node(
+                node_desc.num_inputs as usize,
+                node_desc.num_outputs as usize,
+                node,
+            );
+
+            for param in node_desc.params.iter() {
+                self.set_param(node_id, ParamID(param.id), param.value)?;
+            }
+
+            node_ids.push(node_id);
+        }
+
+        let node_id = |node_ref: NodeRef| -> Result<NodeID, LoadGraphError> {
+            match node_ref {
+                NodeRef::GraphIn => Ok(self.graph_in_id),
+                NodeRef::GraphOut => Ok(self.graph_out_id),
+                NodeRef::Node(idx) => node_ids
+                    .get(idx as usize)
+                    .copied()
+                    .ok_or(LoadGraphError::NodeNotFound(idx)),
+            }
+        };
+
+        let edges = description
+            .edges
+            .iter()
+            .map(|edge| Ok((node_id(edge.src_node)?, edge, node_id(edge.dst_node)?)))
+            .collect::<Result<Vec<_>, LoadGraphError>>()?;
+
+        for (src_node, edge, dst_node) in edges {
+            self.connect(
+                src_node,
+                OutPortIdx(edge.src_port),
+                dst_node,
+                InPortIdx(edge.dst_port),
+                true,
+            )?;
+        }
+
+        Ok(node_ids)
+    }
+
     /// Set the number of input ports for a particular node in the graph.
     ///
     /// This will return an error if a node with the given ID does not
@@ -599,10 +879,22 @@ impl AudioGraph {
                     node_entry.num_inputs as usize,
                     node_entry.num_outputs as usize,
                 ) {
-                    Ok(processor) => new_node_processors.push((*node_id, processor)),
+                    Ok(processor) => new_node_processors.push((
+                        *node_id,
+                        ProcessorNode {
+                            processor,
+                            events: Vec::with_capacity(self.node_event_capacity),
+                            split_at_events: node_entry.weight.split_at_events,
+                            #[cfg(feature = "profiling")]
+                            timing: Default::default(),
+                        },
+                    )),
                     Err(e) => {
-                        for (n_id, processor) in new_node_processors.drain(..) {
-                            self.nodes[n_id.idx].weight.node.deactivate(Some(processor));
+                        for (n_id, node) in new_node_processors.drain(..) {
+                            self.nodes[n_id.idx]
+                                .weight
+                                .node
+                                .deactivate(Some(node.processor));
                         }
 
                         return Err(CompileGraphError::NodeActivationFailed(*node_id, e));
@@ -611,6 +903,10 @@ impl AudioGraph {
             }
         }
 
+        for (node_id, _) in new_node_processors.iter() {
+            self.nodes[node_id.idx].weight.activated = true;
+        }
+
         let schedule_data = ScheduleHeapData::new(
             schedule,
             self.nodes_to_remove_from_schedule.clone(),
@@ -638,17 +934,18 @@ impl AudioGraph {
             self.graph_in_id,
             self.graph_out_id,
             max_block_frames,
+            |weight| weight.silent_when_inputs_silent,
         )
     }
 
     pub(crate) fn on_schedule_returned(&mut self, mut schedule_data: Box<ScheduleHeapData>) {
-        for (node_id, processor) in schedule_data.removed_node_processors.drain(..) {
+        for (node_id, node) in schedule_data.removed_node_processors.drain(..) {
             if let Some(mut node_entry) = self.active_nodes_to_remove.remove(&node_id) {
-                node_entry.weight.node.deactivate(Some(processor));
+                node_entry.weight.node.deactivate(Some(node.processor));
                 node_entry.weight.activated = false;
             } else if let Some(node_entry) = self.nodes.get_mut(node_id.idx) {
                 if node_entry.weight.activated {
-                    node_entry.weight.node.deactivate(Some(processor));
+                    node_entry.weight.node.deactivate(Some(node.processor));
                     node_entry.weight.activated = false;
 
                     self.nodes_to_activate.push(node_id);
@@ -657,11 +954,11 @@ impl AudioGraph {
         }
     }
 
-    pub(crate) fn on_processor_dropped(&mut self, mut nodes: Arena<Box<dyn AudioNodeProcessor>>) {
-        for (node_id, processor) in nodes.drain() {
+    pub(crate) fn on_processor_dropped(&mut self, mut nodes: Arena<ProcessorNode>) {
+        for (node_id, node) in nodes.drain() {
             if let Some(node_entry) = self.nodes.get_mut(node_id) {
                 if node_entry.weight.activated {
-                    node_entry.weight.node.deactivate(Some(processor));
+                    node_entry.weight.node.deactivate(Some(node.processor));
                     node_entry.weight.activated = false;
                 }
             }
@@ -671,6 +968,8 @@ impl AudioGraph {
     pub(crate) fn deactivate(&mut self) {
         self.active_nodes_to_remove.clear();
         self.nodes_to_remove_from_schedule.clear();
+        self.nodes_to_activate.clear();
+        self.event_queue.clear();
         self.needs_compile = true;
 
         for (node_id, node_entry) in self.nodes.iter_mut() {
@@ -696,3 +995,149 @@ impl AudioGraph {
         }
     }
 }
+
+/// Save the given node to a [`GraphDescription`] and load it back into a
+/// new graph using [`NodeRegistry::with_basic_nodes`].
+#[cfg(test)]
+pub(crate) fn reload_node(
+    num_inputs: usize,
+    num_outputs: usize,
+    node: impl Into<Box<dyn AudioNode>>,
+) -> (AudioGraph, NodeID) {
+    let registry = NodeRegistry::with_basic_nodes();
+
+    let mut graph = AudioGraph::new(&AudioGraphConfig::default());
+    graph.add_node(num_inputs, num_outputs, node);
+    let description = graph.to_description(&registry).unwrap();
+
+    let mut new_graph = AudioGraph::new(&AudioGraphConfig::default());
+    let node_ids = new_graph.load_description(&description, &registry).unwrap();
+
+    (new_graph, node_ids[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic_nodes::{beep_test::BeepTestNode, MonoToStereoNode, VolumeNode};
+
+    #[test]
+    fn set_and_get_param() {
+        let mut graph = AudioGraph::new(&AudioGraphConfig::default());
+        let node_id = graph.add_node(2, 2, VolumeNode::new(100.0));
+
+        let params = graph.node_params(node_id).unwrap();
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].id, VolumeNode::PARAM_VOLUME);
+
+        graph
+            .set_param(node_id, VolumeNode::PARAM_VOLUME, 50.0)
+            .unwrap();
+        assert_eq!(graph.get_param(node_id, VolumeNode::PARAM_VOLUME), Ok(50.0));
+
+        // The value is clamped to the range of the parameter.
+        graph
+            .set_param(node_id, VolumeNode::PARAM_VOLUME, -10.0)
+            .unwrap();
+        assert_eq!(graph.get_param(node_id, VolumeNode::PARAM_VOLUME), Ok(0.0));
+
+        assert_eq!(
+            graph.set_param(node_id, ParamID(5), 1.0),
+            Err(ParamError::ParamNotFound(node_id, ParamID(5)))
+        );
+
+        graph.remove_node(node_id).unwrap();
+        assert_eq!(
+            graph.get_param(node_id, VolumeNode::PARAM_VOLUME),
+            Err(ParamError::NodeNotFound(node_id))
+        );
+    }
+
+    #[test]
+    fn description_roundtrip() {
+        let registry = NodeRegistry::with_basic_nodes();
+
+        let mut graph = AudioGraph::new(&AudioGraphConfig::default());
+        let graph_out = graph.graph_out_node();
+        let beep = graph.add_node(0, 1, BeepTestNode::new(440.0, -12.0, true));
+        let stereo = graph.add_node(1, 2, MonoToStereoNode);
+        let volume = graph.add_node(2, 2, VolumeNode::new(100.0));
+        graph
+            .set_param(volume, VolumeNode::PARAM_VOLUME, 50.0)
+            .unwrap();
+        graph.connect(beep, 0, stereo, 0, true).unwrap();
+        graph.connect(stereo, 0, volume, 0, true).unwrap();
+        graph.connect(stereo, 1, volume, 1, true).unwrap();
+        graph.connect(volume, 0, graph_out, 0, true).unwrap();
+        graph.connect(volume, 1, graph_out, 1, true).unwrap();
+
+        let description = graph.to_description(&registry).unwrap();
+        assert_eq!(description.nodes.len(), 3);
+        assert_eq!(description.edges.len(), 5);
+
+        #[cfg(feature = "serde")]
+        let description: GraphDescription =
+            serde_json::from_str(&serde_json::to_string(&description).unwrap()).unwrap();
+
+        let mut new_graph = AudioGraph::new(&AudioGraphConfig::default());
+        let node_ids = new_graph.load_description(&description, &registry).unwrap();
+        assert_eq!(node_ids.len(), 3);
+        assert_eq!(new_graph.edges().count(), 5);
+        assert_eq!(new_graph.to_description(&registry).unwrap(), description);
+
+        let volume = node_ids
+            .iter()
+            .copied()
+            .find(|&id| new_graph.node(id).unwrap().is::<VolumeNode>())
+            .unwrap();
+        assert_eq!(
+            new_graph.get_param(volume, VolumeNode::PARAM_VOLUME),
+            Ok(50.0)
+        );
+
+        let mut bad_description = description.clone();
+        bad_description.nodes[0].type_key = "foo".into();
+        assert!(matches!(
+            new_graph.load_description(&bad_description, &registry),
+            Err(LoadGraphError::UnknownNodeType(key)) if key == "foo"
+        ));
+
+        let mut bad_description = description.clone();
+        bad_description.edges[0].dst_port = 10;
+        assert!(matches!(
+            new_graph.load_description(&bad_description, &registry),
+            Err(LoadGraphError::EdgeError(
+                AddEdgeError::InPortOutOfRange { .. }
+            ))
+        ));
+        assert_eq!(new_graph.nodes().count(), 2);
+    }
+
+    #[test]
+    fn load_description_checks_port_counts() {
+        let registry = NodeRegistry::with_basic_nodes();
+
+        let mut graph = AudioGraph::new(&AudioGraphConfig::default());
+        graph.add_node(1, 2, MonoToStereoNode);
+        graph.add_node(2, 2, VolumeNode::new(100.0));
+        let description = graph.to_description(&registry).unwrap();
+
+        let mut new_graph = AudioGraph::new(&AudioGraphConfig::default());
+        new_graph.add_node(0, 1, BeepTestNode::new(440.0, -12.0, true));
+
+        for (node_idx, num_inputs, num_outputs) in [(0, 2, 2), (0, 1, 0), (1, 65, 2), (1, 2, 100)] {
+            let mut bad_description = description.clone();
+            bad_description.nodes[node_idx].num_inputs = num_inputs;
+            bad_description.nodes[node_idx].num_outputs = num_outputs;
+
+            assert!(matches!(
+                new_graph.load_description(&bad_description, &registry),
+                Err(LoadGraphError::InvalidPortCount { num_inputs: i, num_outputs: o, .. })
+                    if i == num_inputs && o == num_outputs
+            ));
+
+            // The graph is not modified.
+            assert_eq!(new_graph.nodes().count(), 3);
+        }
+    }
+}
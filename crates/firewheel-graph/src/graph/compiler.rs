@@ -142,12 +142,13 @@ pub fn compile<'a, N>(
     graph_in_id: NodeID,
     graph_out_id: NodeID,
     max_block_frames: usize,
+    silent_when_inputs_silent: impl Fn(&N) -> bool,
 ) -> Result<CompiledSchedule, CompileGraphError> {
     Ok(
         GraphIR::preprocess(nodes, edges, graph_in_id, graph_out_id, max_block_frames)
             .sort_topologically(true)?
             .solve_buffer_requirements()?
-            .merge(),
+            .merge(silent_when_inputs_silent),
     )
 }
 
@@ -412,7 +413,13 @@ impl<'a, N> GraphIR<'a, N> {
     }
 
     /// Merge the GraphIR into a [CompiledSchedule].
-    fn merge(self) -> CompiledSchedule {
+    fn merge(mut self, silent_when_inputs_silent: impl Fn(&N) -> bool) -> CompiledSchedule {
+        for entry in self.schedule.iter_mut() {
+            // Nodes without any inputs can never be skipped.
+            entry.silent_when_inputs_silent = !entry.input_buffers.is_empty()
+                && (silent_when_inputs_silent)(&self.nodes[entry.id.idx].weight);
+        }
+
         CompiledSchedule::new(self.schedule, self.max_num_buffers, self.max_block_frames)
     }
 }
@@ -2,9 +2,13 @@ use arrayvec::ArrayVec;
 use smallvec::SmallVec;
 use std::fmt::Debug;
 
-use firewheel_core::{node::AudioNodeProcessor, SilenceMask};
+use firewheel_core::SilenceMask;
 
 use super::NodeID;
+use crate::{
+    graph::{InBufferReport, OutBufferReport, ScheduleReport, ScheduledNodeReport},
+    processor::ProcessorNode,
+};
 
 /// A [ScheduledNode] is a [Node] that has been assigned buffers
 /// and a place in the schedule.
@@ -17,6 +21,9 @@ pub(super) struct ScheduledNode {
     pub input_buffers: SmallVec<[InBufferAssignment; 4]>,
     /// The assigned output buffers.
     pub output_buffers: SmallVec<[OutBufferAssignment; 4]>,
+
+    /// Whether the node can be skipped when all of its inputs are silent.
+    pub silent_when_inputs_silent: bool,
 }
 
 impl ScheduledNode {
@@ -25,6 +32,7 @@ impl ScheduledNode {
             id,
             input_buffers: SmallVec::new(),
             output_buffers: SmallVec::new(),
+            silent_when_inputs_silent: false,
         }
     }
 }
@@ -128,15 +136,15 @@ pub(super) struct OutBufferAssignment {
 pub struct ScheduleHeapData {
     pub schedule: CompiledSchedule,
     pub nodes_to_remove: Vec<NodeID>,
-    pub removed_node_processors: Vec<(NodeID, Box<dyn AudioNodeProcessor>)>,
-    pub new_node_processors: Vec<(NodeID, Box<dyn AudioNodeProcessor>)>,
+    pub removed_node_processors: Vec<(NodeID, ProcessorNode)>,
+    pub new_node_processors: Vec<(NodeID, ProcessorNode)>,
 }
 
 impl ScheduleHeapData {
     pub fn new(
         schedule: CompiledSchedule,
         nodes_to_remove: Vec<NodeID>,
-        new_node_processors: Vec<(NodeID, Box<dyn AudioNodeProcessor>)>,
+        new_node_processors: Vec<(NodeID, ProcessorNode)>,
     ) -> Self {
         let num_nodes_to_remove = nodes_to_remove.len();
 
@@ -170,6 +178,8 @@ pub struct CompiledSchedule {
     buffer_silence_flags: Vec<bool>,
     num_buffers: usize,
     max_block_frames: usize,
+    /// The number of frames in the previous process cycle.
+    prev_block_frames: usize,
 }
 
 impl Debug for CompiledSchedule {
@@ -203,6 +213,7 @@ impl CompiledSchedule {
             buffer_silence_flags: vec![false; num_buffers],
             num_buffers,
             max_block_frames,
+            prev_block_frames: 0,
         }
     }
 
@@ -210,6 +221,39 @@ impl CompiledSchedule {
         self.max_block_frames
     }
 
+    /// A report of the schedule for debugging purposes.
+    pub fn report(&self) -> ScheduleReport {
+        ScheduleReport {
+            nodes: self
+                .schedule
+                .iter()
+                .map(|n| ScheduledNodeReport {
+                    node_id: n.id,
+                    input_buffers: n
+                        .input_buffers
+                        .iter()
+                        .map(|b| InBufferReport {
+                            buffer_index: b.buffer_index,
+                            generation: b.generation,
+                            should_clear: b.should_clear,
+                        })
+                        .collect(),
+                    output_buffers: n
+                        .output_buffers
+                        .iter()
+                        .map(|b| OutBufferReport {
+                            buffer_index: b.buffer_index,
+                            generation: b.generation,
+                        })
+                        .collect(),
+                    silent_when_inputs_silent: n.silent_when_inputs_silent,
+                })
+                .collect(),
+            num_buffers: self.num_buffers,
+            max_block_frames: self.max_block_frames,
+        }
+    }
+
     pub fn prepare_graph_inputs(
         &mut self,
         frames: usize,
@@ -296,7 +340,14 @@ impl CompiledSchedule {
         let mut inputs: ArrayVec<&[f32], 64> = ArrayVec::new();
         let mut outputs: ArrayVec<&mut [f32], 64> = ArrayVec::new();
 
-        for scheduled_node in self.schedule.iter() {
+        // A buffer which is flagged as silent is only guaranteed to contain
+        // zeros up to the number of frames it was last written with, which
+        // is at least the number of frames in the previous process cycle.
+        let trust_silence_flags = frames <= self.prev_block_frames;
+
+        // The buffers of the graph input node are filled in
+        // `prepare_graph_inputs`, so don't overwrite their silence flags.
+        for scheduled_node in self.schedule.iter().skip(1) {
             let mut in_silence_mask = SilenceMask::NONE_SILENT;
 
             inputs.clear();
@@ -307,7 +358,7 @@ impl CompiledSchedule {
                     buffer_slice_mut(&self.buffers, b.buffer_index, self.max_block_frames, frames);
                 let s = silence_mask_mut(&mut self.buffer_silence_flags, b.buffer_index);
 
-                if b.should_clear {
+                if b.should_clear && !(*s && trust_silence_flags) {
                     buf[..frames].fill(0.0);
                     *s = true;
                 }
@@ -319,6 +370,28 @@ impl CompiledSchedule {
                 inputs.push(buf);
             }
 
+            if scheduled_node.silent_when_inputs_silent
+                && in_silence_mask.all_channels_silent(inputs.len())
+            {
+                // Skip processing this node and mark its outputs as silent.
+                for b in scheduled_node.output_buffers.iter() {
+                    let s = silence_mask_mut(&mut self.buffer_silence_flags, b.buffer_index);
+
+                    if !(*s && trust_silence_flags) {
+                        buffer_slice_mut(
+                            &self.buffers,
+                            b.buffer_index,
+                            self.max_block_frames,
+                            frames,
+                        )
+                        .fill(0.0);
+                        *s = true;
+                    }
+                }
+
+                continue;
+            }
+
             for b in scheduled_node.output_buffers.iter() {
                 outputs.push(buffer_slice_mut(
                     &self.buffers,
@@ -340,6 +413,8 @@ impl CompiledSchedule {
                     out_silence_mask.is_channel_silent(i);
             }
         }
+
+        self.prev_block_frames = frames;
     }
 }
 
@@ -392,7 +467,7 @@ fn silence_mask_mut<'a>(buffer_silence_flags: &'a mut [bool], buffer_index: usiz
 #[cfg(test)]
 mod tests {
     use crate::{
-        basic_nodes::DummyAudioNode,
+        basic_nodes::{DummyAudioNode, VolumeNode},
         graph::{AddEdgeError, AudioGraph, AudioGraphConfig, EdgeID, InPortIdx, OutPortIdx},
     };
 
@@ -597,6 +672,64 @@ mod tests {
         verify_node(node6, &[false], &schedule, &graph);
     }
 
+    #[test]
+    fn skip_nodes_with_silent_inputs() {
+        let mut graph = AudioGraph::new(&AudioGraphConfig {
+            num_graph_inputs: 1,
+            num_graph_outputs: 1,
+            ..Default::default()
+        });
+
+        let graph_in = graph.graph_in_node();
+        let graph_out = graph.graph_out_node();
+        let volume = graph.add_node(1, 1, VolumeNode::new(100.0));
+        let dummy = graph.add_node(1, 1, DummyAudioNode);
+        graph.connect(graph_in, 0, volume, 0, false).unwrap();
+        graph.connect(volume, 0, dummy, 0, false).unwrap();
+        graph.connect(dummy, 0, graph_out, 0, false).unwrap();
+
+        let mut schedule = graph.compile_internal(128).unwrap();
+
+        let mut process = |input_silent: bool| -> (Vec<NodeID>, SilenceMask) {
+            schedule.prepare_graph_inputs(128, 1, |channels| {
+                channels[0].fill(if input_silent { 0.0 } else { 1.0 });
+                SilenceMask(input_silent as u64)
+            });
+
+            let mut processed = Vec::new();
+            schedule.process(128, |node_id, in_silence_mask, _, outputs| {
+                processed.push(node_id);
+
+                for out in outputs.iter_mut() {
+                    out.fill(1.0);
+                }
+
+                if node_id == dummy {
+                    in_silence_mask
+                } else {
+                    SilenceMask::NONE_SILENT
+                }
+            });
+
+            let mut out_silence_mask = SilenceMask::NONE_SILENT;
+            schedule.read_graph_outputs(128, 1, |_, silence_mask| {
+                out_silence_mask = silence_mask;
+            });
+
+            (processed, out_silence_mask)
+        };
+
+        let (processed, out_silence_mask) = process(false);
+        assert_eq!(processed, &[volume, dummy, graph_out]);
+        assert_eq!(out_silence_mask, SilenceMask::NONE_SILENT);
+
+        // The volume node is skipped, and the dummy node (which is not
+        // flagged) sees that its input is silent.
+        let (processed, out_silence_mask) = process(true);
+        assert_eq!(processed, &[dummy, graph_out]);
+        assert_eq!(out_silence_mask, SilenceMask::MONO_SILENT);
+    }
+
     fn verify_node(
         node_id: NodeID,
         in_ports_that_should_clear: &[bool],
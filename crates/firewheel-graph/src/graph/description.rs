@@ -0,0 +1,167 @@
+use std::any::TypeId;
+
+use ahash::AHashMap;
+use firewheel_core::node::AudioNode;
+
+use crate::basic_nodes::{
+    beep_test::BeepTestNode, DelayNode, HardClipNode, LoudnessMeterNode, MeterConfig, MeterNode,
+    MonoToStereoNode, PanLaw, PanNode, SpatializerNode, StereoToMonoNode, StereoWidthNode, SumNode,
+    SvfMode, SvfNode, VolumeNode,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A serializable description of the topology of an [`AudioGraph`],
+/// including the parameters of each node.
+///
+/// [`AudioGraph`]: super::AudioGraph
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GraphDescription {
+    pub nodes: Vec<NodeDescription>,
+    pub edges: Vec<EdgeDescription>,
+}
+
+/// A description of a node in a [`GraphDescription`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeDescription {
+    /// The key of the node type in the [`NodeRegistry`]
+    pub type_key: String,
+    pub num_inputs: u32,
+    pub num_outputs: u32,
+    /// The values of the parameters on this node
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub params: Vec<ParamValue>,
+}
+
+/// The value of a parameter in a [`NodeDescription`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParamValue {
+    /// The ID of the parameter (see [`ParamID`])
+    ///
+    /// [`ParamID`]: firewheel_core::param::info::ParamID
+    pub id: u32,
+    pub value: f32,
+}
+
+/// A reference to a node in a [`GraphDescription`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NodeRef {
+    /// The graph input node
+    GraphIn,
+    /// The graph output node
+    GraphOut,
+    /// The node at the given index in [`GraphDescription::nodes`]
+    Node(u32),
+}
+
+/// A description of an edge in a [`GraphDescription`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EdgeDescription {
+    pub src_node: NodeRef,
+    pub src_port: u32,
+    pub dst_node: NodeRef,
+    pub dst_port: u32,
+}
+
+struct NodeFactory {
+    type_id: TypeId,
+    construct: Box<dyn Fn() -> Box<dyn AudioNode>>,
+}
+
+/// A registry of constructors for each type of node which can appear in
+/// a [`GraphDescription`].
+#[derive(Default)]
+pub struct NodeRegistry {
+    factories: AHashMap<String, NodeFactory>,
+    type_keys: AHashMap<TypeId, String>,
+}
+
+impl NodeRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with all of the built-in nodes registered (except
+    /// for the sampler node, since that is generic over the sample
+    /// resource, and the convolution and HRTF nodes, since their impulse
+    /// responses are not parameters).
+    pub fn with_basic_nodes() -> Self {
+        let mut registry = Self::new();
+
+        registry.register("beep_test", || BeepTestNode::new(440.0, -12.0, true));
+        registry.register("delay", || DelayNode::new(2.0, 0.5));
+        registry.register("hard_clip", || HardClipNode::new(0.0));
+        registry.register("loudness_meter", LoudnessMeterNode::new);
+        registry.register("meter", || MeterNode::new(MeterConfig::default()));
+        registry.register("mono_to_stereo", || MonoToStereoNode);
+        registry.register("pan", || PanNode::new(0.0, PanLaw::default()));
+        registry.register("spatializer", || {
+            SpatializerNode::new(Default::default(), false)
+        });
+        registry.register("stereo_to_mono", || StereoToMonoNode);
+        registry.register("stereo_width", || StereoWidthNode::new(1.0));
+        registry.register("sum", || SumNode);
+        registry.register("svf", || {
+            SvfNode::new(SvfMode::default(), 1_000.0, std::f32::consts::FRAC_1_SQRT_2)
+        });
+        registry.register("volume", || VolumeNode::new(100.0));
+
+        registry
+    }
+
+    /// Register a node type with the given key.
+    ///
+    /// The constructor should create the node with the default values of
+    /// its parameters, since the parameters in the description are set
+    /// after the node is constructed.
+    ///
+    /// If the key or the node type was already registered, then the old
+    /// entry is replaced.
+    pub fn register<N: AudioNode>(
+        &mut self,
+        type_key: impl Into<String>,
+        constructor: impl Fn() -> N + 'static,
+    ) {
+        let type_key: String = type_key.into();
+        let type_id = TypeId::of::<N>();
+
+        if let Some(old_key) = self.type_keys.insert(type_id, type_key.clone()) {
+            self.factories.remove(&old_key);
+        }
+
+        if let Some(old_factory) = self.factories.insert(
+            type_key,
+            NodeFactory {
+                type_id,
+                construct: Box::new(move || Box::new(constructor())),
+            },
+        ) {
+            if old_factory.type_id != type_id {
+                self.type_keys.remove(&old_factory.type_id);
+            }
+        }
+    }
+
+    /// Construct a new node with the given type key.
+    ///
+    /// Returns `None` if no node type is registered with the given key.
+    pub fn construct(&self, type_key: &str) -> Option<Box<dyn AudioNode>> {
+        self.factories.get(type_key).map(|f| (f.construct)())
+    }
+
+    /// The key the type of the given node was registered with.
+    ///
+    /// Returns `None` if the type of the node is not registered.
+    pub fn type_key(&self, node: &dyn AudioNode) -> Option<&str> {
+        self.type_keys
+            .get(&node.as_any().type_id())
+            .map(|k| k.as_str())
+    }
+}
@@ -4,6 +4,8 @@
 use std::error::Error;
 use std::fmt;
 
+use firewheel_core::param::info::ParamID;
+
 use super::{
     compiler::{Edge, EdgeID, InPortIdx, OutPortIdx},
     NodeID,
@@ -148,3 +150,133 @@ impl fmt::Display for CompileGraphError {
         }
     }
 }
+
+/// An error occurred while attempting to access a parameter on a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamError {
+    /// The given node was not found in the graph.
+    NodeNotFound(NodeID),
+    /// The node does not have a parameter with the given ID.
+    ParamNotFound(NodeID, ParamID),
+}
+
+impl Error for ParamError {}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NodeNotFound(node_id) => {
+                write!(f, "Could not find node with ID {:?}", node_id)
+            }
+            Self::ParamNotFound(node_id, param_id) => {
+                write!(
+                    f,
+                    "Node with ID {:?} does not have a parameter with ID {:?}",
+                    node_id, param_id
+                )
+            }
+        }
+    }
+}
+
+/// An error occurred while attempting to export the audio graph to a
+/// [`GraphDescription`].
+///
+/// [`GraphDescription`]: super::GraphDescription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveGraphError {
+    /// The type of the given node was not registered in the node registry.
+    UnregisteredNodeType(NodeID),
+}
+
+impl Error for SaveGraphError {}
+
+impl fmt::Display for SaveGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnregisteredNodeType(node_id) => {
+                write!(
+                    f,
+                    "Could not save audio graph: the type of node with ID {:?} is not registered",
+                    node_id
+                )
+            }
+        }
+    }
+}
+
+/// An error occurred while attempting to import a [`GraphDescription`]
+/// into the audio graph.
+///
+/// [`GraphDescription`]: super::GraphDescription
+#[derive(Debug, Clone)]
+pub enum LoadGraphError {
+    /// No node type is registered with the given key.
+    UnknownNodeType(String),
+    /// An edge referred to a node index which does not exist in the
+    /// description.
+    NodeNotFound(u32),
+    /// A node in the description has a number of input or output ports
+    /// which is not supported by its node type.
+    InvalidPortCount {
+        type_key: String,
+        num_inputs: u32,
+        num_outputs: u32,
+    },
+    /// A parameter on a node could not be set.
+    ParamError(ParamError),
+    /// An edge could not be added to the graph.
+    EdgeError(AddEdgeError),
+}
+
+impl Error for LoadGraphError {}
+
+impl fmt::Display for LoadGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownNodeType(type_key) => {
+                write!(
+                    f,
+                    "Could not load audio graph: no node type is registered with the key \"{}\"",
+                    type_key
+                )
+            }
+            Self::NodeNotFound(idx) => {
+                write!(
+                    f,
+                    "Could not load audio graph: an edge refers to a non-existing node at index {}",
+                    idx
+                )
+            }
+            Self::InvalidPortCount {
+                type_key,
+                num_inputs,
+                num_outputs,
+            } => {
+                write!(
+                    f,
+                    "Could not load audio graph: node type \"{}\" does not support {} inputs and {} outputs",
+                    type_key, num_inputs, num_outputs
+                )
+            }
+            Self::ParamError(e) => {
+                write!(f, "Could not load audio graph: {}", e)
+            }
+            Self::EdgeError(e) => {
+                write!(f, "Could not load audio graph: {}", e)
+            }
+        }
+    }
+}
+
+impl From<ParamError> for LoadGraphError {
+    fn from(e: ParamError) -> Self {
+        Self::ParamError(e)
+    }
+}
+
+impl From<AddEdgeError> for LoadGraphError {
+    fn from(e: AddEdgeError) -> Self {
+        Self::EdgeError(e)
+    }
+}
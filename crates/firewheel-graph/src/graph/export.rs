@@ -0,0 +1,180 @@
+use std::fmt::Write;
+
+use super::{AudioGraph, CompileGraphError, NodeID};
+
+/// A report of a compiled schedule, for debugging purposes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleReport {
+    /// The scheduled nodes in the order they are processed.
+    pub nodes: Vec<ScheduledNodeReport>,
+    /// The total number of buffers used by the schedule.
+    pub num_buffers: usize,
+    /// The maximum number of frames in each buffer.
+    pub max_block_frames: usize,
+}
+
+/// A node in a [`ScheduleReport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledNodeReport {
+    pub node_id: NodeID,
+    /// The buffers assigned to the input ports of the node.
+    pub input_buffers: Vec<InBufferReport>,
+    /// The buffers assigned to the output ports of the node.
+    pub output_buffers: Vec<OutBufferReport>,
+    /// Whether processing of this node is skipped when all of its inputs
+    /// are silent.
+    pub silent_when_inputs_silent: bool,
+}
+
+/// A buffer assigned to an input port in a [`ScheduleReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InBufferReport {
+    /// The index of the buffer
+    pub buffer_index: usize,
+    /// How many times this buffer has been used before this assignment
+    pub generation: usize,
+    /// Whether the buffer is cleared before it is passed to the node
+    /// (because the input port is unconnected)
+    pub should_clear: bool,
+}
+
+/// A buffer assigned to an output port in a [`ScheduleReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutBufferReport {
+    /// The index of the buffer
+    pub buffer_index: usize,
+    /// How many times this buffer has been used before this assignment
+    pub generation: usize,
+}
+
+impl AudioGraph {
+    /// Render the graph in the Graphviz DOT format, for debugging
+    /// purposes.
+    ///
+    /// Each node is labeled with its debug name and ID, and each port is
+    /// drawn as a separate field of the node.
+    pub fn to_dot(&self) -> String {
+        let mut s = String::new();
+
+        // Writing to a `String` cannot fail.
+        let _ = self.write_dot(&mut s);
+
+        s
+    }
+
+    fn write_dot(&self, s: &mut String) -> std::fmt::Result {
+        writeln!(s, "digraph AudioGraph {{")?;
+        writeln!(s, "    rankdir=LR;")?;
+        writeln!(s, "    node [shape=record];")?;
+
+        for node_entry in self.nodes() {
+            write!(s, "    {} [label=\"{{", dot_id(node_entry.id))?;
+
+            if node_entry.num_inputs > 0 {
+                write!(s, "{{")?;
+                for i in 0..node_entry.num_inputs {
+                    if i > 0 {
+                        write!(s, "|")?;
+                    }
+                    write!(s, "<in{i}> in {i}")?;
+                }
+                write!(s, "}}|")?;
+            }
+
+            write!(s, "{:?}", node_entry.id)?;
+
+            if node_entry.num_outputs > 0 {
+                write!(s, "|{{")?;
+                for i in 0..node_entry.num_outputs {
+                    if i > 0 {
+                        write!(s, "|")?;
+                    }
+                    write!(s, "<out{i}> out {i}")?;
+                }
+                write!(s, "}}")?;
+            }
+
+            writeln!(s, "}}\"];")?;
+        }
+
+        for edge in self.edges() {
+            writeln!(
+                s,
+                "    {}:out{} -> {}:in{};",
+                dot_id(edge.src_node),
+                edge.src_port.0,
+                dot_id(edge.dst_node),
+                edge.dst_port.0,
+            )?;
+        }
+
+        writeln!(s, "}}")
+    }
+
+    /// Compile the graph and return a report of the resulting schedule,
+    /// for debugging purposes.
+    ///
+    /// This does not activate any nodes, and it does not affect the
+    /// schedule of an active context.
+    pub fn schedule_report(
+        &mut self,
+        max_block_frames: usize,
+    ) -> Result<ScheduleReport, CompileGraphError> {
+        Ok(self.compile_internal(max_block_frames)?.report())
+    }
+}
+
+fn dot_id(node_id: NodeID) -> String {
+    format!("n{}_{}", node_id.idx.slot(), node_id.idx.generation())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{basic_nodes::VolumeNode, graph::AudioGraphConfig};
+
+    #[test]
+    fn export_dot_and_schedule_report() {
+        let mut graph = AudioGraph::new(&AudioGraphConfig {
+            num_graph_inputs: 1,
+            num_graph_outputs: 2,
+            ..Default::default()
+        });
+        let graph_in = graph.graph_in_node();
+        let graph_out = graph.graph_out_node();
+        let volume = graph.add_node(2, 2, VolumeNode::new(100.0));
+        graph.connect(graph_in, 0, volume, 0, false).unwrap();
+        graph.connect(volume, 0, graph_out, 0, false).unwrap();
+        graph.connect(volume, 1, graph_out, 1, false).unwrap();
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph AudioGraph {"));
+        assert!(dot.contains(&format!(
+            "{} [label=\"{{{{<in0> in 0|<in1> in 1}}|{:?}|{{<out0> out 0|<out1> out 1}}}}\"];",
+            dot_id(volume),
+            volume
+        )));
+        assert!(dot.contains(&format!(
+            "{}:out0 -> {}:in0;",
+            dot_id(graph_in),
+            dot_id(volume)
+        )));
+        assert_eq!(dot.matches("->").count(), 3);
+
+        let report = graph.schedule_report(128).unwrap();
+        assert_eq!(report.max_block_frames, 128);
+        let order: Vec<NodeID> = report.nodes.iter().map(|n| n.node_id).collect();
+        assert_eq!(order, &[graph_in, volume, graph_out]);
+
+        // The second input of the volume node is unconnected.
+        let volume_report = &report.nodes[1];
+        assert!(!volume_report.input_buffers[0].should_clear);
+        assert!(volume_report.input_buffers[1].should_clear);
+        assert_eq!(volume_report.output_buffers.len(), 2);
+        assert!(volume_report.silent_when_inputs_silent);
+        assert_eq!(
+            report.nodes[0].output_buffers[0].buffer_index,
+            volume_report.input_buffers[0].buffer_index
+        );
+    }
+}
@@ -3,5 +3,9 @@ pub mod basic_nodes;
 mod context;
 pub mod graph;
 pub mod processor;
+#[cfg(feature = "profiling")]
+pub mod profiling;
 
-pub use context::{FirewheelGraphCtx, UpdateStatus};
+pub use context::{
+    ActivateError, AutoReconnectConfig, FirewheelCtx, FirewheelGraphCtx, UpdateStatus,
+};
@@ -1,9 +1,19 @@
-use std::any::Any;
+use std::{
+    any::Any,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
+use arrayvec::ArrayVec;
 use thunderdome::Arena;
 
 use crate::graph::{NodeID, ScheduleHeapData};
+#[cfg(feature = "profiling")]
+use crate::profiling::{NodeTimingAccumulator, ProfileReport, Profiler};
 use firewheel_core::{
+    event::{NodeEvent, NodeEventType, ProcessorEvent, ProcessorEventQueue},
     node::{AudioNodeProcessor, ProcInfo, StreamStatus},
     SilenceMask,
 };
@@ -15,8 +25,37 @@ pub enum FirewheelProcessorStatus {
     DropProcessor,
 }
 
+/// A node processor along with its queue of scheduled events.
+pub(crate) struct ProcessorNode {
+    pub processor: Box<dyn AudioNodeProcessor>,
+    /// The scheduled events sorted by time.
+    ///
+    /// This is allocated with a fixed capacity on the main thread.
+    pub events: Vec<NodeEvent>,
+    pub split_at_events: bool,
+    #[cfg(feature = "profiling")]
+    pub timing: NodeTimingAccumulator,
+}
+
+/// An event sent from the context to the processor of a node.
+pub(crate) struct ScheduledEvent {
+    pub node_id: NodeID,
+    pub event: NodeEvent,
+}
+
+/// An event sent from the processor of a node to the main thread.
+#[derive(Debug)]
+pub struct NodeProcessorEvent {
+    /// The ID of the node which sent this event.
+    pub node_id: NodeID,
+    /// The value of the frame clock at the start of the process cycle in
+    /// which this event was sent.
+    pub clock_frame: u64,
+    pub event: ProcessorEvent,
+}
+
 pub struct FirewheelProcessor {
-    nodes: Arena<Box<dyn AudioNodeProcessor>>,
+    nodes: Arena<ProcessorNode>,
     schedule_data: Option<Box<ScheduleHeapData>>,
     user_cx: Option<Box<dyn Any + Send>>,
 
@@ -25,16 +64,33 @@ pub struct FirewheelProcessor {
     // use a different channel type when targeting webassembly.
     from_graph_rx: rtrb::Consumer<ContextToProcessorMsg>,
     to_graph_tx: rtrb::Producer<ProcessorToContextMsg>,
+    from_graph_event_rx: rtrb::Consumer<ScheduledEvent>,
+    to_graph_event_tx: rtrb::Producer<NodeEvent>,
+    to_graph_out_event_tx: rtrb::Producer<NodeProcessorEvent>,
+    out_events: ProcessorEventQueue,
 
     running: bool,
     max_block_frames: usize,
+    sample_rate_recip: f64,
+    clock_frames: u64,
+    shared_clock_frames: Arc<AtomicU64>,
+
+    #[cfg(feature = "profiling")]
+    profiler: Profiler,
 }
 
 impl FirewheelProcessor {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         from_graph_rx: rtrb::Consumer<ContextToProcessorMsg>,
         to_graph_tx: rtrb::Producer<ProcessorToContextMsg>,
+        from_graph_event_rx: rtrb::Consumer<ScheduledEvent>,
+        to_graph_event_tx: rtrb::Producer<NodeEvent>,
+        to_graph_out_event_tx: rtrb::Producer<NodeProcessorEvent>,
+        out_event_capacity: usize,
+        shared_clock_frames: Arc<AtomicU64>,
         node_capacity: usize,
+        sample_rate: u32,
         num_stream_in_channels: usize,
         num_stream_out_channels: usize,
         max_block_frames: usize,
@@ -49,11 +105,26 @@ impl FirewheelProcessor {
             user_cx: Some(user_cx),
             from_graph_rx,
             to_graph_tx,
+            from_graph_event_rx,
+            to_graph_event_tx,
+            to_graph_out_event_tx,
+            out_events: ProcessorEventQueue::with_capacity(out_event_capacity),
             running: true,
             max_block_frames,
+            sample_rate_recip: f64::from(sample_rate).recip(),
+            clock_frames: 0,
+            shared_clock_frames,
+            #[cfg(feature = "profiling")]
+            profiler: Profiler::new(),
         }
     }
 
+    /// Set the (empty) report to fill in with profiling data.
+    #[cfg(feature = "profiling")]
+    pub(crate) fn set_profile_report(&mut self, report: Box<ProfileReport>) {
+        self.profiler.set_report(report);
+    }
+
     /// Process the given buffers of audio data.
     ///
     /// If this returns [`ProcessStatus::DropProcessor`], then this
@@ -85,6 +156,11 @@ impl FirewheelProcessor {
 
         if self.schedule_data.is_none() || frames == 0 {
             output.fill(0.0);
+
+            self.clock_frames += frames as u64;
+            self.shared_clock_frames
+                .store(self.clock_frames, Ordering::Relaxed);
+
             return FirewheelProcessorStatus::Ok;
         };
 
@@ -114,7 +190,11 @@ impl FirewheelProcessor {
                     },
                 );
 
-            self.process_block(block_frames, stream_time_secs, stream_status);
+            self.process_block(
+                block_frames,
+                stream_time_secs + frames_processed as f64 * self.sample_rate_recip,
+                stream_status,
+            );
 
             // Copy the output of the graph to the output buffer.
             self.schedule_data
@@ -157,6 +237,9 @@ impl FirewheelProcessor {
             frames_processed += block_frames;
         }
 
+        self.shared_clock_frames
+            .store(self.clock_frames, Ordering::Relaxed);
+
         if self.running {
             FirewheelProcessorStatus::Ok
         } else {
@@ -164,6 +247,77 @@ impl FirewheelProcessor {
         }
     }
 
+    /// Process a single block of audio as the processor of a
+    /// [`SubGraphNode`] in a parent graph.
+    ///
+    /// [`SubGraphNode`]: crate::basic_nodes::SubGraphNode
+    pub(crate) fn process_sub_graph(
+        &mut self,
+        frames: usize,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        proc_info: ProcInfo,
+    ) {
+        // Keep the clock in sync with the parent graph so that events in
+        // the sub graph can be scheduled using the same clock.
+        self.clock_frames = proc_info.clock_frames;
+
+        // Poll for a new schedule before filling the graph inputs so that
+        // the inputs are not written to the buffers of an old schedule.
+        self.poll_messages();
+
+        if !self.running || self.schedule_data.is_none() {
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+
+            self.clock_frames += frames as u64;
+            self.shared_clock_frames
+                .store(self.clock_frames, Ordering::Relaxed);
+
+            return;
+        }
+
+        // Use the user context of the parent graph.
+        std::mem::swap(self.user_cx.as_mut().unwrap(), proc_info.cx);
+
+        let in_silence_mask = proc_info.in_silence_mask;
+        self.schedule_data
+            .as_mut()
+            .unwrap()
+            .schedule
+            .prepare_graph_inputs(
+                frames,
+                inputs.len(),
+                |channels: &mut [&mut [f32]]| -> SilenceMask {
+                    for (ch, input) in channels.iter_mut().zip(inputs.iter()) {
+                        ch[..frames].copy_from_slice(&input[..frames]);
+                    }
+
+                    in_silence_mask
+                },
+            );
+
+        self.process_block(frames, proc_info.stream_time_secs, proc_info.stream_status);
+
+        std::mem::swap(self.user_cx.as_mut().unwrap(), proc_info.cx);
+
+        if let Some(schedule_data) = &mut self.schedule_data {
+            schedule_data.schedule.read_graph_outputs(
+                frames,
+                outputs.len(),
+                |channels: &[&[f32]], silence_mask| {
+                    for (out, ch) in outputs.iter_mut().zip(channels.iter()) {
+                        out[..frames].copy_from_slice(&ch[..frames]);
+                    }
+
+                    *proc_info.out_silence_mask = silence_mask;
+                },
+            );
+        }
+
+        self.shared_clock_frames
+            .store(self.clock_frames, Ordering::Relaxed);
+    }
+
     fn poll_messages(&mut self) {
         while let Ok(msg) = self.from_graph_rx.pop() {
             match msg {
@@ -192,8 +346,8 @@ impl FirewheelProcessor {
                             .unwrap();
                     }
 
-                    for (node_id, processor) in new_schedule_data.new_node_processors.drain(..) {
-                        assert!(self.nodes.insert_at(node_id.idx, processor).is_none());
+                    for (node_id, node) in new_schedule_data.new_node_processors.drain(..) {
+                        assert!(self.nodes.insert_at(node_id.idx, node).is_none());
                     }
 
                     self.schedule_data = Some(new_schedule_data);
@@ -201,10 +355,46 @@ impl FirewheelProcessor {
                 ContextToProcessorMsg::Stop => {
                     self.running = false;
                 }
+                #[cfg(feature = "profiling")]
+                ContextToProcessorMsg::ProfileReport(report) => {
+                    self.profiler.set_report(report);
+                }
             }
         }
     }
 
+    fn poll_events(&mut self) {
+        while let Ok(scheduled_event) = self.from_graph_event_rx.pop() {
+            let ScheduledEvent { node_id, event } = scheduled_event;
+
+            if !self.nodes.contains(node_id.idx) {
+                // The event may be for a node in a schedule which was sent
+                // after the last time messages were polled.
+                self.poll_messages();
+            }
+
+            let Some(node) = self.nodes.get_mut(node_id.idx) else {
+                return_event(&mut self.to_graph_event_tx, event);
+                continue;
+            };
+
+            if node.events.len() == node.events.capacity() {
+                // Don't allocate in the audio thread.
+                return_event(&mut self.to_graph_event_tx, event);
+                continue;
+            }
+
+            // Keep the events sorted by time. Events with the same time
+            // stay in the order they were sent.
+            let i = node
+                .events
+                .iter()
+                .position(|e| e.clock_frame > event.clock_frame)
+                .unwrap_or(node.events.len());
+            node.events.insert(i, event);
+        }
+    }
+
     fn process_block(
         &mut self,
         block_frames: usize,
@@ -217,11 +407,19 @@ impl FirewheelProcessor {
             return;
         }
 
+        self.poll_events();
+
         let Some(schedule_data) = &mut self.schedule_data else {
             return;
         };
 
+        #[cfg(feature = "profiling")]
+        let block_start = std::time::Instant::now();
+
         let user_cx = self.user_cx.as_mut().unwrap();
+        let clock_frames = self.clock_frames;
+        let block_end = clock_frames + block_frames as u64;
+        let sample_rate_recip = self.sample_rate_recip;
 
         schedule_data.schedule.process(
             block_frames,
@@ -230,21 +428,184 @@ impl FirewheelProcessor {
              inputs: &[&[f32]],
              outputs: &mut [&mut [f32]]|
              -> SilenceMask {
-                let mut out_silence_mask = SilenceMask::NONE_SILENT;
-
-                let proc_info = ProcInfo {
-                    in_silence_mask,
-                    out_silence_mask: &mut out_silence_mask,
-                    stream_time_secs,
-                    stream_status,
-                    cx: user_cx,
+                #[cfg(feature = "profiling")]
+                let node_start = std::time::Instant::now();
+
+                let node = &mut self.nodes[node_id.idx];
+
+                let num_events = node
+                    .events
+                    .iter()
+                    .take_while(|e| e.clock_frame < block_end)
+                    .count();
+
+                // Events which were scheduled in the past occur at the start
+                // of this block.
+                for event in node.events[..num_events].iter_mut() {
+                    event.clock_frame = event.clock_frame.max(clock_frames);
+                }
+
+                // Only let the node push events which can be sent to the
+                // main thread, so that none of them are dropped here.
+                self.out_events
+                    .set_max_len(self.to_graph_out_event_tx.slots());
+
+                let out_silence_mask = if node.split_at_events && num_events > 0 {
+                    process_split_at_events(
+                        node,
+                        num_events,
+                        block_frames,
+                        inputs,
+                        outputs,
+                        in_silence_mask,
+                        clock_frames,
+                        stream_time_secs,
+                        sample_rate_recip,
+                        stream_status,
+                        &mut self.out_events,
+                        user_cx,
+                    )
+                } else {
+                    let mut out_silence_mask = SilenceMask::NONE_SILENT;
+
+                    let proc_info = ProcInfo {
+                        in_silence_mask,
+                        out_silence_mask: &mut out_silence_mask,
+                        stream_time_secs,
+                        stream_status,
+                        clock_frames,
+                        events: &mut node.events[..num_events],
+                        out_events: &mut self.out_events,
+                        cx: user_cx,
+                    };
+
+                    node.processor
+                        .process(block_frames, inputs, outputs, proc_info);
+
+                    out_silence_mask
                 };
 
-                self.nodes[node_id.idx].process(block_frames, inputs, outputs, proc_info);
+                #[cfg(feature = "profiling")]
+                node.timing.add(node_start.elapsed());
+
+                for event in node.events.drain(..num_events) {
+                    return_event(&mut self.to_graph_event_tx, event);
+                }
+
+                for event in self.out_events.drain() {
+                    // The queue was limited to the free slots in the
+                    // channel, so this can't fail.
+                    let _ = self.to_graph_out_event_tx.push(NodeProcessorEvent {
+                        node_id,
+                        clock_frame: clock_frames,
+                        event,
+                    });
+                }
 
                 out_silence_mask
             },
         );
+
+        self.clock_frames = block_end;
+
+        #[cfg(feature = "profiling")]
+        {
+            self.profiler
+                .end_block(block_start, block_frames, sample_rate_recip);
+
+            if let Some(report) = self.profiler.take_report(&mut self.nodes) {
+                if let Err(rtrb::PushError::Full(ProcessorToContextMsg::ProfileReport(report))) =
+                    self.to_graph_tx
+                        .push(ProcessorToContextMsg::ProfileReport(report))
+                {
+                    // Don't deallocate the report in the audio thread.
+                    self.profiler.set_report(report);
+                }
+            }
+        }
+    }
+}
+
+/// Process a node in sub-blocks so that each group of events occurs on the
+/// first frame of a sub-block.
+#[allow(clippy::too_many_arguments)]
+fn process_split_at_events(
+    node: &mut ProcessorNode,
+    num_events: usize,
+    block_frames: usize,
+    inputs: &[&[f32]],
+    outputs: &mut [&mut [f32]],
+    in_silence_mask: SilenceMask,
+    clock_frames: u64,
+    stream_time_secs: f64,
+    sample_rate_recip: f64,
+    stream_status: StreamStatus,
+    out_events: &mut ProcessorEventQueue,
+    user_cx: &mut Box<dyn Any + Send>,
+) -> SilenceMask {
+    // Only mark a channel as silent if it is silent in every sub-block.
+    let mut block_silence_mask = SilenceMask(u64::MAX);
+
+    let mut events_start = 0;
+    let mut sub_start = 0;
+    while sub_start < block_frames {
+        let mut events_end = events_start;
+        while events_end < num_events
+            && node.events[events_end].frame_offset(clock_frames) <= sub_start
+        {
+            events_end += 1;
+        }
+
+        let sub_end = if events_end < num_events {
+            node.events[events_end].frame_offset(clock_frames)
+        } else {
+            block_frames
+        };
+        let sub_frames = sub_end - sub_start;
+
+        let sub_inputs: ArrayVec<&[f32], 64> =
+            inputs.iter().map(|b| &b[sub_start..sub_end]).collect();
+        let mut sub_outputs: ArrayVec<&mut [f32], 64> = outputs
+            .iter_mut()
+            .map(|b| &mut b[sub_start..sub_end])
+            .collect();
+
+        let mut out_silence_mask = SilenceMask::NONE_SILENT;
+
+        let proc_info = ProcInfo {
+            in_silence_mask,
+            out_silence_mask: &mut out_silence_mask,
+            stream_time_secs: stream_time_secs + sub_start as f64 * sample_rate_recip,
+            stream_status,
+            clock_frames: clock_frames + sub_start as u64,
+            events: &mut node.events[events_start..events_end],
+            out_events,
+            cx: user_cx,
+        };
+
+        node.processor.process(
+            sub_frames,
+            sub_inputs.as_slice(),
+            sub_outputs.as_mut_slice(),
+            proc_info,
+        );
+
+        block_silence_mask.0 &= out_silence_mask.0;
+
+        events_start = events_end;
+        sub_start = sub_end;
+    }
+
+    block_silence_mask
+}
+
+/// Send a processed event back to the main thread so that it is not
+/// deallocated in the audio thread.
+fn return_event(to_graph_event_tx: &mut rtrb::Producer<NodeEvent>, event: NodeEvent) {
+    if let NodeEventType::Custom(_) = &event.event {
+        // The context never has more custom events in flight than the
+        // capacity of this channel, so this can't fail.
+        let _ = to_graph_event_tx.push(event);
     }
 }
 
@@ -258,6 +619,8 @@ impl Drop for FirewheelProcessor {
             nodes,
             _schedule_data: self.schedule_data.take(),
             user_cx: self.user_cx.take(),
+            #[cfg(feature = "profiling")]
+            _profile_report: self.profiler.take_unused_report(),
         });
     }
 }
@@ -265,13 +628,280 @@ impl Drop for FirewheelProcessor {
 pub(crate) enum ContextToProcessorMsg {
     NewSchedule(Box<ScheduleHeapData>),
     Stop,
+    /// An empty report to fill in with profiling data.
+    #[cfg(feature = "profiling")]
+    ProfileReport(Box<ProfileReport>),
 }
 
 pub(crate) enum ProcessorToContextMsg {
     ReturnSchedule(Box<ScheduleHeapData>),
     Dropped {
-        nodes: Arena<Box<dyn AudioNodeProcessor>>,
+        nodes: Arena<ProcessorNode>,
         _schedule_data: Option<Box<ScheduleHeapData>>,
         user_cx: Option<Box<dyn Any + Send>>,
+        #[cfg(feature = "profiling")]
+        _profile_report: Option<Box<ProfileReport>>,
     },
+    /// A report filled in with profiling data.
+    #[cfg(feature = "profiling")]
+    ProfileReport(Box<ProfileReport>),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        error::Error,
+        sync::{atomic::AtomicUsize, Arc, Mutex},
+    };
+
+    use firewheel_core::{
+        event::EventTime,
+        node::{AudioNode, AudioNodeInfo},
+    };
+
+    use super::*;
+    use crate::{context::TestGraphCtx, graph::AudioGraphConfig};
+
+    /// Records the `(clock_frames, frames, num_events)` of every process call.
+    struct RecordNode(Arc<Mutex<Vec<(u64, usize, usize)>>>);
+
+    impl AudioNode for RecordNode {
+        fn debug_name(&self) -> &'static str {
+            "record"
+        }
+
+        fn info(&self) -> AudioNodeInfo {
+            AudioNodeInfo {
+                num_min_supported_outputs: 1,
+                num_max_supported_outputs: 1,
+                split_at_events: true,
+                ..Default::default()
+            }
+        }
+
+        fn activate(
+            &mut self,
+            _sample_rate: u32,
+            _max_block_frames: usize,
+            _num_inputs: usize,
+            _num_outputs: usize,
+        ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn Error>> {
+            Ok(Box::new(RecordProcessor(Arc::clone(&self.0))))
+        }
+    }
+
+    struct RecordProcessor(Arc<Mutex<Vec<(u64, usize, usize)>>>);
+
+    impl AudioNodeProcessor for RecordProcessor {
+        fn process(
+            &mut self,
+            frames: usize,
+            _inputs: &[&[f32]],
+            outputs: &mut [&mut [f32]],
+            proc_info: ProcInfo,
+        ) {
+            for event in proc_info.events.iter() {
+                assert_eq!(event.clock_frame, proc_info.clock_frames);
+            }
+
+            self.0
+                .lock()
+                .unwrap()
+                .push((proc_info.clock_frames, frames, proc_info.events.len()));
+
+            if !proc_info.events.is_empty() {
+                proc_info
+                    .out_events
+                    .push(ProcessorEvent::MeterValue {
+                        channel: 0,
+                        value: proc_info.events.len() as f32,
+                    })
+                    .unwrap();
+            }
+
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+        }
+    }
+
+    #[test]
+    fn split_block_at_events_and_send_events_back() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        let (mut test_cx, node) = TestGraphCtx::with_node(
+            AudioGraphConfig::default(),
+            0,
+            1,
+            Box::new(RecordNode(Arc::clone(&calls))) as Box<dyn AudioNode>,
+        );
+
+        for time in [
+            EventTime::ClockFrames(100),
+            EventTime::ClockFrames(10),
+            EventTime::Immediate,
+            EventTime::ClockFrames(10),
+        ] {
+            test_cx
+                .cx
+                .graph
+                .queue_event(node, time, NodeEventType::Play)
+                .unwrap();
+        }
+        test_cx.cx.update();
+
+        let mut output = vec![0.0; 128 * 2];
+        test_cx.process(&[], &mut output);
+
+        assert_eq!(
+            calls.lock().unwrap().as_slice(),
+            &[(0, 10, 1), (10, 54, 2), (64, 36, 0), (100, 28, 1)]
+        );
+        assert_eq!(test_cx.cx.clock_frames(), 128);
+
+        let crate::UpdateStatus::Active { events, .. } = test_cx.cx.update() else {
+            panic!("expected the context to be active");
+        };
+        let values: Vec<(NodeID, u64, f32)> = events
+            .iter()
+            .map(|e| match e.event {
+                ProcessorEvent::MeterValue { value, .. } => (e.node_id, e.clock_frame, value),
+                _ => panic!("unexpected event {:?}", e.event),
+            })
+            .collect();
+        assert_eq!(values, &[(node, 0, 1.0), (node, 0, 2.0), (node, 64, 1.0)]);
+    }
+
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn custom_events_are_not_dropped_in_audio_thread() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        let (mut test_cx, node) = TestGraphCtx::with_node(
+            AudioGraphConfig {
+                event_queue_capacity: 2,
+                ..Default::default()
+            },
+            0,
+            1,
+            Box::new(RecordNode(Arc::clone(&calls))) as Box<dyn AudioNode>,
+        );
+
+        // Queue more events than the return channel can hold, all of which
+        // are processed in the same block.
+        for _ in 0..5 {
+            test_cx
+                .cx
+                .graph
+                .queue_event(
+                    node,
+                    EventTime::ClockFrames(256),
+                    NodeEventType::Custom(Box::new(DropCounter(Arc::clone(&drops)))),
+                )
+                .unwrap();
+        }
+
+        let mut output = vec![0.0; 64 * 2];
+        for _ in 0..8 {
+            test_cx.cx.update();
+
+            let drops_before = drops.load(Ordering::Relaxed);
+            test_cx.process(&[], &mut output);
+            assert_eq!(drops.load(Ordering::Relaxed), drops_before);
+        }
+        test_cx.cx.update();
+
+        let num_events: usize = calls.lock().unwrap().iter().map(|c| c.2).sum();
+        assert_eq!(num_events, 5);
+        assert_eq!(drops.load(Ordering::Relaxed), 5);
+    }
+
+    /// Sends three custom events every block, keeping any which could not
+    /// be sent.
+    struct EmitNode(Arc<AtomicUsize>);
+
+    impl AudioNode for EmitNode {
+        fn debug_name(&self) -> &'static str {
+            "emit"
+        }
+
+        fn info(&self) -> AudioNodeInfo {
+            AudioNodeInfo {
+                num_min_supported_outputs: 1,
+                num_max_supported_outputs: 1,
+                ..Default::default()
+            }
+        }
+
+        fn activate(
+            &mut self,
+            _sample_rate: u32,
+            _max_block_frames: usize,
+            _num_inputs: usize,
+            _num_outputs: usize,
+        ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn Error>> {
+            Ok(Box::new(EmitProcessor {
+                drops: Arc::clone(&self.0),
+                unsent: Vec::with_capacity(16),
+            }))
+        }
+    }
+
+    struct EmitProcessor {
+        drops: Arc<AtomicUsize>,
+        unsent: Vec<ProcessorEvent>,
+    }
+
+    impl AudioNodeProcessor for EmitProcessor {
+        fn process(
+            &mut self,
+            frames: usize,
+            _inputs: &[&[f32]],
+            outputs: &mut [&mut [f32]],
+            proc_info: ProcInfo,
+        ) {
+            for _ in 0..3 {
+                let event = ProcessorEvent::Custom(Box::new(DropCounter(Arc::clone(&self.drops))));
+                if let Err(event) = proc_info.out_events.push(event) {
+                    self.unsent.push(event);
+                }
+            }
+
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+        }
+    }
+
+    #[test]
+    fn out_events_are_not_dropped_in_audio_thread() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        let (mut test_cx, _) = TestGraphCtx::with_node(
+            AudioGraphConfig {
+                event_queue_capacity: 2,
+                ..Default::default()
+            },
+            0,
+            1,
+            Box::new(EmitNode(Arc::clone(&drops))) as Box<dyn AudioNode>,
+        );
+
+        let mut output = vec![0.0; 64 * 2];
+        for _ in 0..2 {
+            test_cx.process(&[], &mut output);
+        }
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+
+        let crate::UpdateStatus::Active { events, .. } = test_cx.cx.update() else {
+            panic!("expected the context to be active");
+        };
+        assert_eq!(events.len(), 2);
+        drop(events);
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+    }
 }
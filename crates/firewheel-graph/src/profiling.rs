@@ -0,0 +1,270 @@
+//! Per-node CPU profiling of the audio graph.
+//!
+//! This module is only available with the `profiling` feature enabled.
+
+use std::time::{Duration, Instant};
+
+use ahash::AHashMap;
+use thunderdome::Arena;
+
+use crate::{graph::NodeID, processor::ProcessorNode};
+
+/// The time spent processing a single node.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct NodeTiming {
+    /// The average time spent processing this node per block.
+    pub average: Duration,
+    /// The maximum time spent processing this node in a single block.
+    pub max: Duration,
+    /// The average time spent processing this node as a fraction of the
+    /// real-time deadline of each block (where `1.0` is the entire
+    /// deadline).
+    pub load: f32,
+}
+
+/// Statistics on the time spent processing the audio graph.
+///
+/// These statistics are aggregated over the period between two
+/// consecutive updates of the context.
+#[derive(Default, Debug, Clone)]
+pub struct DspProfile {
+    /// The total time spent processing the graph as a fraction of the
+    /// real-time deadline (where `1.0` means that all of the available
+    /// time was used).
+    pub load: f32,
+    /// The highest load of a single block.
+    pub peak_load: f32,
+    /// The number of blocks that were processed.
+    pub blocks: u64,
+    /// The number of blocks which took longer to process than their
+    /// real-time deadline.
+    pub overruns: u64,
+    /// The time spent processing each node.
+    ///
+    /// Nodes which were not processed in this period (i.e. because their
+    /// inputs were silent) do not appear here.
+    pub nodes: AHashMap<NodeID, NodeTiming>,
+}
+
+impl DspProfile {
+    /// The time spent processing the given node.
+    pub fn node(&self, node_id: NodeID) -> Option<&NodeTiming> {
+        self.nodes.get(&node_id)
+    }
+
+    /// Update the statistics with a report from the processor.
+    pub(crate) fn update(
+        &mut self,
+        report: &mut ProfileReport,
+        node_id: impl Fn(thunderdome::Index) -> Option<NodeID>,
+    ) {
+        let real_time = report.real_time.as_secs_f64();
+
+        self.load = if real_time > 0.0 {
+            (report.process_time.as_secs_f64() / real_time) as f32
+        } else {
+            0.0
+        };
+        self.peak_load = report.peak_load;
+        self.blocks = report.blocks;
+        self.overruns = report.overruns;
+
+        self.nodes.clear();
+        for (idx, acc) in report.nodes.drain(..) {
+            let Some(node_id) = node_id(idx) else {
+                continue;
+            };
+
+            self.nodes.insert(
+                node_id,
+                NodeTiming {
+                    average: acc.total / acc.blocks.max(1),
+                    max: acc.max,
+                    load: if real_time > 0.0 {
+                        (acc.total.as_secs_f64() / real_time) as f32
+                    } else {
+                        0.0
+                    },
+                },
+            );
+        }
+
+        report.clear();
+    }
+}
+
+/// The timings of a node accumulated in the audio thread.
+#[derive(Default, Debug, Clone, Copy)]
+pub(crate) struct NodeTimingAccumulator {
+    total: Duration,
+    max: Duration,
+    blocks: u32,
+}
+
+impl NodeTimingAccumulator {
+    pub fn add(&mut self, time: Duration) {
+        self.total += time;
+        self.max = self.max.max(time);
+        self.blocks += 1;
+    }
+}
+
+/// A report of the aggregated timings sent from the processor to the
+/// context.
+///
+/// This is allocated in the context and sent to the processor to be
+/// filled in, so that no allocations happen in the audio thread.
+pub(crate) struct ProfileReport {
+    blocks: u64,
+    overruns: u64,
+    process_time: Duration,
+    real_time: Duration,
+    peak_load: f32,
+    nodes: Vec<(thunderdome::Index, NodeTimingAccumulator)>,
+}
+
+impl ProfileReport {
+    pub fn new(node_capacity: usize) -> Self {
+        Self {
+            blocks: 0,
+            overruns: 0,
+            process_time: Duration::ZERO,
+            real_time: Duration::ZERO,
+            peak_load: 0.0,
+            nodes: Vec::with_capacity(node_capacity),
+        }
+    }
+
+    /// Make sure that the report has room for the given number of nodes.
+    pub fn reserve_nodes(&mut self, node_capacity: usize) {
+        self.nodes
+            .reserve(node_capacity.saturating_sub(self.nodes.len()));
+    }
+
+    fn clear(&mut self) {
+        self.blocks = 0;
+        self.overruns = 0;
+        self.process_time = Duration::ZERO;
+        self.real_time = Duration::ZERO;
+        self.peak_load = 0.0;
+        self.nodes.clear();
+    }
+}
+
+/// Accumulates the timings in the audio thread.
+pub(crate) struct Profiler {
+    report: Option<Box<ProfileReport>>,
+    blocks: u64,
+    overruns: u64,
+    process_time: Duration,
+    real_time: Duration,
+    peak_load: f32,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            report: None,
+            blocks: 0,
+            overruns: 0,
+            process_time: Duration::ZERO,
+            real_time: Duration::ZERO,
+            peak_load: 0.0,
+        }
+    }
+
+    /// Set the (empty) report to fill in once there is new data.
+    pub fn set_report(&mut self, report: Box<ProfileReport>) {
+        self.report = Some(report);
+    }
+
+    /// Take the report without filling it in (i.e. to return it to the
+    /// context when the processor is dropped).
+    pub fn take_unused_report(&mut self) -> Option<Box<ProfileReport>> {
+        self.report.take()
+    }
+
+    /// Add the time spent processing a single block.
+    pub fn end_block(&mut self, block_start: Instant, block_frames: usize, sample_rate_recip: f64) {
+        let process_time = block_start.elapsed();
+        let deadline = Duration::from_secs_f64(block_frames as f64 * sample_rate_recip);
+
+        let load = if deadline.is_zero() {
+            0.0
+        } else {
+            (process_time.as_secs_f64() / deadline.as_secs_f64()) as f32
+        };
+
+        self.blocks += 1;
+        if process_time > deadline {
+            self.overruns += 1;
+        }
+        self.process_time += process_time;
+        self.real_time += deadline;
+        self.peak_load = self.peak_load.max(load);
+    }
+
+    /// Fill in the report with the accumulated timings and reset them.
+    ///
+    /// Returns `None` if no report was given by the context or if no
+    /// blocks were processed since the last report.
+    pub fn take_report(&mut self, nodes: &mut Arena<ProcessorNode>) -> Option<Box<ProfileReport>> {
+        if self.blocks == 0 {
+            return None;
+        }
+        let mut report = self.report.take()?;
+
+        report.blocks = self.blocks;
+        report.overruns = self.overruns;
+        report.process_time = self.process_time;
+        report.real_time = self.real_time;
+        report.peak_load = self.peak_load;
+
+        for (idx, node) in nodes.iter_mut() {
+            let timing = std::mem::take(&mut node.timing);
+
+            // Don't allocate in the audio thread.
+            if timing.blocks > 0 && report.nodes.len() < report.nodes.capacity() {
+                report.nodes.push((idx, timing));
+            }
+        }
+
+        self.blocks = 0;
+        self.overruns = 0;
+        self.process_time = Duration::ZERO;
+        self.real_time = Duration::ZERO;
+        self.peak_load = 0.0;
+
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        basic_nodes::beep_test::BeepTestNode, context::TestGraphCtx, graph::AudioGraphConfig,
+    };
+
+    #[test]
+    fn report_node_timings() {
+        let (mut test_cx, beep) = TestGraphCtx::with_node(
+            AudioGraphConfig::default(),
+            0,
+            2,
+            BeepTestNode::new(440.0, -12.0, true),
+        );
+
+        let mut output = vec![0.0; 256 * 2];
+        for _ in 0..2 {
+            test_cx.process(&[], &mut output);
+            let cx = &mut test_cx.cx;
+            cx.update();
+
+            // The first report is sent back after the first block, and the
+            // remaining blocks are accumulated into the next report.
+            assert!(cx.profile().blocks > 0);
+            assert!(cx.profile().load > 0.0);
+            assert!(cx.profile().node(beep).is_some());
+        }
+    }
+}
@@ -0,0 +1,653 @@
+use std::{
+    any::Any,
+    fmt::Debug,
+    fs::File,
+    io::{BufWriter, Seek, Write},
+    path::Path,
+};
+
+use firewheel_core::node::StreamStatus;
+use firewheel_graph::{
+    graph::{AudioGraph, AudioGraphConfig, CompileGraphError},
+    processor::{FirewheelProcessor, FirewheelProcessorStatus, NodeProcessorEvent},
+    FirewheelGraphCtx, UpdateStatus,
+};
+
+/// Any output sample with an absolute value at or below this threshold is
+/// considered silent. (This is equal to `-100` dB.)
+const SILENCE_THRESHOLD: f32 = 0.00001;
+
+/// The configuration of an offline rendering stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OfflineConfig {
+    /// The sample rate to render at.
+    ///
+    /// By default this is set to `48000`.
+    pub sample_rate: u32,
+    /// The number of output channels to render.
+    ///
+    /// By default this is set to `2`.
+    pub num_out_channels: usize,
+    /// The number of frames to process in a single block. Graph updates
+    /// and compiles happen in between blocks.
+    ///
+    /// By default this is set to `512`.
+    pub block_frames: usize,
+}
+
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 48_000,
+            num_out_channels: 2,
+            block_frames: 512,
+        }
+    }
+}
+
+/// How long to render for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderLength {
+    /// Render the given number of frames.
+    Frames(u64),
+    /// Render the given number of seconds.
+    Secs(f64),
+    /// Render until the output of the graph has been silent (all samples
+    /// below `-100` dB) for at least `silence_secs` seconds, or until
+    /// `max_secs` seconds have been rendered, whichever comes first.
+    UntilSilent { silence_secs: f64, max_secs: f64 },
+}
+
+/// The sample format of a rendered WAV file
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavSampleFormat {
+    /// 16 bit signed integer
+    Int16,
+    /// 24 bit signed integer
+    Int24,
+    /// 32 bit float
+    #[default]
+    Float32,
+}
+
+/// Information about a completed render
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderInfo {
+    /// The number of frames that were written.
+    pub frames: u64,
+    /// Whether or not the render ended because the output of the graph
+    /// went silent.
+    pub ended_on_silence: bool,
+}
+
+struct ActiveState {
+    processor: FirewheelProcessor,
+    config: OfflineConfig,
+    out_buffer: Vec<f32>,
+    frames_rendered: u64,
+    events: Vec<NodeProcessorEvent>,
+}
+
+/// A Firewheel context which renders the audio graph faster than
+/// realtime instead of running it on an audio stream.
+pub struct FirewheelOfflineCtx {
+    cx: FirewheelGraphCtx,
+    active_state: Option<ActiveState>,
+}
+
+impl FirewheelOfflineCtx {
+    pub fn new(graph_config: AudioGraphConfig) -> Self {
+        Self {
+            cx: FirewheelGraphCtx::new(graph_config),
+            active_state: None,
+        }
+    }
+
+    pub fn graph(&self) -> &AudioGraph {
+        &self.cx.graph
+    }
+
+    pub fn graph_mut(&mut self) -> &mut AudioGraph {
+        &mut self.cx.graph
+    }
+
+    /// Activate the context for rendering.
+    ///
+    /// Returns an error if the context is already active.
+    pub fn activate(
+        &mut self,
+        config: OfflineConfig,
+        user_cx: Option<Box<dyn Any + Send>>,
+    ) -> Result<(), (OfflineActivateError, Option<Box<dyn Any + Send>>)> {
+        if self.cx.is_activated() {
+            return Err((OfflineActivateError::AlreadyActivated, user_cx));
+        }
+
+        if config.sample_rate == 0 {
+            return Err((OfflineActivateError::InvalidSampleRate, user_cx));
+        }
+        if config.num_out_channels == 0 || config.num_out_channels > 64 {
+            return Err((
+                OfflineActivateError::InvalidNumChannels(config.num_out_channels),
+                user_cx,
+            ));
+        }
+        if config.block_frames == 0 {
+            return Err((OfflineActivateError::InvalidBlockFrames, user_cx));
+        }
+
+        let user_cx = user_cx.unwrap_or(Box::new(()));
+
+        let processor = self
+            .cx
+            .activate(
+                config.sample_rate,
+                0,
+                config.num_out_channels,
+                config.block_frames,
+                user_cx,
+            )
+            .unwrap();
+
+        self.active_state = Some(ActiveState {
+            processor,
+            config,
+            out_buffer: vec![0.0; config.block_frames * config.num_out_channels],
+            frames_rendered: 0,
+            events: Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    /// Returns whether or not this context is currently activated.
+    pub fn is_activated(&self) -> bool {
+        self.cx.is_activated()
+    }
+
+    /// Get the configuration of the rendering stream.
+    ///
+    /// Returns `None` if the context is not currently activated.
+    pub fn config(&self) -> Option<&OfflineConfig> {
+        self.active_state.as_ref().map(|s| &s.config)
+    }
+
+    /// The total number of frames that have been rendered since the
+    /// context was activated.
+    ///
+    /// Returns `None` if the context is not currently activated.
+    pub fn frames_rendered(&self) -> Option<u64> {
+        self.active_state.as_ref().map(|s| s.frames_rendered)
+    }
+
+    /// The events which were sent from node processors before the last call
+    /// to [`FirewheelOfflineCtx::process_block`] (i.e. while the previous
+    /// block was rendered).
+    ///
+    /// Returns an empty slice if the context is not currently activated.
+    pub fn events(&self) -> &[NodeProcessorEvent] {
+        self.active_state
+            .as_ref()
+            .map(|s| s.events.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Update the graph and then render a single block of audio.
+    ///
+    /// `frames` will be clamped to [`OfflineConfig::block_frames`].
+    ///
+    /// On success, this returns the rendered interleaved output of the
+    /// graph.
+    pub fn process_block(&mut self, frames: usize) -> Result<&[f32], RenderError> {
+        if self.active_state.is_none() {
+            return Err(RenderError::NotActivated);
+        }
+
+        match self.cx.update() {
+            UpdateStatus::Active {
+                graph_error,
+                events,
+            } => {
+                self.active_state.as_mut().unwrap().events = events;
+
+                if let Some(e) = graph_error {
+                    return Err(RenderError::CompileGraphError(e));
+                }
+            }
+            _ => {
+                self.active_state = None;
+                return Err(RenderError::ProcessorStopped);
+            }
+        }
+
+        let state = self.active_state.as_mut().unwrap();
+
+        let frames = frames.min(state.config.block_frames);
+        let num_out_channels = state.config.num_out_channels;
+        let output = &mut state.out_buffer[..frames * num_out_channels];
+
+        let stream_time_secs = state.frames_rendered as f64 / f64::from(state.config.sample_rate);
+
+        let status = state.processor.process_interleaved(
+            &[],
+            output,
+            0,
+            num_out_channels,
+            frames,
+            stream_time_secs,
+            StreamStatus::empty(),
+        );
+
+        if let FirewheelProcessorStatus::DropProcessor = status {
+            self.active_state = None;
+            return Err(RenderError::ProcessorStopped);
+        }
+
+        let state = self.active_state.as_mut().unwrap();
+        state.frames_rendered += frames as u64;
+
+        Ok(&state.out_buffer[..frames * num_out_channels])
+    }
+
+    /// Render the graph into a WAV file at the given path.
+    pub fn render_to_wav_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        length: RenderLength,
+        format: WavSampleFormat,
+    ) -> Result<RenderInfo, RenderError> {
+        let file = BufWriter::new(File::create(path)?);
+        self.render_to_wav(file, length, format)
+    }
+
+    /// Render the graph into the given writer in the WAV format.
+    pub fn render_to_wav<W: Write + Seek>(
+        &mut self,
+        writer: W,
+        length: RenderLength,
+        format: WavSampleFormat,
+    ) -> Result<RenderInfo, RenderError> {
+        self.render_to_wav_with(writer, length, format, |_, _| {})
+    }
+
+    /// Render the graph into the given writer in the WAV format.
+    ///
+    /// `on_block` is called before every block is rendered with the audio
+    /// graph and the number of frames rendered so far. Any changes made to
+    /// the graph inside of this callback will take effect at the start of
+    /// that block.
+    pub fn render_to_wav_with<W: Write + Seek>(
+        &mut self,
+        writer: W,
+        length: RenderLength,
+        format: WavSampleFormat,
+        mut on_block: impl FnMut(&mut AudioGraph, u64),
+    ) -> Result<RenderInfo, RenderError> {
+        let Some(state) = &self.active_state else {
+            return Err(RenderError::NotActivated);
+        };
+
+        let config = state.config;
+        let sample_rate = f64::from(config.sample_rate);
+
+        let spec = hound::WavSpec {
+            channels: config.num_out_channels as u16,
+            sample_rate: config.sample_rate,
+            bits_per_sample: match format {
+                WavSampleFormat::Int16 => 16,
+                WavSampleFormat::Int24 => 24,
+                WavSampleFormat::Float32 => 32,
+            },
+            sample_format: match format {
+                WavSampleFormat::Float32 => hound::SampleFormat::Float,
+                _ => hound::SampleFormat::Int,
+            },
+        };
+
+        let mut wav_writer = hound::WavWriter::new(writer, spec)?;
+
+        let (max_frames, silence_frames) = match length {
+            RenderLength::Frames(frames) => (frames, None),
+            RenderLength::Secs(secs) => ((secs.max(0.0) * sample_rate).round() as u64, None),
+            RenderLength::UntilSilent {
+                silence_secs,
+                max_secs,
+            } => (
+                (max_secs.max(0.0) * sample_rate).round() as u64,
+                Some((silence_secs.max(0.0) * sample_rate).round() as u64),
+            ),
+        };
+
+        let mut frames_written: u64 = 0;
+        let mut silent_frames: u64 = 0;
+        let mut ended_on_silence = false;
+
+        while frames_written < max_frames {
+            let frames_left = max_frames - frames_written;
+            let frames = if frames_left < config.block_frames as u64 {
+                frames_left as usize
+            } else {
+                config.block_frames
+            };
+
+            let frames_rendered = self.frames_rendered().unwrap_or(0);
+            on_block(&mut self.cx.graph, frames_rendered);
+
+            let output = self.process_block(frames)?;
+
+            match format {
+                WavSampleFormat::Int16 => {
+                    for &s in output.iter() {
+                        wav_writer
+                            .write_sample((s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)?;
+                    }
+                }
+                WavSampleFormat::Int24 => {
+                    const I24_MAX: f32 = 8_388_607.0;
+
+                    for &s in output.iter() {
+                        wav_writer.write_sample((s.clamp(-1.0, 1.0) * I24_MAX).round() as i32)?;
+                    }
+                }
+                WavSampleFormat::Float32 => {
+                    for &s in output.iter() {
+                        wav_writer.write_sample(s)?;
+                    }
+                }
+            }
+
+            frames_written += frames as u64;
+
+            if let Some(silence_frames) = silence_frames {
+                if output.iter().all(|s| s.abs() <= SILENCE_THRESHOLD) {
+                    silent_frames += frames as u64;
+                } else {
+                    silent_frames = 0;
+                }
+
+                if silent_frames >= silence_frames {
+                    ended_on_silence = true;
+                    break;
+                }
+            }
+        }
+
+        wav_writer.finalize()?;
+
+        Ok(RenderInfo {
+            frames: frames_written,
+            ended_on_silence,
+        })
+    }
+
+    /// Deactivate the firewheel context.
+    ///
+    /// If the context is already deactivated, then this will do
+    /// nothing and return `None`.
+    pub fn deactivate(&mut self) -> Option<Box<dyn Any + Send>> {
+        if self.cx.is_activated() {
+            // Drop the processor first so the context does not need to
+            // wait for it to be stopped.
+            self.active_state = None;
+            self.cx.deactivate(false)
+        } else {
+            None
+        }
+    }
+}
+
+// Implement Debug so `unwrap()` can be used.
+impl Debug for FirewheelOfflineCtx {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FirewheelOfflineCtx")
+    }
+}
+
+impl Drop for FirewheelOfflineCtx {
+    fn drop(&mut self) {
+        self.deactivate();
+    }
+}
+
+/// An error occured while trying to activate a [`FirewheelOfflineCtx`]
+#[derive(Debug, thiserror::Error)]
+pub enum OfflineActivateError {
+    #[error("The firewheel context is already activated")]
+    AlreadyActivated,
+    #[error("The sample rate cannot be 0")]
+    InvalidSampleRate,
+    #[error("The number of output channels must be in the range [1, 64], got {0}")]
+    InvalidNumChannels(usize),
+    #[error("The number of block frames cannot be 0")]
+    InvalidBlockFrames,
+}
+
+/// An error occured while rendering with a [`FirewheelOfflineCtx`]
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("The firewheel context is not activated")]
+    NotActivated,
+    #[error("The firewheel processor was stopped")]
+    ProcessorStopped,
+    #[error("{0}")]
+    CompileGraphError(#[from] CompileGraphError),
+    #[error("Failed to write WAV file: {0}")]
+    WavError(#[from] hound::Error),
+    #[error("Failed to create file: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use std::error::Error;
+
+    use firewheel_core::{
+        event::ProcessorEvent,
+        node::{AudioNode, AudioNodeInfo, AudioNodeProcessor, ProcInfo},
+    };
+    use firewheel_graph::basic_nodes::{beep_test::BeepTestNode, LoudnessMeterNode};
+
+    use super::*;
+
+    /// Sends the frame clock at the start of every block as an event.
+    struct ClockEventNode;
+
+    impl AudioNode for ClockEventNode {
+        fn debug_name(&self) -> &'static str {
+            "clock_event"
+        }
+
+        fn info(&self) -> AudioNodeInfo {
+            AudioNodeInfo {
+                num_min_supported_outputs: 1,
+                num_max_supported_outputs: 1,
+                ..Default::default()
+            }
+        }
+
+        fn activate(
+            &mut self,
+            _sample_rate: u32,
+            _max_block_frames: usize,
+            _num_inputs: usize,
+            _num_outputs: usize,
+        ) -> Result<Box<dyn AudioNodeProcessor>, Box<dyn Error>> {
+            Ok(Box::new(ClockEventProcessor))
+        }
+    }
+
+    struct ClockEventProcessor;
+
+    impl AudioNodeProcessor for ClockEventProcessor {
+        fn process(
+            &mut self,
+            frames: usize,
+            _inputs: &[&[f32]],
+            outputs: &mut [&mut [f32]],
+            proc_info: ProcInfo,
+        ) {
+            let _ = proc_info.out_events.push(ProcessorEvent::MeterValue {
+                channel: 0,
+                value: proc_info.clock_frames as f32,
+            });
+
+            firewheel_core::util::clear_all_outputs(frames, outputs, proc_info.out_silence_mask);
+        }
+    }
+
+    fn beep_test_ctx(enabled: bool) -> FirewheelOfflineCtx {
+        let mut cx = FirewheelOfflineCtx::new(Default::default());
+
+        let graph = cx.graph_mut();
+        let beep_test_node = graph.add_node(0, 2, BeepTestNode::new(440.0, -12.0, enabled));
+        graph
+            .connect(beep_test_node, 0, graph.graph_out_node(), 0, false)
+            .unwrap();
+        graph
+            .connect(beep_test_node, 1, graph.graph_out_node(), 1, false)
+            .unwrap();
+
+        cx.activate(Default::default(), None).unwrap();
+
+        cx
+    }
+
+    #[test]
+    fn render_beep_to_wav() {
+        let mut cx = beep_test_ctx(true);
+
+        let mut data = Cursor::new(Vec::new());
+        let info = cx
+            .render_to_wav(
+                &mut data,
+                RenderLength::Frames(1000),
+                WavSampleFormat::Int16,
+            )
+            .unwrap();
+
+        assert_eq!(info.frames, 1000);
+        assert!(!info.ended_on_silence);
+
+        data.set_position(0);
+        let reader = hound::WavReader::new(data).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.spec().sample_rate, 48_000);
+        assert_eq!(reader.duration(), 1000);
+
+        let samples: Vec<i16> = reader.into_samples().map(|s| s.unwrap()).collect();
+        assert!(samples.iter().any(|&s| s != 0));
+    }
+
+    #[test]
+    fn render_until_silent() {
+        let mut cx = beep_test_ctx(false);
+
+        let info = cx
+            .render_to_wav(
+                Cursor::new(Vec::new()),
+                RenderLength::UntilSilent {
+                    silence_secs: 0.1,
+                    max_secs: 10.0,
+                },
+                WavSampleFormat::Float32,
+            )
+            .unwrap();
+
+        assert!(info.ended_on_silence);
+        assert!(info.frames < 48_000);
+    }
+
+    #[test]
+    fn render_is_deterministic() {
+        let render = || {
+            let mut cx = beep_test_ctx(true);
+            let mut data = Cursor::new(Vec::new());
+            cx.render_to_wav(
+                &mut data,
+                RenderLength::Secs(0.05),
+                WavSampleFormat::Float32,
+            )
+            .unwrap();
+            data.into_inner()
+        };
+
+        assert_eq!(render(), render());
+    }
+
+    #[test]
+    fn measure_loudness_of_render() {
+        // A stereo 1 kHz sine wave at -23 dBFS measures -23 LUFS.
+        let mut cx = FirewheelOfflineCtx::new(Default::default());
+
+        let graph = cx.graph_mut();
+        let beep_test_node = graph.add_node(0, 2, BeepTestNode::new(1_000.0, -23.0, true));
+        let meter_node = graph.add_node(2, 2, LoudnessMeterNode::new());
+        for ch in 0..2 {
+            graph
+                .connect(beep_test_node, ch, meter_node, ch, false)
+                .unwrap();
+            graph
+                .connect(meter_node, ch, graph.graph_out_node(), ch, false)
+                .unwrap();
+        }
+
+        cx.activate(Default::default(), None).unwrap();
+
+        cx.render_to_wav(
+            Cursor::new(Vec::new()),
+            RenderLength::Secs(5.0),
+            WavSampleFormat::Float32,
+        )
+        .unwrap();
+
+        let meter = cx
+            .graph()
+            .node(meter_node)
+            .unwrap()
+            .downcast_ref::<LoudnessMeterNode>()
+            .unwrap();
+
+        assert!((meter.momentary_lufs() + 23.0).abs() < 0.1);
+        assert!((meter.short_term_lufs() + 23.0).abs() < 0.1);
+        assert!((meter.integrated_lufs() + 23.0).abs() < 0.1);
+        assert!(meter.loudness_range_lu() < 0.5);
+        assert!((meter.true_peak_dbtp(0) + 23.0).abs() < 0.1);
+        assert!((meter.max_true_peak_dbtp() + 23.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn process_block_keeps_events() {
+        let mut cx = FirewheelOfflineCtx::new(Default::default());
+
+        let graph = cx.graph_mut();
+        let node = graph.add_node(0, 1, Box::new(ClockEventNode) as Box<dyn AudioNode>);
+        graph
+            .connect(node, 0, graph.graph_out_node(), 0, false)
+            .unwrap();
+
+        cx.activate(Default::default(), None).unwrap();
+
+        cx.process_block(100).unwrap();
+        assert!(cx.events().is_empty());
+
+        // The events sent while rendering the previous block are collected
+        // when the graph is updated.
+        cx.process_block(100).unwrap();
+        let events: Vec<(u64, f32)> = cx
+            .events()
+            .iter()
+            .map(|e| match e.event {
+                ProcessorEvent::MeterValue { value, .. } => (e.clock_frame, value),
+                _ => panic!("unexpected event {:?}", e.event),
+            })
+            .collect();
+        assert_eq!(events, &[(0, 0.0)]);
+        assert_eq!(cx.events()[0].node_id, node);
+
+        cx.process_block(100).unwrap();
+        assert_eq!(cx.events().len(), 1);
+        assert_eq!(cx.events()[0].clock_frame, 100);
+    }
+}
@@ -1,6 +1,6 @@
 use std::time::{Duration, Instant};
 
-use firewheel::{basic_nodes::beep_test::BeepTestNode, FirewheelCtx, UpdateStatus};
+use firewheel::{basic_nodes::beep_test::BeepTestNode, FirewheelCpalCtx, UpdateStatus};
 
 const BEEP_FREQUENCY_HZ: f32 = 440.0;
 const BEEP_GAIN_DB: f32 = -12.0;
@@ -12,7 +12,7 @@ fn main() {
 
     println!("Firewheel beep test...");
 
-    let mut cx = FirewheelCtx::new(Default::default());
+    let mut cx = FirewheelCpalCtx::new(Default::default());
 
     let graph = cx.graph_mut();
     let beep_test_node = graph.add_node(
@@ -27,7 +27,7 @@ fn main() {
         .connect(beep_test_node, 1, graph.graph_out_node(), 1, false)
         .unwrap();
 
-    cx.activate(None, true, None).unwrap();
+    cx.activate(Default::default(), None).unwrap();
 
     let start = Instant::now();
     while start.elapsed() < BEEP_DURATION {
@@ -35,11 +35,14 @@ fn main() {
 
         match cx.update() {
             UpdateStatus::Inactive => {}
-            UpdateStatus::Active { graph_error } => {
+            UpdateStatus::Active { graph_error, .. } => {
                 if let Some(e) = graph_error {
                     log::error!("graph error: {}", e);
                 }
             }
+            UpdateStatus::Reconnected { .. }
+            | UpdateStatus::ReconnectFailed { .. }
+            | UpdateStatus::Reconnecting => {}
             UpdateStatus::Deactivated { error, .. } => {
                 log::error!("Deactivated unexpectedly: {:?}", error);
 
@@ -5,7 +5,7 @@ use firewheel::{
     },
     graph::{AddEdgeError, AudioGraph, NodeID},
     node::AudioNode,
-    FirewheelCtx, UpdateStatus,
+    FirewheelCpalCtx, UpdateStatus,
 };
 
 use crate::ui::GuiAudioNode;
@@ -24,13 +24,14 @@ pub enum NodeType {
 }
 
 pub struct AudioSystem {
-    cx: FirewheelCtx,
+    cx: FirewheelCpalCtx,
 }
 
 impl AudioSystem {
     pub fn new() -> Self {
-        let mut cx = FirewheelCtx::new(Default::default());
-        cx.activate(None, true, None).unwrap();
+        let mut cx = FirewheelCpalCtx::new(Default::default());
+        cx.set_auto_reconnect(Some(Default::default()));
+        cx.activate(Default::default(), None).unwrap();
 
         Self { cx }
     }
@@ -115,12 +116,22 @@ impl AudioSystem {
 
     pub fn update(&mut self) {
         match self.cx.update() {
-            UpdateStatus::Inactive => {}
-            UpdateStatus::Active { graph_error } => {
+            UpdateStatus::Inactive | UpdateStatus::Reconnecting => {}
+            UpdateStatus::Active { graph_error, .. } => {
                 if let Some(e) = graph_error {
                     log::error!("audio graph error: {}", e);
                 }
             }
+            UpdateStatus::Reconnected { stream_error } => {
+                if let Some(e) = stream_error {
+                    log::warn!("Stream reconnected after error: {}", e);
+                } else {
+                    log::info!("Stream reconnected to new default device");
+                }
+            }
+            UpdateStatus::ReconnectFailed { error } => {
+                log::error!("Failed to reconnect stream: {}", error);
+            }
             UpdateStatus::Deactivated { error, .. } => {
                 if let Some(e) = error {
                     log::error!("Stream disconnected: {}", e);
@@ -136,13 +147,8 @@ impl AudioSystem {
     }
 
     pub fn set_volume(&mut self, node_id: NodeID, percent_volume: f32) {
-        let volume_node = self
-            .graph_mut()
-            .node_mut(node_id)
-            .unwrap()
-            .downcast_mut::<VolumeNode>()
+        self.graph_mut()
+            .set_param(node_id, VolumeNode::PARAM_VOLUME, percent_volume)
             .unwrap();
-
-        volume_node.set_percent_volume(percent_volume);
     }
 }
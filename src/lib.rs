@@ -4,5 +4,8 @@ pub use firewheel_graph::*;
 #[cfg(feature = "cpal")]
 pub use firewheel_cpal::*;
 
-#[cfg(feature = "cpal")]
-pub type FirewheelCtx = firewheel_cpal::FirewheelCpalCtx;
+#[cfg(feature = "dummy")]
+pub use firewheel_dummy::*;
+
+#[cfg(feature = "offline")]
+pub use firewheel_offline::*;